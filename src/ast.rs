@@ -1,11 +1,26 @@
+use crate::bigint;
+use crate::environment;
+use crate::lexer;
 use crate::operator;
+use crate::token;
+use std::rc::Rc;
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Program {
     pub statements: Vec<Statement>,
+    /// 静的解決したときのグローバル環境のスロット番号順の名前 (静的解決していなければ None)
+    pub globals: Option<Vec<String>>,
+}
+
+/// 位置情報付きの文
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: token::Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Statement {
+pub enum StatementKind {
     Let {
         identifier: Expression,
         value: Expression,
@@ -15,11 +30,22 @@ pub enum Statement {
     Block(Vec<Statement>),
 }
 
+/// 位置情報付きの式
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expression {
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: token::Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionKind {
     Illegal,
-    Identifier(String),
-    Integer(i32),
+    /// 識別子 (静的解決の後は変数の場所が入る)
+    Identifier(String, Option<Resolution>),
+    Integer(i64),
+    BigInt(bigint::BigInt), // i64 に収まらない整数リテラル
+    Float(f64),
+    String(String),
     Boolean(bool),
     PrefixExpression {
         operator: operator::Prefix,
@@ -38,17 +64,33 @@ pub enum Expression {
     Function {
         parameters: Vec<Expression>,
         body: Box<Statement>,
+        /// 静的解決の後に入る、関数のスコープのスロット番号順の名前
+        slots: Option<Rc<environment::SlotNames>>,
     },
     Call {
         function: Box<Expression>,
         args: Vec<Expression>,
     },
+    Array(Vec<Expression>),
+    Hash(Vec<(Expression, Expression)>),
+    Index {
+        left: Box<Expression>,
+        index: Box<Expression>,
+    },
+}
+
+/// 静的解決で求めた変数の場所 (depth 個外側の環境の slot 番目)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resolution {
+    pub depth: usize,
+    pub slot: usize,
 }
 
 impl Program {
     pub fn new() -> Self {
         Program {
             statements: Vec::new(),
+            globals: None,
         }
     }
     pub fn to_code(&self) -> String {
@@ -59,29 +101,54 @@ impl Program {
         }
         code
     }
+
+    /// 構文木を1行1ノードで字下げして表示する (REPL の :ast 向け)
+    ///
+    /// ```text
+    /// Let 1:1
+    ///   Identifier x 1:5
+    ///   Integer 5 1:9
+    /// ```
+    pub fn to_tree(&self) -> String {
+        let mut tree = String::new();
+        for statement in &self.statements {
+            statement.write_tree(0, &mut tree);
+        }
+        tree
+    }
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Program::new()
+    }
 }
 
 impl Statement {
+    pub fn new(kind: StatementKind, span: token::Span) -> Self {
+        Statement { kind, span }
+    }
+
     pub fn to_code(&self) -> String {
         let mut code = "".to_string();
-        match self {
-            Statement::Let { identifier, value } => {
+        match &self.kind {
+            StatementKind::Let { identifier, value } => {
                 code.push_str("let ");
                 code.push_str(identifier.to_code().as_str());
                 code.push_str(" = ");
                 code.push_str(value.to_code().as_str());
                 code.push(';');
             }
-            Statement::Return(expression) => {
+            StatementKind::Return(expression) => {
                 code.push_str("return ");
                 code.push_str(expression.to_code().as_str());
                 code.push(';');
             }
-            Statement::Expression(expression) => {
+            StatementKind::Expression(expression) => {
                 code.push_str(expression.to_code().as_str());
                 code.push(';');
             }
-            Statement::Block(statements) => {
+            StatementKind::Block(statements) => {
                 code.push('{');
                 for statement in statements {
                     code.push('\n');
@@ -93,22 +160,53 @@ impl Statement {
         }
         code
     }
+
+    fn write_tree(&self, depth: usize, tree: &mut String) {
+        match &self.kind {
+            StatementKind::Let { identifier, value } => {
+                write_tree_line(tree, depth, "Let", self.span);
+                identifier.write_tree(depth + 1, tree);
+                value.write_tree(depth + 1, tree);
+            }
+            StatementKind::Return(expression) => {
+                write_tree_line(tree, depth, "Return", self.span);
+                expression.write_tree(depth + 1, tree);
+            }
+            StatementKind::Expression(expression) => {
+                write_tree_line(tree, depth, "ExpressionStatement", self.span);
+                expression.write_tree(depth + 1, tree);
+            }
+            StatementKind::Block(statements) => {
+                write_tree_line(tree, depth, "Block", self.span);
+                for statement in statements {
+                    statement.write_tree(depth + 1, tree);
+                }
+            }
+        }
+    }
 }
 
 impl Expression {
+    pub fn new(kind: ExpressionKind, span: token::Span) -> Self {
+        Expression { kind, span }
+    }
+
     pub fn to_code(&self) -> String {
-        match self {
-            Expression::Identifier(identifier) => identifier.to_string(),
-            Expression::Integer(integer) => integer.to_string(),
-            Expression::Boolean(boolean) => match boolean {
+        match &self.kind {
+            ExpressionKind::Identifier(identifier, _) => identifier.to_string(),
+            ExpressionKind::Integer(integer) => integer.to_string(),
+            ExpressionKind::BigInt(bigint) => bigint.to_string(),
+            ExpressionKind::Float(float) => format!("{:?}", float),
+            ExpressionKind::String(string) => lexer::quote_string(string),
+            ExpressionKind::Boolean(boolean) => match boolean {
                 true => "true".to_string(),
                 false => "false".to_string(),
             },
-            Expression::PrefixExpression {
+            ExpressionKind::PrefixExpression {
                 operator,
                 expression,
             } => "(".to_string() + &operator.to_code() + &expression.to_code() + ")",
-            Expression::InfixExpression {
+            ExpressionKind::InfixExpression {
                 left,
                 operator,
                 right,
@@ -121,7 +219,7 @@ impl Expression {
                     + &right.to_code()
                     + ")"
             }
-            Expression::IfExpression {
+            ExpressionKind::IfExpression {
                 condition,
                 consequence,
                 alternative,
@@ -138,7 +236,9 @@ impl Expression {
                     + " "
                     + &alternative
             }
-            Expression::Function { parameters, body } => {
+            ExpressionKind::Function {
+                parameters, body, ..
+            } => {
                 let param_list = parameters
                     .iter()
                     .map(|param| param.to_code())
@@ -146,29 +246,149 @@ impl Expression {
                 let mut code = "".to_string();
                 code.push_str("fn(");
                 code.push_str(param_list.join(", ").as_str());
-                code.push_str(")");
+                code.push(')');
                 code.push_str(&body.to_code());
 
                 code
             }
-            Expression::Call { function, args } => {
+            ExpressionKind::Call { function, args } => {
                 let args_list = args
                     .iter()
                     .map(|arg| arg.to_code())
                     .collect::<Vec<String>>();
                 let mut code = "".to_string();
                 code.push_str(&function.to_code());
-                code.push_str("(");
+                code.push('(');
                 code.push_str(args_list.join(", ").as_str());
-                code.push_str(")");
+                code.push(')');
 
                 code
             }
-            Expression::Illegal => "[illegal expression]".to_string(),
+            ExpressionKind::Array(elements) => {
+                let element_list = elements
+                    .iter()
+                    .map(|element| element.to_code())
+                    .collect::<Vec<String>>();
+                "[".to_string() + &element_list.join(", ") + "]"
+            }
+            ExpressionKind::Hash(pairs) => {
+                let pair_list = pairs
+                    .iter()
+                    .map(|(key, value)| key.to_code() + ": " + &value.to_code())
+                    .collect::<Vec<String>>();
+                "{".to_string() + &pair_list.join(", ") + "}"
+            }
+            ExpressionKind::Index { left, index } => {
+                "(".to_string() + &left.to_code() + "[" + &index.to_code() + "])"
+            }
+            ExpressionKind::Illegal => "[illegal expression]".to_string(),
+        }
+    }
+
+    fn write_tree(&self, depth: usize, tree: &mut String) {
+        match &self.kind {
+            ExpressionKind::Illegal => write_tree_line(tree, depth, "Illegal", self.span),
+            ExpressionKind::Identifier(identifier, _) => write_tree_line(
+                tree,
+                depth,
+                &format!("Identifier {}", identifier),
+                self.span,
+            ),
+            ExpressionKind::Integer(integer) => {
+                write_tree_line(tree, depth, &format!("Integer {}", integer), self.span)
+            }
+            ExpressionKind::BigInt(bigint) => {
+                write_tree_line(tree, depth, &format!("BigInt {}", bigint), self.span)
+            }
+            ExpressionKind::Float(float) => {
+                write_tree_line(tree, depth, &format!("Float {:?}", float), self.span)
+            }
+            ExpressionKind::String(string) => write_tree_line(
+                tree,
+                depth,
+                &format!("String {}", lexer::quote_string(string)),
+                self.span,
+            ),
+            ExpressionKind::Boolean(boolean) => {
+                write_tree_line(tree, depth, &format!("Boolean {}", boolean), self.span)
+            }
+            ExpressionKind::PrefixExpression {
+                operator,
+                expression,
+            } => {
+                let label = format!("Prefix {}", operator.to_code());
+                write_tree_line(tree, depth, &label, self.span);
+                expression.write_tree(depth + 1, tree);
+            }
+            ExpressionKind::InfixExpression {
+                left,
+                operator,
+                right,
+            } => {
+                let label = format!("Infix {}", operator.to_code());
+                write_tree_line(tree, depth, &label, self.span);
+                left.write_tree(depth + 1, tree);
+                right.write_tree(depth + 1, tree);
+            }
+            ExpressionKind::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                write_tree_line(tree, depth, "If", self.span);
+                condition.write_tree(depth + 1, tree);
+                consequence.write_tree(depth + 1, tree);
+                if let Some(alternative) = alternative {
+                    alternative.write_tree(depth + 1, tree);
+                }
+            }
+            ExpressionKind::Function {
+                parameters, body, ..
+            } => {
+                write_tree_line(tree, depth, "Function", self.span);
+                for parameter in parameters {
+                    parameter.write_tree(depth + 1, tree);
+                }
+                body.write_tree(depth + 1, tree);
+            }
+            ExpressionKind::Call { function, args } => {
+                write_tree_line(tree, depth, "Call", self.span);
+                function.write_tree(depth + 1, tree);
+                for arg in args {
+                    arg.write_tree(depth + 1, tree);
+                }
+            }
+            ExpressionKind::Array(elements) => {
+                write_tree_line(tree, depth, "Array", self.span);
+                for element in elements {
+                    element.write_tree(depth + 1, tree);
+                }
+            }
+            ExpressionKind::Hash(pairs) => {
+                write_tree_line(tree, depth, "Hash", self.span);
+                for (key, value) in pairs {
+                    key.write_tree(depth + 1, tree);
+                    value.write_tree(depth + 2, tree);
+                }
+            }
+            ExpressionKind::Index { left, index } => {
+                write_tree_line(tree, depth, "Index", self.span);
+                left.write_tree(depth + 1, tree);
+                index.write_tree(depth + 1, tree);
+            }
         }
     }
 }
 
+/// 字下げ・ラベル・開始位置の1行を書き足す
+fn write_tree_line(tree: &mut String, depth: usize, label: &str, span: token::Span) {
+    tree.push_str(&"  ".repeat(depth));
+    tree.push_str(label);
+    tree.push(' ');
+    tree.push_str(&span.to_string());
+    tree.push('\n');
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,14 +401,18 @@ return x;
 ";
         let mut program = Program::new();
 
-        let statement1 = Statement::Let {
-            identifier: Expression::Identifier("x".to_string()),
-            value: Expression::Integer(100),
-        };
+        let statement1 = statement(StatementKind::Let {
+            identifier: expression(ExpressionKind::Identifier("x".to_string(), None)),
+            value: expression(ExpressionKind::Integer(100)),
+        });
 
-        let statement2 = Statement::Return(Expression::Identifier("x".to_string()));
+        let statement2 = statement(StatementKind::Return(expression(
+            ExpressionKind::Identifier("x".to_string(), None),
+        )));
 
-        let statement3 = Statement::Expression(Expression::Integer(500));
+        let statement3 = statement(StatementKind::Expression(expression(
+            ExpressionKind::Integer(500),
+        )));
 
         program.statements.push(statement1);
         program.statements.push(statement2);
@@ -202,12 +426,12 @@ return x;
         let expected_code = "(!test);\n";
         let mut program = Program::new();
 
-        let expression_id = Expression::Identifier("test".to_string());
-        let expression_prefix = Expression::PrefixExpression {
+        let expression_id = expression(ExpressionKind::Identifier("test".to_string(), None));
+        let expression_prefix = expression(ExpressionKind::PrefixExpression {
             operator: operator::Prefix::Exclamation,
             expression: Box::new(expression_id),
-        };
-        let statement = Statement::Expression(expression_prefix);
+        });
+        let statement = statement(StatementKind::Expression(expression_prefix));
 
         program.statements.push(statement);
 
@@ -219,17 +443,48 @@ return x;
         let expected_code = "(2 * test);\n";
         let mut program = Program::new();
 
-        let expression_l = Expression::Integer(2);
-        let expression_r = Expression::Identifier("test".to_string());
-        let expression_prefix = Expression::InfixExpression {
+        let expression_l = expression(ExpressionKind::Integer(2));
+        let expression_r = expression(ExpressionKind::Identifier("test".to_string(), None));
+        let expression_prefix = expression(ExpressionKind::InfixExpression {
             left: Box::new(expression_l),
             operator: operator::Infix::Asterisk,
             right: Box::new(expression_r),
-        };
-        let statement = Statement::Expression(expression_prefix);
+        });
+        let statement = statement(StatementKind::Expression(expression_prefix));
 
         program.statements.push(statement);
 
         assert_eq!(program.to_code(), expected_code);
     }
+
+    #[test]
+    fn test_to_tree() {
+        let expected_tree = "\
+Let 1:1
+  Identifier x 1:5
+  Infix + 1:9
+    Integer 1 1:9
+    Prefix - 1:13
+      Identifier y 1:14
+ExpressionStatement 2:1
+  Call 2:1
+    Identifier f 2:1
+    Hash 2:3
+      String \"a\" 2:4
+        Boolean true 2:9
+";
+        let lexer = lexer::Lexer::new("let x = 1 + -y;\nf({\"a\": true})");
+        let mut parser = crate::parser::Parser::new(lexer);
+        let program = parser.parse_program().expect("parser error");
+
+        assert_eq!(program.to_tree(), expected_tree);
+    }
+
+    fn statement(kind: StatementKind) -> Statement {
+        Statement::new(kind, token::Span::default())
+    }
+
+    fn expression(kind: ExpressionKind) -> Expression {
+        Expression::new(kind, token::Span::default())
+    }
 }
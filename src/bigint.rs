@@ -0,0 +1,385 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+/// 任意精度の符号付き整数
+///
+/// 絶対値は 2^32 進数の下位桁から順に保持し、上位の 0 は持たない (0 は空)。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        BigInt {
+            negative: false,
+            magnitude: Vec::new(),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    /// i64 に収まる場合はその値を返す
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+        let mut value: u64 = 0;
+        for (i, digit) in self.magnitude.iter().enumerate() {
+            value |= (*digit as u64) << (32 * i);
+        }
+        if self.negative {
+            if value <= i64::MAX as u64 + 1 {
+                Some((value as i64).wrapping_neg())
+            } else {
+                None
+            }
+        } else {
+            i64::try_from(value).ok()
+        }
+    }
+
+    /// 最も近い f64 に変換する
+    pub fn to_f64(&self) -> f64 {
+        let absolute = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0.0, |acc, digit| acc * 4294967296.0 + *digit as f64);
+        if self.negative {
+            -absolute
+        } else {
+            absolute
+        }
+    }
+
+    /// 10進数の数字の並び (符号なし) から作る (数字以外を含むか空なら None)
+    pub fn from_decimal(digits: &str) -> Option<BigInt> {
+        if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+
+        // 先頭から 9 桁ずつ、magnitude = magnitude * 10^桁数 + 値 として積み上げる
+        let mut magnitude = Vec::new();
+        let mut rest = digits;
+        while !rest.is_empty() {
+            let length = match rest.len() % 9 {
+                0 => 9,
+                length => length,
+            };
+            let (chunk, remaining) = rest.split_at(length);
+            magnitude = mul_magnitude(&magnitude, &[10u32.pow(length as u32)]);
+            magnitude = add_magnitude(&magnitude, &[chunk.parse().ok()?]);
+            rest = remaining;
+        }
+        Some(BigInt::new(false, magnitude))
+    }
+
+    /// 0 方向に切り捨てた商を返す (除数が 0 の場合は None)
+    pub fn checked_div(&self, other: &BigInt) -> Option<BigInt> {
+        if other.is_zero() {
+            return None;
+        }
+        let (quotient, _) = divrem_magnitude(&self.magnitude, &other.magnitude);
+        Some(BigInt::new(self.negative != other.negative, quotient))
+    }
+
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        while magnitude.last() == Some(&0) {
+            magnitude.pop();
+        }
+        let negative = negative && !magnitude.is_empty();
+        BigInt {
+            negative,
+            magnitude,
+        }
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> Self {
+        let absolute = value.unsigned_abs();
+        BigInt::new(value < 0, vec![absolute as u32, (absolute >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::ops::Neg for &BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::new(!self.negative, self.magnitude.clone())
+    }
+}
+
+impl std::ops::Add for &BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: &BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::new(
+                self.negative,
+                add_magnitude(&self.magnitude, &other.magnitude),
+            );
+        }
+        // 符号が異なる場合は絶対値の大きい方から小さい方を引く
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(
+                other.negative,
+                sub_magnitude(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitude(&self.magnitude, &other.magnitude),
+            ),
+        }
+    }
+}
+
+impl std::ops::Sub for &BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: &BigInt) -> BigInt {
+        self + &(-other)
+    }
+}
+
+impl std::ops::Mul for &BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: &BigInt) -> BigInt {
+        BigInt::new(
+            self.negative != other.negative,
+            mul_magnitude(&self.magnitude, &other.magnitude),
+        )
+    }
+}
+
+impl std::fmt::Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+
+        // 10^9 ごとに区切って下位から求める
+        let mut chunks = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = divrem_small(&magnitude, 1_000_000_000);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        if let Some(first) = chunks.next() {
+            write!(f, "{}", first)?;
+        }
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn cmp_magnitude(left: &[u32], right: &[u32]) -> Ordering {
+    left.len()
+        .cmp(&right.len())
+        .then_with(|| left.iter().rev().cmp(right.iter().rev()))
+}
+
+fn add_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..left.len().max(right.len()) {
+        let sum = *left.get(i).unwrap_or(&0) as u64 + *right.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry > 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+/// left >= right であること
+fn sub_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(left.len());
+    let mut borrow = 0i64;
+    for (i, digit) in left.iter().enumerate() {
+        let mut difference = *digit as i64 - *right.get(i).unwrap_or(&0) as i64 - borrow;
+        if difference < 0 {
+            difference += 1 << 32;
+            borrow = 1;
+        } else {
+            borrow = 0;
+        }
+        result.push(difference as u32);
+    }
+    while result.last() == Some(&0) {
+        result.pop();
+    }
+    result
+}
+
+fn mul_magnitude(left: &[u32], right: &[u32]) -> Vec<u32> {
+    let mut result = vec![0u32; left.len() + right.len()];
+    for (i, left_digit) in left.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, right_digit) in right.iter().enumerate() {
+            let product = *left_digit as u64 * *right_digit as u64 + result[i + j] as u64 + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + right.len()] = carry as u32;
+    }
+    while result.last() == Some(&0) {
+        result.pop();
+    }
+    result
+}
+
+fn divrem_small(magnitude: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; magnitude.len()];
+    let mut remainder = 0u64;
+    for (i, digit) in magnitude.iter().enumerate().rev() {
+        let current = (remainder << 32) | *digit as u64;
+        quotient[i] = (current / divisor as u64) as u32;
+        remainder = current % divisor as u64;
+    }
+    while quotient.last() == Some(&0) {
+        quotient.pop();
+    }
+    (quotient, remainder as u32)
+}
+
+/// 1 ビットずつ引き算する筆算 (divisor は 0 でないこと)
+fn divrem_magnitude(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_magnitude(dividend, divisor) == Ordering::Less {
+        return (Vec::new(), dividend.to_vec());
+    }
+
+    let mut quotient = vec![0u32; dividend.len()];
+    let mut remainder: Vec<u32> = Vec::new();
+    for bit in (0..dividend.len() * 32).rev() {
+        // remainder = remainder * 2 + (dividend の bit 桁目)
+        remainder = add_magnitude(&remainder, &remainder);
+        if (dividend[bit / 32] >> (bit % 32)) & 1 == 1 {
+            remainder = add_magnitude(&remainder, &[1]);
+        }
+        if cmp_magnitude(&remainder, divisor) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, divisor);
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    while quotient.last() == Some(&0) {
+        quotient.pop();
+    }
+    (quotient, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_i64_round_trip() {
+        let values = [0, 1, -1, 42, i64::MAX, i64::MIN, 1 << 32, -(1 << 32)];
+
+        for value in values {
+            let bigint = BigInt::from(value);
+            assert_eq!(bigint.to_i64(), Some(value));
+            assert_eq!(bigint.to_string(), value.to_string());
+        }
+
+        let over = &BigInt::from(i64::MAX) + &BigInt::from(1);
+        assert_eq!(over.to_i64(), None);
+        assert_eq!(over.to_f64(), 9223372036854775808.0);
+        assert_eq!((-&over).to_f64(), -9223372036854775808.0);
+        assert_eq!(over.to_string(), "9223372036854775808");
+
+        let under = &BigInt::from(i64::MIN) - &BigInt::from(1);
+        assert_eq!(under.to_i64(), None);
+        assert_eq!(under.to_string(), "-9223372036854775809");
+    }
+
+    #[test]
+    fn test_from_decimal() {
+        let values = [
+            "0",
+            "7",
+            "4294967296",
+            "9223372036854775807",
+            "99999999999999999999",
+        ];
+
+        for value in values {
+            let bigint = BigInt::from_decimal(value).expect(value);
+            assert_eq!(bigint.to_string(), value);
+        }
+
+        assert_eq!(BigInt::from_decimal("000123"), Some(BigInt::from(123)));
+        assert_eq!(BigInt::from_decimal(""), None);
+        assert_eq!(BigInt::from_decimal("-1"), None);
+        assert_eq!(BigInt::from_decimal("1.5"), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = &BigInt::from(i64::MAX) * &BigInt::from(i64::MAX);
+        assert_eq!(a.to_string(), "85070591730234615847396907784232501249");
+
+        let b = &a * &BigInt::from(-3);
+        assert_eq!(b.to_string(), "-255211775190703847542190723352697503747");
+
+        assert_eq!(
+            (&a + &b).to_string(),
+            "-170141183460469231694793815568465002498"
+        );
+        assert_eq!((&b - &b), BigInt::zero());
+        assert_eq!((&b + &(-&b)), BigInt::zero());
+
+        assert_eq!(b.checked_div(&a), Some(BigInt::from(-3)));
+        let quotient = a.checked_div(&BigInt::from(-1_000_000_007)).unwrap();
+        assert_eq!(quotient.to_string(), "-85070591134740477904213562454");
+        assert_eq!(
+            BigInt::from(7).checked_div(&BigInt::from(-2)),
+            Some(BigInt::from(-3))
+        );
+        assert_eq!(a.checked_div(&BigInt::zero()), None);
+    }
+
+    #[test]
+    fn test_ordering() {
+        let big = &BigInt::from(i64::MAX) + &BigInt::from(1);
+        let small = &(-&big) - &BigInt::from(1);
+
+        assert!(big > BigInt::from(i64::MAX));
+        assert!(small < BigInt::from(i64::MIN));
+        assert_eq!((-&big).to_i64(), Some(i64::MIN));
+        assert!(small < big);
+        assert!(BigInt::from(-1) < BigInt::zero());
+        assert_eq!(BigInt::from(5).cmp(&BigInt::from(5)), Ordering::Equal);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::error;
+use crate::object;
+use crate::token;
+use std::rc::Rc;
+
+/// 組み込み関数の実体 (引数と呼び出し位置を受け取る)
+pub type BuiltinFunction =
+    fn(&[object::Object], token::Span) -> Result<object::Object, Box<dyn std::error::Error>>;
+
+type NativeFunction =
+    dyn Fn(&[object::Object], token::Span) -> Result<object::Object, Box<dyn std::error::Error>>;
+
+/// 名前付きのネイティブ関数 (名前が同じなら等しいとみなす)
+#[derive(Clone)]
+pub struct Builtin {
+    pub name: String,
+    function: Rc<NativeFunction>,
+}
+
+impl Builtin {
+    pub fn new<F>(name: &str, function: F) -> Self
+    where
+        F: Fn(&[object::Object], token::Span) -> Result<object::Object, Box<dyn std::error::Error>>
+            + 'static,
+    {
+        Builtin {
+            name: name.to_string(),
+            function: Rc::new(function),
+        }
+    }
+
+    pub fn call(
+        &self,
+        args: &[object::Object],
+        span: token::Span,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        (self.function)(args, span)
+    }
+}
+
+impl std::fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Builtin({})", self.name)
+    }
+}
+
+impl PartialEq for Builtin {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+const BUILTINS: [(&str, BuiltinFunction); 6] = [
+    ("len", len),
+    ("puts", puts),
+    ("first", first),
+    ("last", last),
+    ("rest", rest),
+    ("push", push),
+];
+
+/// 名前から組み込み関数を探す
+pub fn lookup(name: &str) -> Option<object::Object> {
+    BUILTINS
+        .iter()
+        .find(|(builtin_name, _)| *builtin_name == name)
+        .map(|(builtin_name, function)| {
+            object::Object::Builtin(Builtin::new(builtin_name, *function))
+        })
+}
+
+/// 組み込み関数の名前の一覧
+pub fn names() -> impl Iterator<Item = &'static str> {
+    BUILTINS.iter().map(|(name, _)| *name)
+}
+
+fn check_arity(
+    name: &str,
+    args: &[object::Object],
+    expected: usize,
+    span: token::Span,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if args.len() == expected {
+        Ok(())
+    } else {
+        Err(error::EvaluatorError::ArityMismatch {
+            function: name.to_string(),
+            expected,
+            actual: args.len(),
+            span,
+        })?
+    }
+}
+
+fn unsupported_argument(
+    name: &str,
+    argument: &object::Object,
+    span: token::Span,
+) -> Result<object::Object, Box<dyn std::error::Error>> {
+    Err(error::EvaluatorError::UnsupportedArgument {
+        function: name.to_string(),
+        argument: argument.clone(),
+        span,
+    })?
+}
+
+fn len(
+    args: &[object::Object],
+    span: token::Span,
+) -> Result<object::Object, Box<dyn std::error::Error>> {
+    check_arity("len", args, 1, span)?;
+    match &args[0] {
+        object::Object::String(string) => {
+            Ok(object::Object::Integer(string.chars().count() as i64))
+        }
+        object::Object::Array(elements) => Ok(object::Object::Integer(elements.len() as i64)),
+        object::Object::Hash(pairs) => Ok(object::Object::Integer(pairs.len() as i64)),
+        other => unsupported_argument("len", other, span),
+    }
+}
+
+fn puts(
+    args: &[object::Object],
+    _span: token::Span,
+) -> Result<object::Object, Box<dyn std::error::Error>> {
+    for arg in args {
+        match arg {
+            object::Object::String(string) => println!("{}", string),
+            other => println!("{}", other.inspect()),
+        }
+    }
+    Ok(object::Object::Null)
+}
+
+fn first(
+    args: &[object::Object],
+    span: token::Span,
+) -> Result<object::Object, Box<dyn std::error::Error>> {
+    check_arity("first", args, 1, span)?;
+    match &args[0] {
+        object::Object::Array(elements) => {
+            Ok(elements.first().cloned().unwrap_or(object::Object::Null))
+        }
+        other => unsupported_argument("first", other, span),
+    }
+}
+
+fn last(
+    args: &[object::Object],
+    span: token::Span,
+) -> Result<object::Object, Box<dyn std::error::Error>> {
+    check_arity("last", args, 1, span)?;
+    match &args[0] {
+        object::Object::Array(elements) => {
+            Ok(elements.last().cloned().unwrap_or(object::Object::Null))
+        }
+        other => unsupported_argument("last", other, span),
+    }
+}
+
+fn rest(
+    args: &[object::Object],
+    span: token::Span,
+) -> Result<object::Object, Box<dyn std::error::Error>> {
+    check_arity("rest", args, 1, span)?;
+    match &args[0] {
+        object::Object::Array(elements) if elements.is_empty() => Ok(object::Object::Null),
+        object::Object::Array(elements) => Ok(object::Object::Array(elements[1..].to_vec())),
+        other => unsupported_argument("rest", other, span),
+    }
+}
+
+fn push(
+    args: &[object::Object],
+    span: token::Span,
+) -> Result<object::Object, Box<dyn std::error::Error>> {
+    check_arity("push", args, 2, span)?;
+    match &args[0] {
+        object::Object::Array(elements) => {
+            let mut elements = elements.clone();
+            elements.push(args[1].clone());
+            Ok(object::Object::Array(elements))
+        }
+        other => unsupported_argument("push", other, span),
+    }
+}
@@ -0,0 +1,782 @@
+use crate::bigint;
+use crate::code::{self, Opcode};
+use crate::error::BytecodeFileError;
+use crate::token;
+use std::rc::Rc;
+
+/// ファイルの先頭の4バイト
+pub const MAGIC: [u8; 4] = *b"MKBC";
+
+/// 形式のバージョン。命令の並びや定数の表し方を変えたら上げる
+pub const VERSION: u16 = 3;
+
+const FLAG_DEBUG_INFO: u8 = 1;
+
+const TAG_INTEGER: u8 = 0;
+const TAG_FLOAT: u8 = 1;
+const TAG_STRING: u8 = 2;
+const TAG_FUNCTION: u8 = 3;
+const TAG_BIGINT: u8 = 4;
+
+const VARIABLE_NONE: u8 = 0;
+const VARIABLE_GLOBAL: u8 = 1;
+const VARIABLE_LOCAL: u8 = 2;
+const VARIABLE_FREE: u8 = 3;
+
+/// バイトコードのファイルか (先頭がマジックナンバーか)
+pub fn is_bytecode_file(bytes: &[u8]) -> bool {
+    bytes.starts_with(&MAGIC)
+}
+
+/// バイトコードをファイルの形式に書き出す
+///
+/// ```text
+/// マジックナンバー "MKBC" / バージョン (u16) / フラグ (u8)
+/// グローバル変数の名前 / 定数表 / トップレベルの関数
+/// ```
+///
+/// 数値はビッグエンディアン、個数と長さは u32、文字列は長さ + UTF-8。
+/// 多倍長整数 (リテラルなので負にならない) は10進数の文字列で書く。
+/// 変数の場所 (`code::Variable`) は種類 (u8) と番号 (u32) で書く。
+/// debug_info が false なら、エラー位置の表 (`CompiledFunction::positions`) を省く。
+pub fn write(bytecode: &code::Bytecode, debug_info: bool) -> Vec<u8> {
+    let mut writer = Writer {
+        bytes: Vec::new(),
+        debug_info,
+    };
+    writer.bytes.extend_from_slice(&MAGIC);
+    writer.bytes.extend_from_slice(&VERSION.to_be_bytes());
+    writer
+        .bytes
+        .push(if debug_info { FLAG_DEBUG_INFO } else { 0 });
+
+    writer.strings(&bytecode.globals);
+    writer.u32(bytecode.constants.len());
+    for constant in &bytecode.constants {
+        match constant {
+            code::Constant::Integer(integer) => {
+                writer.bytes.push(TAG_INTEGER);
+                writer.bytes.extend_from_slice(&integer.to_be_bytes());
+            }
+            code::Constant::BigInt(bigint) => {
+                writer.bytes.push(TAG_BIGINT);
+                writer.string(&bigint.to_string());
+            }
+            code::Constant::Float(float) => {
+                writer.bytes.push(TAG_FLOAT);
+                writer
+                    .bytes
+                    .extend_from_slice(&float.to_bits().to_be_bytes());
+            }
+            code::Constant::String(string) => {
+                writer.bytes.push(TAG_STRING);
+                writer.string(string);
+            }
+            code::Constant::Function(function) => {
+                writer.bytes.push(TAG_FUNCTION);
+                writer.function(function);
+            }
+        }
+    }
+    writer.function(&bytecode.main);
+    writer.bytes
+}
+
+/// ファイルの形式からバイトコードを読み込む
+///
+/// 途中で終わっているファイルや版の違うファイルはエラーにする。
+/// 命令は、オペランドの番号が定数表・変数の範囲に収まり、飛び先が命令の先頭であることまで確かめる。
+pub fn read(bytes: &[u8]) -> Result<code::Bytecode, BytecodeFileError> {
+    if !is_bytecode_file(bytes) {
+        return Err(BytecodeFileError::InvalidMagic);
+    }
+    let mut reader = Reader {
+        bytes,
+        offset: MAGIC.len(),
+        debug_info: false,
+        num_globals: 0,
+    };
+    let version = u16::from_be_bytes([reader.u8()?, reader.u8()?]);
+    if version != VERSION {
+        return Err(BytecodeFileError::UnsupportedVersion(version));
+    }
+    let flags_offset = reader.offset;
+    let flags = reader.u8()?;
+    if flags & !FLAG_DEBUG_INFO != 0 {
+        return Err(BytecodeFileError::InvalidData(flags_offset));
+    }
+    reader.debug_info = flags & FLAG_DEBUG_INFO != 0;
+
+    let globals = reader.strings()?;
+    reader.num_globals = globals.len();
+    let num_constants = reader.u32()?;
+    let mut constants = Vec::new();
+    for _ in 0..num_constants {
+        let tag_offset = reader.offset;
+        let constant = match reader.u8()? {
+            TAG_INTEGER => code::Constant::Integer(i64::from_be_bytes(reader.array()?)),
+            TAG_FLOAT => code::Constant::Float(f64::from_bits(u64::from_be_bytes(reader.array()?))),
+            TAG_STRING => code::Constant::String(reader.string()?),
+            TAG_FUNCTION => code::Constant::Function(Rc::new(reader.function()?)),
+            TAG_BIGINT => match bigint::BigInt::from_decimal(&reader.string()?) {
+                Some(bigint) => code::Constant::BigInt(bigint),
+                None => return Err(BytecodeFileError::InvalidData(tag_offset)),
+            },
+            _ => return Err(BytecodeFileError::InvalidData(tag_offset)),
+        };
+        constants.push(constant);
+    }
+    // トップレベルはローカル変数の領域を確保せずに実行する
+    let main_offset = reader.offset;
+    let main = reader.function()?;
+    if main.num_locals != 0 || !main.captures.is_empty() {
+        return Err(BytecodeFileError::InvalidData(main_offset));
+    }
+    if reader.offset != bytes.len() {
+        return Err(BytecodeFileError::InvalidData(reader.offset));
+    }
+
+    let bytecode = code::Bytecode {
+        main: Rc::new(main),
+        constants,
+        globals,
+    };
+    verify(&bytecode.main, "main", &bytecode)?;
+    for (index, constant) in bytecode.constants.iter().enumerate() {
+        if let code::Constant::Function(function) = constant {
+            verify(function, &format!("constant {}", index), &bytecode)?;
+        }
+    }
+    Ok(bytecode)
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+    debug_info: bool,
+}
+
+impl Writer {
+    fn u32(&mut self, value: usize) {
+        self.bytes.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+
+    fn string(&mut self, string: &str) {
+        self.u32(string.len());
+        self.bytes.extend_from_slice(string.as_bytes());
+    }
+
+    fn strings(&mut self, strings: &[String]) {
+        self.u32(strings.len());
+        for string in strings {
+            self.string(string);
+        }
+    }
+
+    fn variable(&mut self, variable: Option<code::Variable>) {
+        let (tag, index) = match variable {
+            None => (VARIABLE_NONE, 0),
+            Some(code::Variable::Global(index)) => (VARIABLE_GLOBAL, index),
+            Some(code::Variable::Local(index)) => (VARIABLE_LOCAL, index),
+            Some(code::Variable::Free(index)) => (VARIABLE_FREE, index),
+        };
+        self.bytes.push(tag);
+        self.u32(index);
+    }
+
+    fn position(&mut self, position: token::Position) {
+        self.u32(position.offset);
+        self.u32(position.line);
+        self.u32(position.column);
+    }
+
+    fn function(&mut self, function: &code::CompiledFunction) {
+        self.u32(function.num_locals);
+        self.u32(function.num_parameters);
+        self.u32(function.instructions.len());
+        self.bytes.extend_from_slice(&function.instructions);
+        self.strings(&function.local_names);
+        self.strings(&function.free_names);
+        for capture in &function.captures {
+            self.variable(Some(*capture));
+        }
+        for fallback in function
+            .local_fallbacks
+            .iter()
+            .chain(&function.free_fallbacks)
+        {
+            self.variable(*fallback);
+        }
+        self.string(&function.code);
+        if self.debug_info {
+            self.u32(function.positions.len());
+            for (offset, span) in &function.positions {
+                self.u32(*offset);
+                self.position(span.start);
+                self.position(span.end);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    debug_info: bool,
+    num_globals: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], BytecodeFileError> {
+        match self
+            .bytes
+            .get(self.offset..self.offset.saturating_add(length))
+        {
+            Some(bytes) => {
+                self.offset += length;
+                Ok(bytes)
+            }
+            None => Err(BytecodeFileError::Truncated(self.bytes.len())),
+        }
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BytecodeFileError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8, BytecodeFileError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, BytecodeFileError> {
+        Ok(u32::from_be_bytes(self.array()?) as usize)
+    }
+
+    fn string(&mut self) -> Result<String, BytecodeFileError> {
+        let length = self.u32()?;
+        let start = self.offset;
+        let bytes = self.take(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| BytecodeFileError::InvalidData(start))
+    }
+
+    fn strings(&mut self) -> Result<Vec<String>, BytecodeFileError> {
+        // 個数は信用せず、読めた分だけ領域を確保する
+        let count = self.u32()?;
+        let mut strings = Vec::new();
+        for _ in 0..count {
+            strings.push(self.string()?);
+        }
+        Ok(strings)
+    }
+
+    fn variable(&mut self) -> Result<Option<code::Variable>, BytecodeFileError> {
+        let tag_offset = self.offset;
+        let tag = self.u8()?;
+        let index = self.u32()?;
+        match tag {
+            VARIABLE_NONE if index == 0 => Ok(None),
+            VARIABLE_GLOBAL => Ok(Some(code::Variable::Global(index))),
+            VARIABLE_LOCAL => Ok(Some(code::Variable::Local(index))),
+            VARIABLE_FREE => Ok(Some(code::Variable::Free(index))),
+            _ => Err(BytecodeFileError::InvalidData(tag_offset)),
+        }
+    }
+
+    /// 未束縛の変数を読んだときに探す先 (グローバル変数か捕まえた変数)
+    fn fallback(&mut self, num_free: usize) -> Result<Option<code::Variable>, BytecodeFileError> {
+        let offset = self.offset;
+        let fallback = self.variable()?;
+        match fallback {
+            None => Ok(None),
+            Some(code::Variable::Global(index)) if index < self.num_globals => Ok(fallback),
+            Some(code::Variable::Free(index)) if index < num_free => Ok(fallback),
+            _ => Err(BytecodeFileError::InvalidData(offset)),
+        }
+    }
+
+    fn position(&mut self) -> Result<token::Position, BytecodeFileError> {
+        Ok(token::Position {
+            offset: self.u32()?,
+            line: self.u32()?,
+            column: self.u32()?,
+        })
+    }
+
+    fn function(&mut self) -> Result<code::CompiledFunction, BytecodeFileError> {
+        let num_locals = self.u32()?;
+        let num_parameters = self.u32()?;
+        let length = self.u32()?;
+        let instructions = self.take(length)?.to_vec();
+        let names_offset = self.offset;
+        let local_names = self.strings()?;
+        if num_parameters > num_locals || local_names.len() != num_locals {
+            return Err(BytecodeFileError::InvalidData(names_offset));
+        }
+        let free_names = self.strings()?;
+        let mut captures = Vec::new();
+        for _ in 0..free_names.len() {
+            let offset = self.offset;
+            match self.variable()? {
+                Some(capture @ code::Variable::Local(_))
+                | Some(capture @ code::Variable::Free(_)) => captures.push(capture),
+                _ => return Err(BytecodeFileError::InvalidData(offset)),
+            }
+        }
+        let mut local_fallbacks = Vec::new();
+        for _ in 0..num_locals {
+            local_fallbacks.push(self.fallback(captures.len())?);
+        }
+        let mut free_fallbacks = Vec::new();
+        for _ in 0..captures.len() {
+            free_fallbacks.push(self.fallback(captures.len())?);
+        }
+        // 捕まえた変数の探す先をたどると、いずれ終わる (循環しない)
+        for start in 0..free_fallbacks.len() {
+            let mut current = start;
+            for _ in 0..=free_fallbacks.len() {
+                match free_fallbacks[current] {
+                    Some(code::Variable::Free(next)) => current = next,
+                    _ => break,
+                }
+            }
+            if matches!(free_fallbacks[current], Some(code::Variable::Free(_))) {
+                return Err(BytecodeFileError::InvalidData(names_offset));
+            }
+        }
+        let code = self.string()?;
+
+        let mut positions = Vec::new();
+        if self.debug_info {
+            let count = self.u32()?;
+            for _ in 0..count {
+                let offset_at = self.offset;
+                let offset = self.u32()?;
+                let span = token::Span::new(self.position()?, self.position()?);
+                // span_at は位置の昇順を前提に二分探索し、エラー表示は行・列を 1 始まりとして扱う
+                let unordered = matches!(positions.last(), Some((last, _)) if *last >= offset);
+                if unordered || span.start.line == 0 || span.start.column == 0 {
+                    return Err(BytecodeFileError::InvalidData(offset_at));
+                }
+                positions.push((offset, span));
+            }
+        }
+
+        Ok(code::CompiledFunction {
+            instructions,
+            num_locals,
+            num_parameters,
+            positions,
+            local_names,
+            free_names,
+            captures,
+            local_fallbacks,
+            free_fallbacks,
+            code,
+        })
+    }
+}
+
+/// VM が範囲外を読まないよう、命令のオペランドとスタックの深さを確かめる
+///
+/// スタックの値が足りなくなる命令や、経路によってスタックの深さが変わる合流先を含む関数は不正とする。
+fn verify(
+    function: &code::CompiledFunction,
+    name: &str,
+    bytecode: &code::Bytecode,
+) -> Result<(), BytecodeFileError> {
+    let invalid = |offset: usize| BytecodeFileError::InvalidInstruction {
+        function: name.to_string(),
+        offset,
+    };
+    let instructions = &function.instructions;
+
+    // 先に命令の先頭を集めておき、飛び先を確かめる
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    while offset < instructions.len() {
+        let opcode = Opcode::from_byte(instructions[offset]).ok_or_else(|| invalid(offset))?;
+        if offset + opcode.width() > instructions.len() {
+            return Err(invalid(offset));
+        }
+        starts.push(offset);
+        offset += opcode.width();
+    }
+    // 最後の命令で必ず呼び出し元に戻る
+    match starts.last() {
+        Some(last) if instructions[*last] == Opcode::ReturnValue as u8 => {}
+        _ => return Err(invalid(instructions.len())),
+    }
+
+    let decode = |start: usize| {
+        let opcode = Opcode::from_byte(instructions[start]).expect("確認済みの命令です");
+        let mut operands = Vec::new();
+        let mut position = start + 1;
+        for width in opcode.operand_widths() {
+            operands.push(code::read_operand(instructions, position, *width));
+            position += width;
+        }
+        (opcode, operands)
+    };
+
+    for start in &starts {
+        let (opcode, operands) = decode(*start);
+        let constant = |index: usize| bytecode.constants.get(index);
+        let valid = match opcode {
+            // 関数は Closure で積む (Constant で積むと捕まえる変数が無い)
+            Opcode::Constant => !matches!(
+                constant(operands[0]),
+                None | Some(code::Constant::Function(_))
+            ),
+            Opcode::Jump | Opcode::JumpIfFalse => starts.binary_search(&operands[0]).is_ok(),
+            Opcode::GetGlobal | Opcode::SetGlobal => operands[0] < bytecode.globals.len(),
+            Opcode::GetLocal | Opcode::SetLocal => operands[0] < function.num_locals,
+            Opcode::GetFree => operands[0] < function.captures.len(),
+            Opcode::Call => matches!(constant(operands[1]), Some(code::Constant::String(_))),
+            // 捕まえる変数は、クロージャを作る関数の変数の範囲に収まる
+            Opcode::Closure => match constant(operands[0]) {
+                Some(code::Constant::Function(closure)) => {
+                    closure.captures.iter().all(|capture| match capture {
+                        code::Variable::Local(index) => *index < function.num_locals,
+                        code::Variable::Free(index) => *index < function.captures.len(),
+                        code::Variable::Global(_) => false,
+                    })
+                }
+                _ => false,
+            },
+            _ => true,
+        };
+        if !valid {
+            return Err(invalid(*start));
+        }
+    }
+
+    // 通り得る命令ごとにスタックの深さを求め、足りなくならないことと、
+    // 合流する命令でどの経路からも同じ深さになることを確かめる
+    let mut depths: Vec<Option<usize>> = vec![None; instructions.len()];
+    let mut pending = vec![(0, 0)];
+    while let Some((start, depth)) = pending.pop() {
+        match depths[start] {
+            Some(known) if known == depth => continue,
+            Some(_) => return Err(invalid(start)),
+            None => depths[start] = Some(depth),
+        }
+        let (opcode, operands) = decode(start);
+        let (pops, pushes) = stack_effect(opcode, &operands);
+        let depth = depth.checked_sub(pops).ok_or_else(|| invalid(start))? + pushes;
+        let next = start + opcode.width();
+        match opcode {
+            Opcode::Jump => pending.push((operands[0], depth)),
+            Opcode::JumpIfFalse => pending.extend([(operands[0], depth), (next, depth)]),
+            Opcode::ReturnValue => {}
+            _ => pending.push((next, depth)),
+        }
+    }
+    Ok(())
+}
+
+/// 命令が取り出す値と積む値の数
+fn stack_effect(opcode: Opcode, operands: &[usize]) -> (usize, usize) {
+    match opcode {
+        Opcode::Constant
+        | Opcode::Null
+        | Opcode::True
+        | Opcode::False
+        | Opcode::GetGlobal
+        | Opcode::GetLocal
+        | Opcode::GetFree
+        | Opcode::Closure => (0, 1),
+        Opcode::Pop
+        | Opcode::JumpIfFalse
+        | Opcode::SetGlobal
+        | Opcode::SetLocal
+        | Opcode::ReturnValue => (1, 0),
+        Opcode::Add
+        | Opcode::Sub
+        | Opcode::Mul
+        | Opcode::Div
+        | Opcode::Equal
+        | Opcode::NotEqual
+        | Opcode::GreaterThan
+        | Opcode::LessThan
+        | Opcode::Index => (2, 1),
+        Opcode::Minus | Opcode::Bang | Opcode::HashKey => (1, 1),
+        Opcode::Jump => (0, 0),
+        Opcode::Array => (operands[0], 1),
+        Opcode::Hash => (operands[0] * 2, 1),
+        Opcode::Call => (operands[0] + 1, 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler;
+    use crate::diagnostic::Diagnostic;
+    use crate::lexer;
+    use crate::message::{self, Locale};
+    use crate::parser;
+    use crate::vm;
+
+    fn compile(input: &str) -> code::Bytecode {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = parser::Parser::new(lexer);
+        let program = parser.parse_program().expect("parser error");
+        compiler::Compiler::new()
+            .compile(&program)
+            .expect("compiler error")
+    }
+
+    const PROGRAM: &str = "
+let adder = fn(a) { fn(b) { a + b } };
+let fib = fn(n) { if (n < 2) { n } else { fib(n - 1) + fib(n - 2) } };
+let later = fn(c) { let g = fn() { h }; if (c) { let h = 4; }; g() };
+let h = 3;
+[adder(1.5)(2.0), fib(10), {\"k\": \"v\"}[\"k\"], 99999999999999999999 + 1, later(true), later(false)]";
+
+    #[test]
+    fn test_round_trip() {
+        let bytecode = compile(PROGRAM);
+        let loaded = read(&write(&bytecode, true)).expect("読み込みエラー");
+        assert_eq!(loaded, bytecode);
+        assert_eq!(
+            vm::Vm::new().run(&loaded).expect("vm error").inspect(),
+            "[3.5, 55, \"v\", 100000000000000000000, 4, 3]"
+        );
+
+        // エラー位置の表を省いても実行できる
+        let stripped = read(&write(&bytecode, false)).expect("読み込みエラー");
+        assert!(stripped.main.positions.is_empty());
+        assert_eq!(stripped.constants.len(), bytecode.constants.len());
+        assert_eq!(
+            vm::Vm::new().run(&stripped).expect("vm error"),
+            vm::Vm::new().run(&bytecode).expect("vm error")
+        );
+    }
+
+    #[test]
+    fn test_runtime_error_position() {
+        message::set_locale(Locale::Ja);
+        let bytecode = compile("let f = fn(x) { x + true };\n\nf(1)");
+        let loaded = read(&write(&bytecode, true)).expect("読み込みエラー");
+        let err = vm::Vm::new()
+            .run(&loaded)
+            .expect_err("エラーを期待しました");
+        assert_eq!(err.to_string(), "1:17: 型のミスマッチ: 1 + true");
+        assert_eq!(
+            vm::Vm::new().run(&bytecode).unwrap_err().to_string(),
+            err.to_string()
+        );
+
+        // 位置の表を省くと、位置を作らずに位置の無いエラーになる
+        let stripped = read(&write(&bytecode, false)).expect("読み込みエラー");
+        let err = vm::Vm::new()
+            .run(&stripped)
+            .expect_err("エラーを期待しました");
+        assert_eq!(err.to_string(), "型のミスマッチ: 1 + true");
+        let diagnostic = Diagnostic::from_error(err.as_ref()).expect("診断");
+        assert_eq!((diagnostic.code, diagnostic.span), ("E0001", None));
+    }
+
+    #[test]
+    fn test_read_error() {
+        let bytes = write(&compile(PROGRAM), true);
+
+        assert_eq!(read(b"let x = 1;"), Err(BytecodeFileError::InvalidMagic));
+        let mut version = bytes.clone();
+        version[5] = 1;
+        assert_eq!(
+            read(&version),
+            Err(BytecodeFileError::UnsupportedVersion(1))
+        );
+
+        // どこで途切れても、パニックせずにエラーになる
+        for length in MAGIC.len()..bytes.len() {
+            assert_eq!(
+                read(&bytes[..length]),
+                Err(BytecodeFileError::Truncated(length)),
+                "{}",
+                length
+            );
+        }
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            read(&trailing),
+            Err(BytecodeFileError::InvalidData(bytes.len()))
+        );
+    }
+
+    #[test]
+    fn test_read_invalid_instruction() {
+        let bytecode = compile("let x = 1; if (x) { x }");
+        let problem = [
+            (0, 255, 0),                            // 未知の命令
+            (5, 1, 3),                              // SetGlobal 1 (グローバル変数は1つ)
+            (11, 1, 9),                             // JumpIfFalse の飛び先が命令の途中
+            (2, bytecode.constants.len() as u8, 0), // Constant の番号が範囲外
+        ];
+
+        for (index, byte, offset) in problem {
+            let mut main = (*bytecode.main).clone();
+            main.instructions[index] = byte;
+            let broken = code::Bytecode {
+                main: Rc::new(main),
+                ..bytecode.clone()
+            };
+            assert_eq!(
+                read(&write(&broken, true)),
+                Err(BytecodeFileError::InvalidInstruction {
+                    function: "main".to_string(),
+                    offset,
+                }),
+                "{}",
+                broken.disassemble()
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_invalid_stack() {
+        message::set_locale(Locale::Ja);
+        let bytecode = compile("let f = fn(a) { fn() { a } }; f(1)()");
+        let closure = bytecode
+            .constants
+            .iter()
+            .position(|constant| {
+                matches!(constant, code::Constant::Function(function) if !function.captures.is_empty())
+            })
+            .expect("クロージャがありません");
+        let with_main = |instructions: &[(Opcode, Vec<usize>)]| {
+            let mut main = (*bytecode.main).clone();
+            main.instructions = instructions
+                .iter()
+                .flat_map(|(opcode, operands)| code::make(*opcode, operands).expect("make"))
+                .collect();
+            code::Bytecode {
+                main: Rc::new(main),
+                ..bytecode.clone()
+            }
+        };
+        let problem = [
+            // 空のスタックから取り出す
+            (
+                vec![(Opcode::Pop, vec![]), (Opcode::ReturnValue, vec![])],
+                0,
+            ),
+            (
+                vec![
+                    (Opcode::Null, vec![]),
+                    (Opcode::Add, vec![]),
+                    (Opcode::ReturnValue, vec![]),
+                ],
+                1,
+            ),
+            // 捕まえる変数の無いクロージャを Constant で積む
+            (
+                vec![
+                    (Opcode::Constant, vec![closure]),
+                    (Opcode::ReturnValue, vec![]),
+                ],
+                0,
+            ),
+            // 合流する位置で深さが違う
+            (
+                vec![
+                    (Opcode::Null, vec![]),
+                    (Opcode::True, vec![]),
+                    (Opcode::JumpIfFalse, vec![6]),
+                    (Opcode::Null, vec![]),
+                    (Opcode::ReturnValue, vec![]),
+                ],
+                6,
+            ),
+        ];
+
+        for (instructions, offset) in problem {
+            let broken = with_main(&instructions);
+            assert_eq!(
+                read(&write(&broken, false)),
+                Err(BytecodeFileError::InvalidInstruction {
+                    function: "main".to_string(),
+                    offset,
+                }),
+                "{}",
+                broken.disassemble()
+            );
+        }
+
+        // HashKey で確かめていないキーは、パニックせずに実行時エラーになる
+        let unchecked = with_main(&[
+            (Opcode::Array, vec![0]),
+            (Opcode::Null, vec![]),
+            (Opcode::Hash, vec![1]),
+            (Opcode::ReturnValue, vec![]),
+        ]);
+        let loaded = read(&write(&unchecked, false)).expect("読み込みエラー");
+        let err = vm::Vm::new()
+            .run(&loaded)
+            .expect_err("エラーを期待しました");
+        assert_eq!(err.to_string(), "ハッシュのキーに使えません。: []");
+    }
+
+    #[test]
+    fn test_read_invalid_variables() {
+        let bytecode = compile("let f = fn(a) { let b = 1; fn() { a + b } }; f(1)()");
+        let function = |index: usize| match &bytecode.constants[index] {
+            code::Constant::Function(function) => (**function).clone(),
+            other => panic!("関数を期待しましたが、{:?}でした。", other),
+        };
+        let replace = |index: usize, function: code::CompiledFunction| {
+            let mut constants = bytecode.constants.clone();
+            constants[index] = code::Constant::Function(Rc::new(function));
+            code::Bytecode {
+                constants,
+                ..bytecode.clone()
+            }
+        };
+        let inner = bytecode
+            .constants
+            .iter()
+            .position(|constant| {
+                matches!(constant, code::Constant::Function(function) if !function.captures.is_empty())
+            })
+            .expect("クロージャがありません");
+        let outer = inner + 1;
+
+        // 捕まえる変数が、クロージャを作る関数の変数の範囲外
+        let mut broken = function(inner);
+        broken.captures[1] = code::Variable::Local(2);
+        assert_eq!(
+            read(&write(&replace(inner, broken), true)),
+            Err(BytecodeFileError::InvalidInstruction {
+                function: format!("constant {}", outer),
+                offset: function(outer)
+                    .instructions
+                    .iter()
+                    .position(|byte| *byte == Opcode::Closure as u8)
+                    .expect("Closure がありません"),
+            })
+        );
+
+        // 探す先が循環している・範囲外
+        for fallbacks in [
+            vec![Some(code::Variable::Free(1)), Some(code::Variable::Free(0))],
+            vec![None, Some(code::Variable::Free(2))],
+            vec![None, Some(code::Variable::Global(9))],
+            vec![None, Some(code::Variable::Local(0))],
+        ] {
+            let mut broken = function(inner);
+            broken.free_fallbacks = fallbacks.clone();
+            assert!(
+                matches!(
+                    read(&write(&replace(inner, broken), true)),
+                    Err(BytecodeFileError::InvalidData(_))
+                ),
+                "{:?}",
+                fallbacks
+            );
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use crate::bigint;
+use crate::token;
+use std::rc::Rc;
+
+/// バイトコードの命令。オペランドはビッグエンディアンで命令の後に続く
+#[repr(u8)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Opcode {
+    Constant,    // 定数を積む (定数の番号)
+    Pop,         // 捨てる
+    Null,        // null を積む
+    True,        // true を積む
+    False,       // false を積む
+    Add,         // +
+    Sub,         // -
+    Mul,         // *
+    Div,         // /
+    Equal,       // ==
+    NotEqual,    // !=
+    GreaterThan, // >
+    LessThan,    // <
+    Minus,       // 前置の -
+    Bang,        // 前置の !
+    Jump,        // 無条件に飛ぶ (飛び先)
+    JumpIfFalse, // 取り出した値が偽なら飛ぶ (飛び先)
+    GetGlobal,   // グローバル変数を積む (変数の番号)
+    SetGlobal,   // グローバル変数に束縛する (変数の番号)
+    GetLocal,    // ローカル変数を積む (変数の番号)
+    SetLocal,    // ローカル変数に束縛する (変数の番号)
+    GetFree,     // クロージャが捕まえた変数を積む (変数の番号)
+    Array,       // 積まれた要素から配列を作る (要素数)
+    Hash,        // 積まれたキーと値から辞書を作る (組の数)
+    HashKey,     // 先頭の値がハッシュのキーに使えるか確かめる
+    Index,       // 添字演算子
+    Call,        // 関数を呼ぶ (引数の数, 呼び出した式を表す定数の番号)
+    ReturnValue, // 先頭の値を返す
+    Closure,     // クロージャを作る (関数の定数の番号)
+}
+
+const OPCODES: [Opcode; 29] = [
+    Opcode::Constant,
+    Opcode::Pop,
+    Opcode::Null,
+    Opcode::True,
+    Opcode::False,
+    Opcode::Add,
+    Opcode::Sub,
+    Opcode::Mul,
+    Opcode::Div,
+    Opcode::Equal,
+    Opcode::NotEqual,
+    Opcode::GreaterThan,
+    Opcode::LessThan,
+    Opcode::Minus,
+    Opcode::Bang,
+    Opcode::Jump,
+    Opcode::JumpIfFalse,
+    Opcode::GetGlobal,
+    Opcode::SetGlobal,
+    Opcode::GetLocal,
+    Opcode::SetLocal,
+    Opcode::GetFree,
+    Opcode::Array,
+    Opcode::Hash,
+    Opcode::HashKey,
+    Opcode::Index,
+    Opcode::Call,
+    Opcode::ReturnValue,
+    Opcode::Closure,
+];
+
+impl Opcode {
+    pub fn from_byte(byte: u8) -> Option<Opcode> {
+        OPCODES.get(byte as usize).copied()
+    }
+
+    /// 各オペランドのバイト数
+    pub fn operand_widths(self) -> &'static [usize] {
+        match self {
+            Opcode::Constant
+            | Opcode::Jump
+            | Opcode::JumpIfFalse
+            | Opcode::GetGlobal
+            | Opcode::SetGlobal
+            | Opcode::Array
+            | Opcode::Hash
+            | Opcode::Closure => &[2],
+            Opcode::GetLocal | Opcode::SetLocal | Opcode::GetFree => &[1],
+            Opcode::Call => &[1, 2],
+            _ => &[],
+        }
+    }
+
+    /// オペランドを含めた命令のバイト数
+    pub fn width(self) -> usize {
+        1 + self.operand_widths().iter().sum::<usize>()
+    }
+}
+
+/// 命令を組み立てる (オペランドが幅に収まらなければ None)
+pub fn make(opcode: Opcode, operands: &[usize]) -> Option<Vec<u8>> {
+    let widths = opcode.operand_widths();
+    debug_assert_eq!(widths.len(), operands.len(), "{:?}", opcode);
+
+    let mut instruction = vec![opcode as u8];
+    for (operand, width) in operands.iter().zip(widths) {
+        if *width < std::mem::size_of::<usize>() && *operand >> (width * 8) != 0 {
+            return None;
+        }
+        for i in (0..*width).rev() {
+            instruction.push((operand >> (i * 8)) as u8);
+        }
+    }
+    Some(instruction)
+}
+
+/// 命令の先頭 (オペコードの次) から width バイトのオペランドを読む
+pub fn read_operand(instructions: &[u8], offset: usize, width: usize) -> usize {
+    instructions[offset..offset + width]
+        .iter()
+        .fold(0, |operand, byte| (operand << 8) | *byte as usize)
+}
+
+/// 関数から見た変数の場所
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Variable {
+    Global(usize),
+    Local(usize),
+    Free(usize),
+}
+
+/// コンパイル済みの関数
+///
+/// ローカル変数と捕まえた変数は、束縛されるまでは未束縛で、その間に読むと
+/// fallbacks の場所 (外側で同じ名前の変数) を探す (木構造の評価器が外側の環境を探すのと同じ)。
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompiledFunction {
+    pub instructions: Vec<u8>,
+    pub num_locals: usize,
+    pub num_parameters: usize,
+    /// エラーになりうる命令の位置と、対応するソースの範囲 (位置の昇順)
+    pub positions: Vec<(usize, token::Span)>,
+    /// ローカル変数の名前 (番号順、逆アセンブル用)
+    pub local_names: Vec<String>,
+    /// 捕まえた変数の名前 (番号順、逆アセンブル用)
+    pub free_names: Vec<String>,
+    /// 捕まえる変数の、クロージャを作る関数での場所 (Local か Free、番号順)
+    pub captures: Vec<Variable>,
+    /// 未束縛のローカル変数を読んだときに探す場所 (Global か Free、引数は None)
+    pub local_fallbacks: Vec<Option<Variable>>,
+    /// 未束縛の捕まえた変数を読んだときに探す場所 (Global か より後ろの Free)
+    pub free_fallbacks: Vec<Option<Variable>>,
+    /// 表示用のソース (`Object::inspect` で木構造の評価器の関数と同じ表記にする)
+    pub code: String,
+}
+
+impl CompiledFunction {
+    /// offset の命令に対応するソースの範囲
+    ///
+    /// 位置の表を省いたバイトコードのように、offset 以前の位置が表に無ければ None。
+    pub fn span_at(&self, offset: usize) -> Option<token::Span> {
+        match self
+            .positions
+            .binary_search_by_key(&offset, |(position, _)| *position)
+        {
+            Ok(index) => Some(self.positions[index].1),
+            Err(0) => None,
+            Err(index) => Some(self.positions[index - 1].1),
+        }
+    }
+
+    /// 命令を1行に1つずつ「位置 命令名 オペランド (注釈)」の形で書き出す
+    ///
+    /// 注釈には定数の値や変数の名前を添える。
+    pub fn disassemble(&self, constants: &[Constant], globals: &[String]) -> String {
+        let mut out = String::new();
+        let mut offset = 0;
+        while offset < self.instructions.len() {
+            let byte = self.instructions[offset];
+            let opcode = match Opcode::from_byte(byte) {
+                Some(opcode) => opcode,
+                None => {
+                    out.push_str(&format!("{:04} 不明な命令 ({:#04x})\n", offset, byte));
+                    offset += 1;
+                    continue;
+                }
+            };
+            if offset + opcode.width() > self.instructions.len() {
+                out.push_str(&format!(
+                    "{:04} {:?} (命令が途中で終わっています)\n",
+                    offset, opcode
+                ));
+                break;
+            }
+
+            let mut operands = Vec::new();
+            let mut position = offset + 1;
+            for width in opcode.operand_widths() {
+                operands.push(read_operand(&self.instructions, position, *width));
+                position += width;
+            }
+            let operand_text = operands
+                .iter()
+                .map(|operand| operand.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let line = format!(
+                "{:04} {:<14} {}",
+                offset,
+                format!("{:?}", opcode),
+                operand_text
+            );
+            match self.annotation(opcode, &operands, constants, globals) {
+                Some(note) => out.push_str(&format!("{:<26} ({})\n", line, note)),
+                None => out.push_str(&format!("{}\n", line.trim_end())),
+            }
+            offset += opcode.width();
+        }
+        out
+    }
+
+    fn annotation(
+        &self,
+        opcode: Opcode,
+        operands: &[usize],
+        constants: &[Constant],
+        globals: &[String],
+    ) -> Option<String> {
+        let constant = |index: usize| {
+            constants
+                .get(index)
+                .map(Constant::describe)
+                .unwrap_or_else(|| "範囲外".to_string())
+        };
+        let name = |names: &[String], index: usize| {
+            names
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| "範囲外".to_string())
+        };
+        match opcode {
+            Opcode::Constant | Opcode::Closure => Some(constant(operands[0])),
+            Opcode::Call => Some(constant(operands[1])),
+            Opcode::GetGlobal | Opcode::SetGlobal => Some(name(globals, operands[0])),
+            Opcode::GetLocal | Opcode::SetLocal => Some(name(&self.local_names, operands[0])),
+            Opcode::GetFree => Some(name(&self.free_names, operands[0])),
+            _ => None,
+        }
+    }
+}
+
+/// 定数表に置く値
+#[derive(Debug, PartialEq, Clone)]
+pub enum Constant {
+    Integer(i64),
+    BigInt(bigint::BigInt),
+    Float(f64),
+    String(String),
+    Function(Rc<CompiledFunction>),
+}
+
+impl Constant {
+    /// 逆アセンブルの注釈に使う短い表記 (関数は引数の並びまで)
+    pub fn describe(&self) -> String {
+        match self {
+            Constant::Integer(integer) => integer.to_string(),
+            Constant::BigInt(bigint) => bigint.to_string(),
+            Constant::Float(float) => format!("{:?}", float),
+            Constant::String(string) => format!("{:?}", string),
+            Constant::Function(function) => match function.code.split_once('{') {
+                Some((signature, _)) => signature.to_string(),
+                None => function.code.trim_end().to_string(),
+            },
+        }
+    }
+}
+
+/// コンパイル結果 (トップレベルの命令・定数表・グローバル変数の名前)
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bytecode {
+    pub main: Rc<CompiledFunction>,
+    pub constants: Vec<Constant>,
+    pub globals: Vec<String>,
+}
+
+impl Bytecode {
+    /// トップレベルと定数表の全ての関数を逆アセンブルする
+    pub fn disassemble(&self) -> String {
+        let mut out = String::from("== main ==\n");
+        out.push_str(&self.main.disassemble(&self.constants, &self.globals));
+        for (index, constant) in self.constants.iter().enumerate() {
+            if let Constant::Function(function) = constant {
+                out.push_str(&format!(
+                    "\n== constant {}: {} ==\n",
+                    index,
+                    constant.describe()
+                ));
+                out.push_str(&function.disassemble(&self.constants, &self.globals));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make() {
+        let problem = [
+            (
+                Opcode::Constant,
+                vec![65534],
+                vec![Opcode::Constant as u8, 255, 254],
+            ),
+            (
+                Opcode::GetLocal,
+                vec![255],
+                vec![Opcode::GetLocal as u8, 255],
+            ),
+            (Opcode::Add, vec![], vec![Opcode::Add as u8]),
+            (
+                Opcode::Call,
+                vec![255, 65534],
+                vec![Opcode::Call as u8, 255, 255, 254],
+            ),
+        ];
+
+        for (opcode, operands, expected) in problem {
+            let instruction = make(opcode, &operands).expect("オペランドが大きすぎます");
+            assert_eq!(instruction, expected);
+            assert_eq!(instruction.len(), opcode.width());
+
+            let mut offset = 1;
+            for (operand, width) in operands.iter().zip(opcode.operand_widths()) {
+                assert_eq!(read_operand(&instruction, offset, *width), *operand);
+                offset += width;
+            }
+        }
+
+        assert_eq!(make(Opcode::GetLocal, &[256]), None);
+        assert_eq!(make(Opcode::Constant, &[65536]), None);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let lexer =
+            crate::lexer::Lexer::new("let x = 1; let f = fn(a) { fn() { a + x } }; f(\"s\")");
+        let mut parser = crate::parser::Parser::new(lexer);
+        let program = parser.parse_program().expect("構文エラー");
+        let bytecode = crate::compiler::Compiler::new()
+            .compile(&program)
+            .expect("コンパイルエラー");
+
+        assert_eq!(
+            bytecode.disassemble(),
+            "\
+== main ==
+0000 Constant       0      (1)
+0003 SetGlobal      0      (x)
+0006 Closure        2      (fn(a))
+0009 SetGlobal      1      (f)
+0012 GetGlobal      1      (f)
+0015 Constant       3      (\"s\")
+0018 Call           1 4    (\"f\")
+0022 ReturnValue
+
+== constant 1: fn() ==
+0000 GetFree        0      (a)
+0002 GetGlobal      0      (x)
+0005 Add
+0006 ReturnValue
+
+== constant 2: fn(a) ==
+0000 Closure        1      (fn())
+0003 ReturnValue
+"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_broken() {
+        let function = CompiledFunction {
+            instructions: vec![Opcode::Pop as u8, 255, Opcode::Constant as u8, 0],
+            num_locals: 0,
+            num_parameters: 0,
+            positions: Vec::new(),
+            local_names: Vec::new(),
+            free_names: Vec::new(),
+            captures: Vec::new(),
+            local_fallbacks: Vec::new(),
+            free_fallbacks: Vec::new(),
+            code: String::new(),
+        };
+        assert_eq!(
+            function.disassemble(&[], &[]),
+            "0000 Pop\n0001 不明な命令 (0xff)\n0002 Constant (命令が途中で終わっています)\n"
+        );
+    }
+
+    #[test]
+    fn test_from_byte() {
+        for (byte, opcode) in OPCODES.iter().enumerate() {
+            assert_eq!(*opcode as u8, byte as u8);
+            assert_eq!(Opcode::from_byte(byte as u8), Some(*opcode));
+        }
+        assert_eq!(Opcode::from_byte(OPCODES.len() as u8), None);
+    }
+}
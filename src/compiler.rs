@@ -0,0 +1,738 @@
+use crate::ast;
+use crate::code::{self, Opcode};
+use crate::error;
+use crate::object;
+use crate::operator;
+use crate::resolver;
+use crate::token;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SymbolScope {
+    Global,
+    Local,
+    Free,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Symbol {
+    pub scope: SymbolScope,
+    pub index: usize,
+}
+
+impl Symbol {
+    fn variable(self) -> code::Variable {
+        match self.scope {
+            SymbolScope::Global => code::Variable::Global(self.index),
+            SymbolScope::Local => code::Variable::Local(self.index),
+            SymbolScope::Free => code::Variable::Free(self.index),
+        }
+    }
+}
+
+/// 名前と変数の番号の対応 (関数ごとに1つ作り、外側の関数のものを outer に持つ)
+#[derive(Default)]
+struct SymbolTable {
+    outer: Option<Box<SymbolTable>>,
+    store: HashMap<String, Symbol>,
+    num_definitions: usize,
+    num_parameters: usize,
+    free_symbols: Vec<Symbol>, // 捕まえた変数の、外側のスコープでのシンボル
+    free_names: Vec<String>,   // 捕まえた変数の名前 (番号順)
+    names: Vec<String>,        // グローバル変数・ローカル変数の名前 (番号順)
+    local_fallbacks: Vec<Option<Symbol>>, // 未束縛のローカル変数を読んだときに探す変数
+    free_fallbacks: Vec<Option<Symbol>>, // 未束縛の捕まえた変数を読んだときに探す変数
+}
+
+impl SymbolTable {
+    fn enclosed(outer: SymbolTable) -> Self {
+        SymbolTable {
+            outer: Some(Box::new(outer)),
+            ..SymbolTable::default()
+        }
+    }
+
+    fn scope(&self) -> SymbolScope {
+        if self.outer.is_some() {
+            SymbolScope::Local
+        } else {
+            SymbolScope::Global
+        }
+    }
+
+    /// 同じスコープで束縛済みの名前は同じ番号を使う (評価器の環境の上書きと同じ)
+    fn define(&mut self, name: &str) -> Symbol {
+        match self.store.get(name) {
+            Some(symbol) if symbol.scope == self.scope() => *symbol,
+            _ => self.define_new(name),
+        }
+    }
+
+    /// 同名の引数があっても別の番号を振る (後の引数が見える)
+    fn define_new(&mut self, name: &str) -> Symbol {
+        let symbol = Symbol {
+            scope: self.scope(),
+            index: self.num_definitions,
+        };
+        self.num_definitions += 1;
+        self.names.push(name.to_string());
+        self.local_fallbacks.push(None);
+        self.store.insert(name.to_string(), symbol);
+        symbol
+    }
+
+    /// 引数は呼び出した時点で束縛済みなので、外側を探すことはない
+    fn define_parameter(&mut self, name: &str) -> Symbol {
+        self.num_parameters += 1;
+        self.define_new(name)
+    }
+
+    /// 名前が指す変数
+    ///
+    /// どこにも無い名前はグローバル変数とみなす (後から let される変数や組み込み関数は、
+    /// 実行時にグローバル変数として探す)。let で束縛するローカル変数は、束縛より前に
+    /// 読まれた場合に備えて、外側で同じ名前が指す変数を探す先として用意する。
+    fn resolve(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.store.get(name).copied() {
+            let unbound = symbol.scope == SymbolScope::Local
+                && symbol.index >= self.num_parameters
+                && self.local_fallbacks[symbol.index].is_none();
+            if unbound {
+                let fallback = self.resolve_outer(name);
+                self.local_fallbacks[symbol.index] = Some(fallback);
+            }
+            return symbol;
+        }
+        let symbol = self.resolve_outer(name);
+        if symbol.scope == SymbolScope::Free {
+            self.store.insert(name.to_string(), symbol);
+        }
+        symbol
+    }
+
+    /// この関数の外側で名前が指す変数 (この関数から見たシンボル)
+    fn resolve_outer(&mut self, name: &str) -> Symbol {
+        match self.outer.as_mut() {
+            None => self.define_new(name),
+            Some(outer) => {
+                let symbol = outer.resolve(name);
+                self.capture(name, symbol)
+            }
+        }
+    }
+
+    /// 外側の関数の変数を捕まえる (同じ変数は1度だけ捕まえる)
+    ///
+    /// 外側の変数が未束縛なら外側の関数と同じ先を探すので、その先も捕まえておく。
+    fn capture(&mut self, name: &str, original: Symbol) -> Symbol {
+        if original.scope == SymbolScope::Global {
+            return original;
+        }
+        if let Some(index) = self.free_symbols.iter().position(|free| *free == original) {
+            return Symbol {
+                scope: SymbolScope::Free,
+                index,
+            };
+        }
+        let fallback = self
+            .outer
+            .as_ref()
+            .and_then(|outer| outer.fallback(original));
+        self.free_symbols.push(original);
+        self.free_names.push(name.to_string());
+        self.free_fallbacks.push(None);
+        let index = self.free_symbols.len() - 1;
+        if let Some(fallback) = fallback {
+            self.free_fallbacks[index] = Some(self.capture(name, fallback));
+        }
+        Symbol {
+            scope: SymbolScope::Free,
+            index,
+        }
+    }
+
+    fn fallback(&self, symbol: Symbol) -> Option<Symbol> {
+        match symbol.scope {
+            SymbolScope::Global => None,
+            SymbolScope::Local => self.local_fallbacks[symbol.index],
+            SymbolScope::Free => self.free_fallbacks[symbol.index],
+        }
+    }
+}
+
+/// コンパイル中の関数の命令
+#[derive(Default)]
+struct Scope {
+    instructions: Vec<u8>,
+    positions: Vec<(usize, token::Span)>,
+}
+
+/// `ast::Program` をバイトコードに変換する
+///
+/// グローバル変数と定数表はコンパイルをまたいで引き継ぐ (REPL のように続けて実行できる)。
+pub struct Compiler {
+    constants: Vec<code::Constant>,
+    integer_constants: HashMap<i64, usize>,
+    string_constants: HashMap<String, usize>,
+    symbols: SymbolTable,
+    scopes: Vec<Scope>,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Compiler {
+            constants: Vec::new(),
+            integer_constants: HashMap::new(),
+            string_constants: HashMap::new(),
+            symbols: SymbolTable::default(),
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn compile(
+        &mut self,
+        program: &ast::Program,
+    ) -> Result<code::Bytecode, Box<dyn std::error::Error>> {
+        self.scopes = vec![Scope::default()];
+        let span = match (program.statements.first(), program.statements.last()) {
+            (Some(first), Some(last)) => first.span.to(last.span),
+            _ => token::Span::default(),
+        };
+
+        let compiled = self
+            .compile_block(&program.statements, span)
+            .and_then(|_| self.emit(Opcode::ReturnValue, &[], span));
+        if let Err(err) = compiled {
+            // 関数の途中で失敗した場合はグローバルのスコープに戻す
+            while let Some(outer) = self.symbols.outer.take() {
+                self.symbols = *outer;
+            }
+            return Err(Box::new(err));
+        }
+
+        let scope = self.scopes.pop().unwrap_or_default();
+        Ok(code::Bytecode {
+            main: Rc::new(code::CompiledFunction {
+                instructions: scope.instructions,
+                num_locals: 0,
+                num_parameters: 0,
+                positions: scope.positions,
+                local_names: Vec::new(),
+                free_names: Vec::new(),
+                captures: Vec::new(),
+                local_fallbacks: Vec::new(),
+                free_fallbacks: Vec::new(),
+                code: String::new(),
+            }),
+            constants: self.constants.clone(),
+            globals: self.symbols.names.clone(),
+        })
+    }
+
+    /// 文の並びを、最後の文の値を1つ積むようにコンパイルする
+    fn compile_block(
+        &mut self,
+        statements: &[ast::Statement],
+        span: token::Span,
+    ) -> Result<(), error::CompilerError> {
+        if statements.is_empty() {
+            self.emit(Opcode::Null, &[], span)?;
+        }
+        for (i, statement) in statements.iter().enumerate() {
+            self.compile_statement(statement, i == statements.len() - 1)?;
+        }
+        Ok(())
+    }
+
+    /// keep_value なら文の値を積んだままにする (let は null)
+    fn compile_statement(
+        &mut self,
+        statement: &ast::Statement,
+        keep_value: bool,
+    ) -> Result<(), error::CompilerError> {
+        match &statement.kind {
+            ast::StatementKind::Let { identifier, value } => {
+                let name = if let ast::ExpressionKind::Identifier(name, _) = &identifier.kind {
+                    name
+                } else {
+                    unreachable!();
+                };
+                self.compile_expression(value)?;
+                let symbol = self.symbols.define(name);
+                let opcode = match symbol.scope {
+                    SymbolScope::Global => Opcode::SetGlobal,
+                    _ => Opcode::SetLocal,
+                };
+                self.emit(opcode, &[symbol.index], statement.span)?;
+                if keep_value {
+                    self.emit(Opcode::Null, &[], statement.span)?;
+                }
+            }
+            ast::StatementKind::Return(expression) => {
+                self.compile_expression(expression)?;
+                self.emit(Opcode::ReturnValue, &[], statement.span)?;
+            }
+            ast::StatementKind::Expression(expression) => {
+                self.compile_expression(expression)?;
+                if !keep_value {
+                    self.emit(Opcode::Pop, &[], statement.span)?;
+                }
+            }
+            ast::StatementKind::Block(statements) => {
+                self.compile_block(statements, statement.span)?;
+                if !keep_value {
+                    self.emit(Opcode::Pop, &[], statement.span)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_expression(
+        &mut self,
+        expression: &ast::Expression,
+    ) -> Result<(), error::CompilerError> {
+        let span = expression.span;
+        match &expression.kind {
+            ast::ExpressionKind::Identifier(name, _) => {
+                let symbol = self.symbols.resolve(name);
+                self.load_symbol(symbol, span)?;
+            }
+            ast::ExpressionKind::Integer(integer) => {
+                let index = match self.integer_constants.get(integer) {
+                    Some(index) => *index,
+                    None => {
+                        let index = self.add_constant(code::Constant::Integer(*integer));
+                        self.integer_constants.insert(*integer, index);
+                        index
+                    }
+                };
+                self.emit(Opcode::Constant, &[index], span)?;
+            }
+            ast::ExpressionKind::BigInt(bigint) => {
+                let index = self.add_constant(code::Constant::BigInt(bigint.clone()));
+                self.emit(Opcode::Constant, &[index], span)?;
+            }
+            ast::ExpressionKind::Float(float) => {
+                let index = self.add_constant(code::Constant::Float(*float));
+                self.emit(Opcode::Constant, &[index], span)?;
+            }
+            ast::ExpressionKind::String(string) => {
+                let index = self.add_string_constant(string);
+                self.emit(Opcode::Constant, &[index], span)?;
+            }
+            ast::ExpressionKind::Boolean(true) => {
+                self.emit(Opcode::True, &[], span)?;
+            }
+            ast::ExpressionKind::Boolean(false) => {
+                self.emit(Opcode::False, &[], span)?;
+            }
+            ast::ExpressionKind::PrefixExpression {
+                operator,
+                expression,
+            } => {
+                self.compile_expression(expression)?;
+                let opcode = match operator {
+                    operator::Prefix::Minus => Opcode::Minus,
+                    operator::Prefix::Exclamation => Opcode::Bang,
+                };
+                self.emit(opcode, &[], span)?;
+            }
+            ast::ExpressionKind::InfixExpression {
+                left,
+                operator,
+                right,
+            } => {
+                self.compile_expression(left)?;
+                self.compile_expression(right)?;
+                let opcode = match operator {
+                    operator::Infix::Plus => Opcode::Add,
+                    operator::Infix::Minus => Opcode::Sub,
+                    operator::Infix::Asterisk => Opcode::Mul,
+                    operator::Infix::Slash => Opcode::Div,
+                    operator::Infix::LessThan => Opcode::LessThan,
+                    operator::Infix::GreaterThan => Opcode::GreaterThan,
+                    operator::Infix::Equal => Opcode::Equal,
+                    operator::Infix::NotEqual => Opcode::NotEqual,
+                };
+                self.emit(opcode, &[], span)?;
+            }
+            ast::ExpressionKind::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.compile_expression(condition)?;
+                let jump_if_false = self.emit(Opcode::JumpIfFalse, &[0], span)?;
+                self.compile_statement(consequence, true)?;
+                let jump = self.emit(Opcode::Jump, &[0], span)?;
+                self.patch_jump(jump_if_false, span)?;
+                match alternative {
+                    Some(alternative) => self.compile_statement(alternative, true)?,
+                    None => {
+                        self.emit(Opcode::Null, &[], span)?;
+                    }
+                }
+                self.patch_jump(jump, span)?;
+            }
+            ast::ExpressionKind::Function {
+                parameters, body, ..
+            } => {
+                self.compile_function(parameters, body, span)?;
+            }
+            ast::ExpressionKind::Call { function, args } => {
+                self.compile_expression(function)?;
+                for arg in args {
+                    self.compile_expression(arg)?;
+                }
+                // 引数の数が合わない場合のエラーメッセージに使う
+                let callee = self.add_string_constant(&function.to_code());
+                self.emit(Opcode::Call, &[args.len(), callee], span)?;
+            }
+            ast::ExpressionKind::Array(elements) => {
+                for element in elements {
+                    self.compile_expression(element)?;
+                }
+                self.emit(Opcode::Array, &[elements.len()], span)?;
+            }
+            ast::ExpressionKind::Hash(pairs) => {
+                // 評価器と同じく、値を評価する前にキーを確かめる
+                for (key, value) in pairs {
+                    self.compile_expression(key)?;
+                    self.emit(Opcode::HashKey, &[], key.span)?;
+                    self.compile_expression(value)?;
+                }
+                self.emit(Opcode::Hash, &[pairs.len()], span)?;
+            }
+            ast::ExpressionKind::Index { left, index } => {
+                self.compile_expression(left)?;
+                self.compile_expression(index)?;
+                self.emit(Opcode::Index, &[], span)?;
+            }
+            ast::ExpressionKind::Illegal => {
+                self.emit(Opcode::Null, &[], span)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_function(
+        &mut self,
+        parameters: &[ast::Expression],
+        body: &ast::Statement,
+        span: token::Span,
+    ) -> Result<(), error::CompilerError> {
+        self.scopes.push(Scope::default());
+        let outer = std::mem::take(&mut self.symbols);
+        self.symbols = SymbolTable::enclosed(outer);
+        for parameter in parameters {
+            if let ast::ExpressionKind::Identifier(name, _) = &parameter.kind {
+                self.symbols.define_parameter(name);
+            } else {
+                unreachable!();
+            }
+        }
+        // 関数の中の let はどこにあっても関数全体で同じ変数を指す (評価器の環境と同じ)
+        for name in resolver::declared_names(body) {
+            self.symbols.define(&name);
+        }
+
+        self.compile_statement(body, true)?;
+        self.emit(Opcode::ReturnValue, &[], body.span)?;
+
+        let scope = self.scopes.pop().unwrap_or_default();
+        let outer = self
+            .symbols
+            .outer
+            .take()
+            .expect("外側のスコープがありません");
+        let symbols = std::mem::replace(&mut self.symbols, *outer);
+        let function = code::CompiledFunction {
+            instructions: scope.instructions,
+            num_locals: symbols.num_definitions,
+            num_parameters: parameters.len(),
+            positions: scope.positions,
+            local_names: symbols.names,
+            free_names: symbols.free_names,
+            captures: symbols
+                .free_symbols
+                .iter()
+                .map(|free| free.variable())
+                .collect(),
+            local_fallbacks: variables(&symbols.local_fallbacks),
+            free_fallbacks: variables(&symbols.free_fallbacks),
+            code: object::function_code(parameters, body),
+        };
+        let index = self.add_constant(code::Constant::Function(Rc::new(function)));
+        self.emit(Opcode::Closure, &[index], span)?;
+        Ok(())
+    }
+
+    fn load_symbol(
+        &mut self,
+        symbol: Symbol,
+        span: token::Span,
+    ) -> Result<(), error::CompilerError> {
+        match symbol.scope {
+            SymbolScope::Global => self.emit(Opcode::GetGlobal, &[symbol.index], span)?,
+            SymbolScope::Local => self.emit(Opcode::GetLocal, &[symbol.index], span)?,
+            SymbolScope::Free => self.emit(Opcode::GetFree, &[symbol.index], span)?,
+        };
+        Ok(())
+    }
+
+    fn add_constant(&mut self, constant: code::Constant) -> usize {
+        self.constants.push(constant);
+        self.constants.len() - 1
+    }
+
+    fn add_string_constant(&mut self, string: &str) -> usize {
+        if let Some(index) = self.string_constants.get(string) {
+            return *index;
+        }
+        let index = self.add_constant(code::Constant::String(string.to_string()));
+        self.string_constants.insert(string.to_string(), index);
+        index
+    }
+
+    /// 命令を書き足してその位置を返す。span はエラーの表示に使う
+    fn emit(
+        &mut self,
+        opcode: Opcode,
+        operands: &[usize],
+        span: token::Span,
+    ) -> Result<usize, error::CompilerError> {
+        let instruction = code::make(opcode, operands).ok_or_else(|| {
+            let operand = operands
+                .iter()
+                .zip(opcode.operand_widths())
+                .find(|(operand, width)| **operand >> (**width * 8) != 0)
+                .map_or(0, |(operand, _)| *operand);
+            error::CompilerError::OperandOverflow {
+                opcode,
+                operand,
+                span,
+            }
+        })?;
+
+        let scope = self.scopes.last_mut().expect("スコープがありません");
+        let position = scope.instructions.len();
+        if scope.positions.last().map(|(_, last)| *last) != Some(span) {
+            scope.positions.push((position, span));
+        }
+        scope.instructions.extend(instruction);
+        Ok(position)
+    }
+
+    /// position の飛び先を現在の位置にする
+    fn patch_jump(
+        &mut self,
+        position: usize,
+        span: token::Span,
+    ) -> Result<(), error::CompilerError> {
+        let scope = self.scopes.last_mut().expect("スコープがありません");
+        let target = scope.instructions.len();
+        let opcode = Opcode::from_byte(scope.instructions[position]).expect("命令ではありません");
+        let instruction =
+            code::make(opcode, &[target]).ok_or(error::CompilerError::OperandOverflow {
+                opcode,
+                operand: target,
+                span,
+            })?;
+        scope.instructions[position..position + instruction.len()].copy_from_slice(&instruction);
+        Ok(())
+    }
+}
+
+fn variables(symbols: &[Option<Symbol>]) -> Vec<Option<code::Variable>> {
+    symbols
+        .iter()
+        .map(|symbol| symbol.map(Symbol::variable))
+        .collect()
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Compiler::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::message::{self, Locale};
+    use crate::parser;
+
+    fn compile(input: &str) -> code::Bytecode {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = parser::Parser::new(lexer);
+        let program = parser.parse_program().expect("parser error");
+        Compiler::new().compile(&program).expect("compiler error")
+    }
+
+    fn instructions(instructions: &[(Opcode, &[usize])]) -> Vec<u8> {
+        instructions
+            .iter()
+            .flat_map(|(opcode, operands)| code::make(*opcode, operands).expect("make"))
+            .collect()
+    }
+
+    #[test]
+    fn test_compile_expression() {
+        let bytecode = compile("1 + 2; -3 == 1");
+        assert_eq!(
+            bytecode.main.instructions,
+            instructions(&[
+                (Opcode::Constant, &[0]),
+                (Opcode::Constant, &[1]),
+                (Opcode::Add, &[]),
+                (Opcode::Pop, &[]),
+                (Opcode::Constant, &[2]),
+                (Opcode::Minus, &[]),
+                (Opcode::Constant, &[0]),
+                (Opcode::Equal, &[]),
+                (Opcode::ReturnValue, &[]),
+            ])
+        );
+        assert_eq!(
+            bytecode.constants,
+            vec![
+                code::Constant::Integer(1),
+                code::Constant::Integer(2),
+                code::Constant::Integer(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compile_if() {
+        let bytecode = compile("if (true) { 10 }; 3333;");
+        assert_eq!(
+            bytecode.main.instructions,
+            instructions(&[
+                (Opcode::True, &[]),          // 0000
+                (Opcode::JumpIfFalse, &[10]), // 0001
+                (Opcode::Constant, &[0]),     // 0004
+                (Opcode::Jump, &[11]),        // 0007
+                (Opcode::Null, &[]),          // 0010
+                (Opcode::Pop, &[]),           // 0011
+                (Opcode::Constant, &[1]),     // 0012
+                (Opcode::ReturnValue, &[]),   // 0015
+            ])
+        );
+    }
+
+    #[test]
+    fn test_compile_global_let() {
+        let bytecode = compile("let one = 1; let two = one; let one = two; len");
+        assert_eq!(
+            bytecode.main.instructions,
+            instructions(&[
+                (Opcode::Constant, &[0]),
+                (Opcode::SetGlobal, &[0]),
+                (Opcode::GetGlobal, &[0]),
+                (Opcode::SetGlobal, &[1]),
+                (Opcode::GetGlobal, &[1]),
+                (Opcode::SetGlobal, &[0]),
+                (Opcode::GetGlobal, &[2]),
+                (Opcode::ReturnValue, &[]),
+            ])
+        );
+        assert_eq!(bytecode.globals, vec!["one", "two", "len"]);
+    }
+
+    #[test]
+    fn test_compile_closure() {
+        let bytecode = compile("fn(a) { let f = fn(b) { a + b + f(b) }; f }");
+        let function = |index: usize| match &bytecode.constants[index] {
+            code::Constant::Function(function) => function.clone(),
+            other => panic!("関数を期待しましたが、{:?}でした。", other),
+        };
+
+        assert_eq!(
+            bytecode.constants[0],
+            code::Constant::String("f".to_string())
+        );
+        let inner = function(1);
+        assert_eq!(
+            inner.instructions,
+            instructions(&[
+                (Opcode::GetFree, &[0]),
+                (Opcode::GetLocal, &[0]),
+                (Opcode::Add, &[]),
+                (Opcode::GetFree, &[1]),
+                (Opcode::GetLocal, &[0]),
+                (Opcode::Call, &[1, 0]),
+                (Opcode::Add, &[]),
+                (Opcode::ReturnValue, &[]),
+            ])
+        );
+        assert_eq!(inner.local_names, vec!["b"]);
+        assert_eq!(inner.free_names, vec!["a", "f"]);
+        // f は外側の関数のローカル変数を参照で捕まえ、束縛される前ならグローバル変数を探す
+        assert_eq!(
+            inner.captures,
+            vec![code::Variable::Local(0), code::Variable::Local(1)]
+        );
+        assert_eq!(
+            inner.free_fallbacks,
+            vec![None, Some(code::Variable::Global(0))]
+        );
+        assert_eq!(bytecode.globals, vec!["f"]);
+
+        let outer = function(2);
+        assert_eq!(outer.num_locals, 2);
+        assert_eq!(outer.local_names, vec!["a", "f"]);
+        assert_eq!(outer.num_parameters, 1);
+        assert_eq!(
+            outer.local_fallbacks,
+            vec![None, Some(code::Variable::Global(0))]
+        );
+        assert_eq!(
+            outer.instructions,
+            instructions(&[
+                (Opcode::Closure, &[1]),
+                (Opcode::SetLocal, &[1]),
+                (Opcode::GetLocal, &[1]),
+                (Opcode::ReturnValue, &[]),
+            ])
+        );
+        assert_eq!(
+            outer.code,
+            "fn(a){\nlet f = fn(b){\n((a + b) + f(b));\n};\nf;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_compile_error() {
+        message::set_locale(Locale::Ja);
+        // 識別子に数字は使えないので vaa, vab, ... と名付ける
+        let lets = (0..300)
+            .map(|i| {
+                let first = (b'a' + (i / 26) as u8) as char;
+                let second = (b'a' + (i % 26) as u8) as char;
+                format!("let v{}{} = 1;", first, second)
+            })
+            .collect::<Vec<_>>();
+        let input = format!("fn() {{ {} }}", lets.join("\n"));
+        let lexer = lexer::Lexer::new(&input);
+        let mut parser = parser::Parser::new(lexer);
+        let program = parser.parse_program().expect("parser error");
+
+        let mut compiler = Compiler::new();
+        match compiler.compile(&program) {
+            Ok(_) => panic!("エラーを期待しましたが、成功しました。"),
+            Err(err) => assert_eq!(
+                err.to_string(),
+                "257:1: SetLocal のオペランドが大きすぎます。: 256"
+            ),
+        }
+        // 失敗した後も続けてコンパイルできる
+        let program = parser::Parser::new(lexer::Lexer::new("let a = 1;"))
+            .parse_program()
+            .expect("parser error");
+        assert!(compiler.compile(&program).is_ok());
+    }
+}
@@ -0,0 +1,528 @@
+use crate::error;
+use crate::token;
+use std::io::IsTerminal;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// 表示用にエラーから取り出した情報
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    /// ソース中の範囲 (バイトコードのファイルの読み込みエラーのように、ソースに位置が無ければ None)
+    pub span: Option<token::Span>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// `Interpreter::eval_str` などが返すエラーから取り出す
+    /// (ParserError・CompilerError・EvaluatorError・UnlocatedError・BytecodeFileError 以外は None)
+    pub fn from_error(error: &(dyn std::error::Error + 'static)) -> Option<Diagnostic> {
+        if let Some(error) = error.downcast_ref::<error::ParserError<'static>>() {
+            return Some(error.into());
+        }
+        if let Some(error) = error.downcast_ref::<error::BytecodeFileError>() {
+            return Some(error.into());
+        }
+        if let Some(error::UnlocatedError(error)) = error.downcast_ref() {
+            return Some(Diagnostic {
+                span: None,
+                ..error.into()
+            });
+        }
+        if let Some(error) = error.downcast_ref::<error::CompilerError>() {
+            return Some(error.into());
+        }
+        error
+            .downcast_ref::<error::EvaluatorError>()
+            .map(|error| error.into())
+    }
+
+    /// 1行の JSON にする (JSON Lines 形式の1行分、改行は含まない)
+    ///
+    /// ```text
+    /// {"code":"P0001","severity":"error","message":"...","span":{"start":{"offset":17,"line":2,"column":7},"end":{...}},"file":"main.monkey"}
+    /// ```
+    ///
+    /// 位置が無ければ `"span":null` になる。
+    pub fn to_json(&self, file: Option<&str>) -> String {
+        let span = self.span.map_or("null".to_string(), |span| {
+            format!(
+                "{{\"start\":{},\"end\":{}}}",
+                json_position(span.start),
+                json_position(span.end)
+            )
+        });
+        format!(
+            "{{\"code\":{},\"severity\":{},\"message\":{},\"span\":{},\"file\":{}}}",
+            json_string(self.code),
+            json_string(&self.severity.to_string()),
+            json_string(&self.message),
+            span,
+            file.map_or("null".to_string(), json_string)
+        )
+    }
+}
+
+impl From<&error::ParserError<'_>> for Diagnostic {
+    fn from(error: &error::ParserError<'_>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: error.code(),
+            message: error.message(),
+            span: Some(error.span()),
+            help: error.help(),
+        }
+    }
+}
+
+impl From<&error::CompilerError> for Diagnostic {
+    fn from(error: &error::CompilerError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: error.code(),
+            message: error.message(),
+            span: Some(error.span()),
+            help: error.help(),
+        }
+    }
+}
+
+impl From<&error::EvaluatorError> for Diagnostic {
+    fn from(error: &error::EvaluatorError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: error.code(),
+            message: error.message(),
+            span: Some(error.span()),
+            help: error.help(),
+        }
+    }
+}
+
+impl From<&error::BytecodeFileError> for Diagnostic {
+    fn from(error: &error::BytecodeFileError) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: error.code(),
+            message: error.to_string(),
+            span: None,
+            help: None,
+        }
+    }
+}
+
+/// rustc 風に、該当行とその下のキャレットでエラー箇所を示す
+///
+/// ```text
+/// error[P0001]: Assignを期待しましたが、Integer(6)でした。
+///  --> main.monkey:2:7
+///   |
+/// 2 | let y 6;
+///   |       ^
+///   = help: ここに Assign が必要です。
+/// ```
+///
+/// ソースが無い (`without_source`) か診断に位置が無ければ、該当行を省いて
+/// ` --> ` の行までを表示する。
+pub struct Renderer<'a> {
+    source: Option<&'a str>,
+    file: Option<&'a str>,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Renderer {
+            source: Some(source),
+            file: None,
+            color: false,
+        }
+    }
+
+    /// 読み込んだバイトコードのように、ソースの無いプログラムの診断を表示する
+    pub fn without_source() -> Self {
+        Renderer {
+            source: None,
+            file: None,
+            color: false,
+        }
+    }
+
+    /// ` --> ` の行にファイル名を付ける
+    pub fn with_file(mut self, file: &'a str) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// ANSI エスケープシーケンスで色を付ける
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let header = format!(
+            "{}: {}",
+            self.paint(
+                RED,
+                &format!("{}[{}]", diagnostic.severity, diagnostic.code)
+            ),
+            self.paint(BOLD, &diagnostic.message)
+        );
+        let (span, source) = match (diagnostic.span, self.source) {
+            (Some(span), Some(source)) => (span, source),
+            (span, _) => return self.render_location(header, span, diagnostic),
+        };
+        let start = span.start;
+        let end = span.end;
+        let line = source.lines().nth(start.line - 1).unwrap_or("");
+        let gutter = " ".repeat(start.line.to_string().len());
+
+        // 先頭からキャレットの位置までの空白 (タブと全角文字の幅を合わせる)
+        let padding: String = line
+            .chars()
+            .take(start.column - 1)
+            .map(|c| match c {
+                '\t' => "\t",
+                c if is_wide(c) => "  ",
+                _ => " ",
+            })
+            .collect();
+        // 複数行にまたがる場合は行末まで
+        let underlined = if end.line == start.line {
+            end.column.saturating_sub(start.column)
+        } else {
+            line.chars().count().saturating_sub(start.column - 1)
+        };
+        let width: usize = line
+            .chars()
+            .skip(start.column - 1)
+            .take(underlined)
+            .map(|c| if is_wide(c) { 2 } else { 1 })
+            .sum();
+        let carets = "^".repeat(width.max(1));
+
+        let location = match self.file {
+            Some(file) => format!("{}:{}", file, start),
+            None => start.to_string(),
+        };
+
+        let mut lines = vec![
+            header,
+            format!("{}{} {}", gutter, self.paint(BLUE, "-->"), location),
+            format!("{} {}", gutter, self.paint(BLUE, "|")),
+            format!(
+                "{} {}",
+                self.paint(BLUE, &format!("{} |", start.line)),
+                line
+            ),
+            format!(
+                "{} {} {}{}",
+                gutter,
+                self.paint(BLUE, "|"),
+                padding,
+                self.paint(RED, &carets)
+            ),
+        ];
+        lines.extend(self.help(&gutter, diagnostic));
+        lines.join("\n")
+    }
+
+    /// 該当行を省いて、見出しと位置 (あれば) だけを表示する
+    fn render_location(
+        &self,
+        header: String,
+        span: Option<token::Span>,
+        diagnostic: &Diagnostic,
+    ) -> String {
+        let location = match (self.file, span) {
+            (Some(file), Some(span)) => Some(format!("{}:{}", file, span.start)),
+            (Some(file), None) => Some(file.to_string()),
+            (None, Some(span)) => Some(span.start.to_string()),
+            (None, None) => None,
+        };
+        let mut lines = vec![header];
+        if let Some(location) = location {
+            lines.push(format!(" {} {}", self.paint(BLUE, "-->"), location));
+        }
+        lines.extend(self.help(" ", diagnostic));
+        lines.join("\n")
+    }
+
+    fn help(&self, gutter: &str, diagnostic: &Diagnostic) -> Option<String> {
+        diagnostic.help.as_ref().map(|help| {
+            format!(
+                "{} {} {}",
+                gutter,
+                self.paint(BLUE, "="),
+                self.paint(BOLD, &format!("help: {}", help))
+            )
+        })
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// 診断を JSON Lines 形式で並べる (1件につき1行)
+pub fn to_json_lines(diagnostics: &[Diagnostic], file: Option<&str>) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.to_json(file) + "\n")
+        .collect()
+}
+
+fn json_position(position: token::Position) -> String {
+    format!(
+        "{{\"offset\":{},\"line\":{},\"column\":{}}}",
+        position.offset, position.line, position.column
+    )
+}
+
+fn json_string(string: &str) -> String {
+    let mut json = String::from('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// 標準出力が端末で、環境変数 NO_COLOR が無ければ色を付ける
+pub fn color_enabled() -> bool {
+    color_enabled_for(&std::io::stdout())
+}
+
+/// 出力先が端末で、環境変数 NO_COLOR が無ければ色を付ける
+pub fn color_enabled_for<T: IsTerminal>(stream: &T) -> bool {
+    stream.is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// 端末上で 2 桁分の幅を取る文字 (CJK・全角記号など)
+pub(crate) fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x20000..=0x3FFFD)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter;
+    use crate::lexer;
+    use crate::message::{self, Locale};
+    use crate::parser;
+
+    fn parse_error(input: &str) -> Diagnostic {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = parser::Parser::new(lexer);
+        let (_, errors) = parser.parse_program_with_errors();
+        Diagnostic::from(&errors[0])
+    }
+
+    #[test]
+    fn test_render_parser_error() {
+        message::set_locale(Locale::Ja);
+        let input = "let x = 5;\nlet y 6;";
+        let rendered = Renderer::new(input)
+            .with_file("main.monkey")
+            .render(&parse_error(input));
+        assert_eq!(
+            rendered,
+            [
+                "error[P0001]: Assignを期待しましたが、Integer(6)でした。",
+                " --> main.monkey:2:7",
+                "  |",
+                "2 | let y 6;",
+                "  |       ^",
+                "  = help: ここに Assign が必要です。",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_evaluator_error() {
+        message::set_locale(Locale::Ja);
+        let input = "\n\n\n\n\n\n\n\n\n\"あい\" + 5 - 1";
+        let error = interpreter::Interpreter::new().eval_str(input).unwrap_err();
+        let diagnostic = Diagnostic::from_error(error.as_ref()).unwrap();
+        assert_eq!(
+            Renderer::new(input).render(&diagnostic),
+            [
+                "error[E0001]: 型のミスマッチ: \"あい\" + 5",
+                "  --> 10:1",
+                "   |",
+                "10 | \"あい\" + 5 - 1",
+                "   | ^^^^^^^^^^",
+                "   = help: 演算子の両辺の型を揃えてください。",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_without_help() {
+        message::set_locale(Locale::Ja);
+        let input = "\tlet x = 1 + ;";
+        let rendered = Renderer::new(input).render(&parse_error(input));
+        assert_eq!(
+            rendered,
+            [
+                "error[P0006]: 式のパーサーが未実装です。",
+                " --> 1:14",
+                "  |",
+                "1 | \tlet x = 1 + ;",
+                "  | \t            ^",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_with_color() {
+        let input = "let = 5;";
+        let rendered = Renderer::new(input)
+            .with_color(true)
+            .render(&parse_error(input));
+        assert!(rendered.starts_with("\x1b[1;31merror[P0003]\x1b[0m: "));
+        assert!(rendered.contains("\x1b[1;31m^\x1b[0m"));
+    }
+
+    #[test]
+    fn test_render_without_source() {
+        message::set_locale(Locale::Ja);
+        // 読み込んだバイトコードのように、ソースが無ければ位置だけを示す
+        let error = interpreter::Interpreter::new()
+            .eval_str("\n1 + true")
+            .unwrap_err();
+        let diagnostic = Diagnostic::from_error(error.as_ref()).unwrap();
+        assert_eq!(
+            Renderer::without_source()
+                .with_file("main.mkbc")
+                .render(&diagnostic),
+            [
+                "error[E0001]: 型のミスマッチ: 1 + true",
+                " --> main.mkbc:2:1",
+                "  = help: 演算子の両辺の型を揃えてください。",
+            ]
+            .join("\n")
+        );
+
+        // 位置の無い診断はソースがあっても該当行を示さない
+        let diagnostic = Diagnostic::from(&error::BytecodeFileError::InvalidMagic);
+        assert_eq!(
+            Renderer::new("1").with_file("a.mkbc").render(&diagnostic),
+            "error[B0001]: バイトコードのファイルではありません。\n --> a.mkbc"
+        );
+        assert_eq!(
+            Renderer::without_source().render(&diagnostic),
+            "error[B0001]: バイトコードのファイルではありません。"
+        );
+    }
+
+    #[test]
+    fn test_from_error() {
+        let error: Box<dyn std::error::Error> =
+            Box::new(error::LexerError::InvalidNumber("1e".to_string()));
+        assert_eq!(Diagnostic::from_error(error.as_ref()), None);
+
+        let error: Box<dyn std::error::Error> = Box::new(error::BytecodeFileError::Truncated(3));
+        let diagnostic = Diagnostic::from_error(error.as_ref()).unwrap();
+        assert_eq!((diagnostic.code, diagnostic.span), ("B0003", None));
+    }
+
+    #[test]
+    fn test_to_json() {
+        message::set_locale(Locale::Ja);
+        let input = "let x = 5;\nlet y 6;";
+        let diagnostic = parse_error(input);
+        assert_eq!(
+            diagnostic.to_json(Some("main.monkey")),
+            concat!(
+                r#"{"code":"P0001","severity":"error","#,
+                r#""message":"Assignを期待しましたが、Integer(6)でした。","#,
+                r#""span":{"start":{"offset":17,"line":2,"column":7},"#,
+                r#""end":{"offset":18,"line":2,"column":8}},"#,
+                r#""file":"main.monkey"}"#
+            )
+        );
+
+        let diagnostic = Diagnostic {
+            message: "\"a\\b\"\n\u{1}".to_string(),
+            ..diagnostic
+        };
+        assert!(diagnostic
+            .to_json(None)
+            .contains(r#""message":"\"a\\b\"\n\u0001","#));
+        assert!(diagnostic.to_json(None).ends_with(r#""file":null}"#));
+
+        let diagnostic = Diagnostic {
+            span: None,
+            ..diagnostic
+        };
+        assert!(diagnostic
+            .to_json(None)
+            .ends_with(r#""span":null,"file":null}"#));
+    }
+
+    #[test]
+    fn test_to_json_lines() {
+        let input = "let = 1;\nlet y 2;";
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = parser::Parser::new(lexer);
+        let (_, errors) = parser.parse_program_with_errors();
+        let diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+
+        let lines = to_json_lines(&diagnostics, Some("a.monkey"));
+        let codes: Vec<&str> = lines
+            .lines()
+            .map(|line| &line[9..14]) // {"code":"P0003" の P0003
+            .collect();
+        assert_eq!(codes, vec!["P0003", "P0001"]);
+        assert!(lines.ends_with("}\n"));
+        assert_eq!(to_json_lines(&[], None), "");
+    }
+}
@@ -1,16 +1,86 @@
+use crate::ast;
 use crate::object;
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::collections::HashMap;
+use std::{cell::RefCell, rc::Rc};
 
+/// スロット番号順の名前と、名前からスロット番号への索引
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SlotNames {
+    names: Vec<String>,
+    slots: HashMap<String, usize>,
+    fallbacks: Vec<Option<ast::Resolution>>, // 未束縛のスロットを読んだときに探す、外側の環境での場所
+}
+
+impl SlotNames {
+    pub fn new(names: Vec<String>) -> Self {
+        let mut slot_names = SlotNames::default();
+        for name in names {
+            slot_names.declare(name);
+        }
+        slot_names
+    }
+
+    /// 関数のスコープの名前を、それぞれの名前の外側のスコープでの場所とともに作る
+    ///
+    /// 外側に無い名前 (None) は、実行時に外側の環境を名前で探す。
+    pub fn with_fallbacks(
+        names: Vec<String>,
+        fallback: impl Fn(&str) -> Option<ast::Resolution>,
+    ) -> Self {
+        let mut slot_names = SlotNames::new(names);
+        slot_names.fallbacks = slot_names.names.iter().map(|name| fallback(name)).collect();
+        slot_names
+    }
+
+    /// 名前のスロット番号を返す (無ければ新しく振る)
+    pub fn declare(&mut self, name: String) -> usize {
+        if let Some(slot) = self.lookup(&name) {
+            return slot;
+        }
+        self.slots.insert(name.clone(), self.names.len());
+        self.names.push(name);
+        self.names.len() - 1
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<usize> {
+        self.slots.get(name).copied()
+    }
+
+    /// slot 番目の名前の、外側の環境での場所
+    pub fn fallback(&self, slot: usize) -> Option<ast::Resolution> {
+        self.fallbacks.get(slot).copied().flatten()
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// 変数の環境
+///
+/// 束縛はスロット番号で引ける配列に持つ。静的解決した識別子はスロット番号で、
+/// そうでない識別子やホストからの操作は名前で読み書きする。
+/// 関数のスコープの環境は静的解決した SlotNames から作るので、スロットの並びは解決時と同じになる。
 #[derive(Debug, Clone, PartialEq)]
 pub struct Environment {
-    store: HashMap<String, object::Object>,
+    names: Rc<SlotNames>,
+    slots: Vec<Option<object::Object>>,
     outer: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
     pub fn new() -> Self {
         Environment {
-            store: HashMap::new(),
+            names: Rc::new(SlotNames::default()),
+            slots: Vec::new(),
             outer: None,
         }
     }
@@ -21,20 +91,93 @@ impl Environment {
         environment
     }
 
-    pub fn get(&self, name: String) -> Option<object::Object> {
-        match self.store.get(&name) {
-            Some(value) => Some(value.clone()),
-            None => {
-                if let Some(outer) = &self.outer {
-                    outer.borrow().get(name)
-                } else {
-                    None
-                }
-            }
+    /// 静的解決した関数のスコープの環境を作る (スロットはすべて未束縛)
+    pub fn with_slots(names: Rc<SlotNames>, outer: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            slots: vec![None; names.len()],
+            names,
+            outer: Some(outer),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<object::Object> {
+        match self
+            .names
+            .lookup(name)
+            .and_then(|slot| self.slots[slot].clone())
+        {
+            Some(value) => Some(value),
+            None => self
+                .outer
+                .as_ref()
+                .and_then(|outer| outer.borrow().get(name)),
+        }
+    }
+
+    /// depth 個外側の環境の slot 番目にある name の値
+    ///
+    /// まだ束縛されていなければ、外側の環境で同じ名前の変数を探す
+    /// (束縛より前の参照を、名前で引く場合と同じ結果にするため)。外側での場所は
+    /// 静的解決で求めてあり、外側のスコープに無かった名前だけを名前で探す。
+    pub fn get_slot(&self, depth: usize, slot: usize, name: &str) -> Option<object::Object> {
+        if depth > 0 {
+            let outer = self
+                .outer
+                .as_ref()
+                .expect("静的解決したスコープの環境がありません。");
+            return outer.borrow().get_slot(depth - 1, slot, name);
+        }
+        if let Some(value) = &self.slots[slot] {
+            return Some(value.clone());
+        }
+        let outer = self.outer.as_ref()?.borrow();
+        match self.names.fallback(slot) {
+            Some(fallback) => outer.get_slot(fallback.depth, fallback.slot, name),
+            None => outer.get(name),
         }
     }
 
     pub fn set(&mut self, name: String, value: object::Object) {
-        self.store.insert(name, value);
+        let slot = self.declare(name);
+        self.slots[slot] = Some(value);
+    }
+
+    /// slot 番目に束縛する
+    pub fn set_slot(&mut self, slot: usize, value: object::Object) {
+        self.slots[slot] = Some(value);
+    }
+
+    /// この環境に名前のスロットを用意して、その番号を返す (既にあればその番号)
+    pub fn declare(&mut self, name: String) -> usize {
+        if let Some(slot) = self.names.lookup(&name) {
+            return slot;
+        }
+        let slot = Rc::make_mut(&mut self.names).declare(name);
+        self.slots.push(None);
+        slot
+    }
+
+    /// この環境のスロット番号順の名前 (束縛されていないものも含む)
+    pub fn names(&self) -> &[String] {
+        self.names.names()
+    }
+
+    /// この環境自身の束縛を名前順で返す (外側の環境は含まない)
+    pub fn bindings(&self) -> Vec<(String, object::Object)> {
+        let mut bindings = self
+            .names
+            .names()
+            .iter()
+            .zip(self.slots.iter())
+            .filter_map(|(name, value)| Some((name.clone(), value.clone()?)))
+            .collect::<Vec<_>>();
+        bindings.sort_by(|(left, _), (right, _)| left.cmp(right));
+        bindings
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Environment::new()
     }
 }
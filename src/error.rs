@@ -1,22 +1,39 @@
+use crate::code;
+use crate::message::Message;
 use crate::object;
 use crate::operator;
 use crate::token;
 
 // anyhow, thiserror を利用すれば楽できるが、ローカル環境で git が通らないので妥協
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexerError {
+    InvalidNumber(String),
+    UnterminatedBlockComment,
+    UnterminatedString,
+    InvalidEscape(Box<str>), // String だと Token と ParserError が大きくなる
+}
+
 #[derive(Debug)]
 pub enum ParserError<'a> {
     UnexpectedToken {
         actual_token: token::Token,
         expected_token: token::Token,
+        span: token::Span,
     },
     NotFoundInfixToken {
         found_token: token::Token,
+        span: token::Span,
     },
     NotFoundLetIdentifier {
         found_token: token::Token,
+        span: token::Span,
+    },
+    InvalidToken {
+        error: LexerError,
+        span: token::Span,
     },
-    UnImplementationStatemant(&'a str),
-    UnImplementationParser(&'a str),
+    UnImplementationStatemant(&'a str, token::Span),
+    UnImplementationParser(&'a str, token::Span),
 }
 
 #[derive(Debug)]
@@ -25,86 +42,882 @@ pub enum EvaluatorError {
         left: object::Object,
         operator: operator::Infix,
         right: object::Object,
+        span: token::Span,
     },
     UnknowInfixOperator {
         left: object::Object,
         operator: operator::Infix,
         right: object::Object,
+        span: token::Span,
     },
     UnknowPrefixOperator {
         operator: operator::Prefix,
         right: object::Object,
+        span: token::Span,
     },
     NotFoundIdentifier {
         identifier: String,
+        span: token::Span,
+    },
+    UnknowIndexOperator {
+        left: object::Object,
+        index: object::Object,
+        span: token::Span,
+    },
+    IndexOutOfRange {
+        index: i64,
+        length: usize,
+        span: token::Span,
+    },
+    UnhashableKey {
+        key: object::Object,
+        span: token::Span,
+    },
+    ArityMismatch {
+        function: String,
+        expected: usize,
+        actual: usize,
+        span: token::Span,
+    },
+    UnsupportedArgument {
+        function: String,
+        argument: object::Object,
+        span: token::Span,
+    },
+    HostFunctionError {
+        function: String,
+        message: String,
+        span: token::Span,
+    },
+    NotCallable {
+        object: object::Object,
+        span: token::Span,
+    },
+    DivisionByZero {
+        left: object::Object,
+        span: token::Span,
     },
 }
 
-impl<'a> std::fmt::Display for ParserError<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+#[derive(Debug)]
+pub enum CompilerError {
+    OperandOverflow {
+        opcode: code::Opcode,
+        operand: usize,
+        span: token::Span,
+    },
+}
+
+/// バイトコードのファイルを読み込めない (位置はファイル先頭からのバイト数)
+#[derive(Debug, PartialEq, Clone)]
+pub enum BytecodeFileError {
+    InvalidMagic,
+    UnsupportedVersion(u16),
+    Truncated(usize),
+    InvalidData(usize),
+    InvalidInstruction { function: String, offset: usize },
+}
+
+/// ソースの位置が分からない実行時エラー (位置の表を省いたバイトコードを実行したとき)
+///
+/// 中のエラーの位置は意味を持たないので、表示には使わない。
+#[derive(Debug)]
+pub struct UnlocatedError(pub EvaluatorError);
+
+impl<'a> ParserError<'a> {
+    /// エラーが発生したソース中の範囲
+    pub fn span(&self) -> token::Span {
+        match self {
+            self::ParserError::UnexpectedToken { span, .. } => *span,
+            self::ParserError::NotFoundInfixToken { span, .. } => *span,
+            self::ParserError::NotFoundLetIdentifier { span, .. } => *span,
+            self::ParserError::InvalidToken { span, .. } => *span,
+            self::ParserError::UnImplementationStatemant(_, span) => *span,
+            self::ParserError::UnImplementationParser(_, span) => *span,
+        }
+    }
+
+    /// エラーの種類を表すコード
+    ///
+    /// CI などが参照するので番号は変えない。バリアントを追加したら次の番号を振る。
+    pub fn code(&self) -> &'static str {
+        match self {
+            self::ParserError::UnexpectedToken { .. } => "P0001",
+            self::ParserError::NotFoundInfixToken { .. } => "P0002",
+            self::ParserError::NotFoundLetIdentifier { .. } => "P0003",
+            self::ParserError::InvalidToken {
+                error: self::LexerError::UnterminatedString,
+                ..
+            } => "P0007",
+            self::ParserError::InvalidToken {
+                error: self::LexerError::InvalidEscape(_),
+                ..
+            } => "P0008",
+            self::ParserError::InvalidToken { .. } => "P0004",
+            self::ParserError::UnImplementationStatemant(..) => "P0005",
+            self::ParserError::UnImplementationParser(..) => "P0006",
+        }
+    }
+
+    /// 修正のヒント
+    pub fn help(&self) -> Option<String> {
+        match self {
+            self::ParserError::UnexpectedToken { expected_token, .. } => {
+                Some(Message::HelpUnexpectedToken.format(&[&format!("{:?}", expected_token)]))
+            }
+            self::ParserError::NotFoundLetIdentifier { .. } => {
+                Some(Message::HelpNotFoundLetIdentifier.format(&[]))
+            }
+            self::ParserError::InvalidToken {
+                error: self::LexerError::UnterminatedBlockComment,
+                ..
+            } => Some(Message::HelpUnterminatedBlockComment.format(&[])),
+            self::ParserError::InvalidToken {
+                error: self::LexerError::UnterminatedString,
+                ..
+            } => Some(Message::HelpUnterminatedString.format(&[])),
+            self::ParserError::InvalidToken {
+                error: self::LexerError::InvalidEscape(_),
+                ..
+            } => Some(Message::HelpInvalidEscape.format(&[])),
+            _ => None,
+        }
+    }
+
+    /// 位置を含まないエラーメッセージ
+    pub fn message(&self) -> String {
         match self {
             self::ParserError::UnexpectedToken {
                 actual_token,
                 expected_token,
-            } => {
-                write!(
-                    f,
-                    "({:?}を期待しましたが、{:?}でした。)",
-                    expected_token, actual_token
-                )
+                ..
+            } => Message::UnexpectedToken.format(&[
+                &format!("{:?}", expected_token),
+                &format!("{:?}", actual_token),
+            ]),
+            self::ParserError::NotFoundInfixToken { found_token, .. } => {
+                Message::NotFoundInfixToken.format(&[&format!("{:?}", found_token)])
             }
-            self::ParserError::NotFoundInfixToken { found_token } => {
-                write!(f, "(Infixを期待しましたが、{:?}でした。)", found_token)
+            self::ParserError::NotFoundLetIdentifier { found_token, .. } => {
+                Message::NotFoundLetIdentifier.format(&[&format!("{:?}", found_token)])
             }
-            self::ParserError::NotFoundLetIdentifier { found_token } => {
-                write!(f, "(Identifierを期待しましたが、{:?}でした。)", found_token)
+            self::ParserError::InvalidToken { error, .. } => error.to_string(),
+            self::ParserError::UnImplementationStatemant(message, _) => {
+                Message::UnImplementationStatemant.format(&[message])
             }
-            self::ParserError::UnImplementationParser(message) => {
-                write!(f, "({})", (message))
+            self::ParserError::UnImplementationParser(message, _) => {
+                Message::UnImplementationParser.format(&[message])
             }
-            _ => write!(f, "(未実装エラーです。)"),
         }
     }
 }
 
-impl std::fmt::Display for EvaluatorError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl EvaluatorError {
+    /// エラーが発生したソース中の範囲
+    pub fn span(&self) -> token::Span {
+        match self {
+            self::EvaluatorError::TypeMissMatch { span, .. } => *span,
+            self::EvaluatorError::UnknowInfixOperator { span, .. } => *span,
+            self::EvaluatorError::UnknowPrefixOperator { span, .. } => *span,
+            self::EvaluatorError::NotFoundIdentifier { span, .. } => *span,
+            self::EvaluatorError::UnknowIndexOperator { span, .. } => *span,
+            self::EvaluatorError::IndexOutOfRange { span, .. } => *span,
+            self::EvaluatorError::UnhashableKey { span, .. } => *span,
+            self::EvaluatorError::ArityMismatch { span, .. } => *span,
+            self::EvaluatorError::UnsupportedArgument { span, .. } => *span,
+            self::EvaluatorError::HostFunctionError { span, .. } => *span,
+            self::EvaluatorError::NotCallable { span, .. } => *span,
+            self::EvaluatorError::DivisionByZero { span, .. } => *span,
+        }
+    }
+
+    /// エラーの種類を表すコード
+    ///
+    /// CI などが参照するので番号は変えない。バリアントを追加したら次の番号を振る。
+    pub fn code(&self) -> &'static str {
+        match self {
+            self::EvaluatorError::TypeMissMatch { .. } => "E0001",
+            self::EvaluatorError::UnknowInfixOperator { .. } => "E0002",
+            self::EvaluatorError::UnknowPrefixOperator { .. } => "E0003",
+            self::EvaluatorError::NotFoundIdentifier { .. } => "E0004",
+            self::EvaluatorError::UnknowIndexOperator { .. } => "E0005",
+            self::EvaluatorError::IndexOutOfRange { .. } => "E0006",
+            self::EvaluatorError::UnhashableKey { .. } => "E0007",
+            self::EvaluatorError::ArityMismatch { .. } => "E0008",
+            self::EvaluatorError::UnsupportedArgument { .. } => "E0009",
+            self::EvaluatorError::HostFunctionError { .. } => "E0010",
+            self::EvaluatorError::NotCallable { .. } => "E0011",
+            self::EvaluatorError::DivisionByZero { .. } => "E0012",
+        }
+    }
+
+    /// 修正のヒント
+    pub fn help(&self) -> Option<String> {
+        match self {
+            self::EvaluatorError::TypeMissMatch { .. } => {
+                Some(Message::HelpTypeMissMatch.format(&[]))
+            }
+            self::EvaluatorError::NotFoundIdentifier { .. } => {
+                Some(Message::HelpNotFoundIdentifier.format(&[]))
+            }
+            self::EvaluatorError::IndexOutOfRange { length: 0, .. } => {
+                Some(Message::HelpEmptyIndex.format(&[]))
+            }
+            self::EvaluatorError::IndexOutOfRange { length, .. } => {
+                Some(Message::HelpIndexOutOfRange.format(&[length, &(length - 1)]))
+            }
+            self::EvaluatorError::UnhashableKey { .. } => {
+                Some(Message::HelpUnhashableKey.format(&[]))
+            }
+            self::EvaluatorError::DivisionByZero { .. } => {
+                Some(Message::HelpDivisionByZero.format(&[]))
+            }
+            _ => None,
+        }
+    }
+
+    /// 位置を含まないエラーメッセージ
+    pub fn message(&self) -> String {
         match self {
             self::EvaluatorError::TypeMissMatch {
                 left,
                 operator,
                 right,
-            } => {
-                write!(
-                    f,
-                    "型のミスマッチ: {} {} {}",
-                    left.inspect(),
-                    operator.to_code(),
-                    right.inspect()
-                )
-            }
+                ..
+            } => Message::TypeMissMatch.format(&[
+                &left.inspect(),
+                &operator.to_code(),
+                &right.inspect(),
+            ]),
             self::EvaluatorError::UnknowInfixOperator {
                 left,
                 operator,
                 right,
-            } => {
-                write!(
-                    f,
-                    "未知の演算子: {} {} {}",
-                    left.inspect(),
-                    operator.to_code(),
-                    right.inspect()
-                )
+                ..
+            } => Message::UnknowInfixOperator.format(&[
+                &left.inspect(),
+                &operator.to_code(),
+                &right.inspect(),
+            ]),
+            self::EvaluatorError::UnknowPrefixOperator {
+                operator, right, ..
+            } => Message::UnknowPrefixOperator.format(&[&operator.to_code(), &right.inspect()]),
+            self::EvaluatorError::NotFoundIdentifier { identifier, .. } => {
+                Message::NotFoundIdentifier.format(&[identifier])
+            }
+            self::EvaluatorError::UnknowIndexOperator { left, index, .. } => {
+                Message::UnknowIndexOperator.format(&[&left.inspect(), &index.inspect()])
+            }
+            self::EvaluatorError::IndexOutOfRange { index, length, .. } => {
+                Message::IndexOutOfRange.format(&[index, length])
             }
-            self::EvaluatorError::UnknowPrefixOperator { operator, right } => {
-                write!(f, "未知の演算子: {}{}", operator.to_code(), right.inspect())
+            self::EvaluatorError::UnhashableKey { key, .. } => {
+                Message::UnhashableKey.format(&[&key.inspect()])
             }
-            self::EvaluatorError::NotFoundIdentifier { identifier } => {
-                write!(f, "識別子が見つかりません。: {}", identifier)
+            self::EvaluatorError::ArityMismatch {
+                function,
+                expected,
+                actual,
+                ..
+            } => Message::ArityMismatch.format(&[function, expected, actual]),
+            self::EvaluatorError::UnsupportedArgument {
+                function, argument, ..
+            } => Message::UnsupportedArgument.format(&[function, &argument.inspect()]),
+            self::EvaluatorError::HostFunctionError {
+                function, message, ..
+            } => Message::HostFunctionError.format(&[function, message]),
+            self::EvaluatorError::NotCallable { object, .. } => {
+                Message::NotCallable.format(&[&object.inspect()])
+            }
+            self::EvaluatorError::DivisionByZero { left, .. } => {
+                Message::DivisionByZero.format(&[&left.inspect()])
             }
         }
     }
 }
 
-impl<'a> std::error::Error for ParserError<'a> {}
-impl<'a> std::error::Error for EvaluatorError {}
+impl CompilerError {
+    /// エラーが発生したソース中の範囲
+    pub fn span(&self) -> token::Span {
+        match self {
+            self::CompilerError::OperandOverflow { span, .. } => *span,
+        }
+    }
+
+    /// エラーの種類を表すコード
+    ///
+    /// CI などが参照するので番号は変えない。バリアントを追加したら次の番号を振る。
+    pub fn code(&self) -> &'static str {
+        match self {
+            self::CompilerError::OperandOverflow { .. } => "C0001",
+        }
+    }
+
+    /// 修正のヒント
+    pub fn help(&self) -> Option<String> {
+        match self {
+            self::CompilerError::OperandOverflow { .. } => {
+                Some(Message::HelpOperandOverflow.format(&[]))
+            }
+        }
+    }
+
+    /// 位置を含まないエラーメッセージ
+    pub fn message(&self) -> String {
+        match self {
+            self::CompilerError::OperandOverflow {
+                opcode, operand, ..
+            } => Message::OperandOverflow.format(&[&format!("{:?}", opcode), operand]),
+        }
+    }
+}
+
+impl BytecodeFileError {
+    /// エラーの種類を表すコード
+    ///
+    /// CI などが参照するので番号は変えない。バリアントを追加したら次の番号を振る。
+    pub fn code(&self) -> &'static str {
+        match self {
+            self::BytecodeFileError::InvalidMagic => "B0001",
+            self::BytecodeFileError::UnsupportedVersion(_) => "B0002",
+            self::BytecodeFileError::Truncated(_) => "B0003",
+            self::BytecodeFileError::InvalidData(_) => "B0004",
+            self::BytecodeFileError::InvalidInstruction { .. } => "B0005",
+        }
+    }
+}
+
+impl std::fmt::Display for LexerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            self::LexerError::InvalidNumber(literal) => Message::InvalidNumber.format(&[literal]),
+            self::LexerError::UnterminatedBlockComment => {
+                Message::UnterminatedBlockComment.format(&[])
+            }
+            self::LexerError::UnterminatedString => Message::UnterminatedString.format(&[]),
+            self::LexerError::InvalidEscape(sequence) => Message::InvalidEscape.format(&[sequence]),
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::fmt::Display for ParserError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ({})", self.span(), self.message())
+    }
+}
+
+impl std::fmt::Display for EvaluatorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span(), self.message())
+    }
+}
+
+impl std::fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.span(), self.message())
+    }
+}
+
+impl std::fmt::Display for UnlocatedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.message())
+    }
+}
+
+impl std::fmt::Display for BytecodeFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            self::BytecodeFileError::InvalidMagic => Message::InvalidMagic.format(&[]),
+            self::BytecodeFileError::UnsupportedVersion(version) => {
+                Message::UnsupportedVersion.format(&[version, &crate::bytecode_file::VERSION])
+            }
+            self::BytecodeFileError::Truncated(offset) => {
+                Message::TruncatedBytecode.format(&[offset])
+            }
+            self::BytecodeFileError::InvalidData(offset) => {
+                Message::InvalidBytecodeData.format(&[offset])
+            }
+            self::BytecodeFileError::InvalidInstruction { function, offset } => {
+                Message::InvalidInstruction.format(&[function, offset])
+            }
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for LexerError {}
+impl std::error::Error for ParserError<'_> {}
+impl std::error::Error for EvaluatorError {}
+impl std::error::Error for CompilerError {}
+impl std::error::Error for BytecodeFileError {}
+impl std::error::Error for UnlocatedError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{self, Locale};
+
+    fn assert_locales<E: std::fmt::Display>(error: &E, ja: &str, en: &str) {
+        message::set_locale(Locale::Ja);
+        assert_eq!(error.to_string(), ja);
+        message::set_locale(Locale::En);
+        assert_eq!(error.to_string(), en);
+        message::set_locale(Locale::Ja);
+    }
+
+    #[test]
+    fn test_lexer_error_message() {
+        let problem = [
+            (
+                LexerError::InvalidNumber("1e".to_string()),
+                "数値リテラルが不正です。: 1e",
+                "invalid numeric literal: 1e",
+            ),
+            (
+                LexerError::UnterminatedBlockComment,
+                "ブロックコメントが閉じられていません。",
+                "unterminated block comment",
+            ),
+            (
+                LexerError::UnterminatedString,
+                "文字列が閉じられていません。",
+                "unterminated string literal",
+            ),
+            (
+                LexerError::InvalidEscape(r"\q".into()),
+                r"不正なエスケープシーケンスです。: \q",
+                r"invalid escape sequence: \q",
+            ),
+        ];
+
+        for (error, ja, en) in problem {
+            assert_locales(&error, ja, en);
+        }
+    }
+
+    #[test]
+    fn test_parser_error_message() {
+        let span = token::Span::default();
+        let problem = [
+            (
+                ParserError::UnexpectedToken {
+                    actual_token: token::Token::Integer(6),
+                    expected_token: token::Token::Assign,
+                    span,
+                },
+                "1:1: (Assignを期待しましたが、Integer(6)でした。)",
+                "1:1: (expected Assign, found Integer(6))",
+            ),
+            (
+                ParserError::NotFoundInfixToken {
+                    found_token: token::Token::Comma,
+                    span,
+                },
+                "1:1: (Infixを期待しましたが、Commaでした。)",
+                "1:1: (expected an infix operator, found Comma)",
+            ),
+            (
+                ParserError::NotFoundLetIdentifier {
+                    found_token: token::Token::Assign,
+                    span,
+                },
+                "1:1: (Identifierを期待しましたが、Assignでした。)",
+                "1:1: (expected an identifier, found Assign)",
+            ),
+            (
+                ParserError::InvalidToken {
+                    error: LexerError::UnterminatedBlockComment,
+                    span,
+                },
+                "1:1: (ブロックコメントが閉じられていません。)",
+                "1:1: (unterminated block comment)",
+            ),
+            (
+                ParserError::UnImplementationStatemant("文のパーサーが未実装です。", span),
+                "1:1: (文のパーサーが未実装です。)",
+                "1:1: (no parser is implemented for this statement: 文のパーサーが未実装です。)",
+            ),
+            (
+                ParserError::UnImplementationParser("式のパーサーが未実装です。", span),
+                "1:1: (式のパーサーが未実装です。)",
+                "1:1: (no parser is implemented for this expression: 式のパーサーが未実装です。)",
+            ),
+        ];
+
+        for (error, ja, en) in problem {
+            assert_locales(&error, ja, en);
+        }
+    }
+
+    #[test]
+    fn test_evaluator_error_message() {
+        let span = token::Span::default();
+        let problem = [
+            (
+                EvaluatorError::TypeMissMatch {
+                    left: object::Object::Integer(5),
+                    operator: operator::Infix::Plus,
+                    right: object::Object::Boolean(true),
+                    span,
+                },
+                "1:1: 型のミスマッチ: 5 + true",
+                "1:1: type mismatch: 5 + true",
+            ),
+            (
+                EvaluatorError::UnknowInfixOperator {
+                    left: object::Object::Boolean(true),
+                    operator: operator::Infix::Plus,
+                    right: object::Object::Boolean(false),
+                    span,
+                },
+                "1:1: 未知の演算子: true + false",
+                "1:1: unknown operator: true + false",
+            ),
+            (
+                EvaluatorError::UnknowPrefixOperator {
+                    operator: operator::Prefix::Minus,
+                    right: object::Object::Boolean(true),
+                    span,
+                },
+                "1:1: 未知の演算子: -true",
+                "1:1: unknown operator: -true",
+            ),
+            (
+                EvaluatorError::NotFoundIdentifier {
+                    identifier: "foobar".to_string(),
+                    span,
+                },
+                "1:1: 識別子が見つかりません。: foobar",
+                "1:1: identifier not found: foobar",
+            ),
+            (
+                EvaluatorError::UnknowIndexOperator {
+                    left: object::Object::Integer(1),
+                    index: object::Object::Integer(0),
+                    span,
+                },
+                "1:1: 未知の添字演算子: 1[0]",
+                "1:1: unknown index operator: 1[0]",
+            ),
+            (
+                EvaluatorError::IndexOutOfRange {
+                    index: 3,
+                    length: 3,
+                    span,
+                },
+                "1:1: 添字が範囲外です。: 3 (長さ 3)",
+                "1:1: index out of range: 3 (length 3)",
+            ),
+            (
+                EvaluatorError::UnhashableKey {
+                    key: object::Object::Array(vec![]),
+                    span,
+                },
+                "1:1: ハッシュのキーに使えません。: []",
+                "1:1: unusable as hash key: []",
+            ),
+            (
+                EvaluatorError::ArityMismatch {
+                    function: "len".to_string(),
+                    expected: 1,
+                    actual: 2,
+                    span,
+                },
+                "1:1: 引数の数が合いません。: len (1個を期待しましたが、2個でした。)",
+                "1:1: wrong number of arguments: len (expected 1, found 2)",
+            ),
+            (
+                EvaluatorError::UnsupportedArgument {
+                    function: "len".to_string(),
+                    argument: object::Object::Integer(1),
+                    span,
+                },
+                "1:1: 引数の型が不正です。: len(1)",
+                "1:1: unsupported argument: len(1)",
+            ),
+            (
+                EvaluatorError::HostFunctionError {
+                    function: "read".to_string(),
+                    message: "io".to_string(),
+                    span,
+                },
+                "1:1: read の実行に失敗しました。: io",
+                "1:1: read failed: io",
+            ),
+            (
+                EvaluatorError::NotCallable {
+                    object: object::Object::Integer(5),
+                    span,
+                },
+                "1:1: 関数ではありません。: 5",
+                "1:1: not a function: 5",
+            ),
+            (
+                EvaluatorError::DivisionByZero {
+                    left: object::Object::Integer(5),
+                    span,
+                },
+                "1:1: ゼロで除算しました。: 5 / 0",
+                "1:1: division by zero: 5 / 0",
+            ),
+        ];
+
+        for (error, ja, en) in problem {
+            assert_locales(&error, ja, en);
+        }
+    }
+
+    #[test]
+    fn test_help_message() {
+        let error = EvaluatorError::IndexOutOfRange {
+            index: 3,
+            length: 3,
+            span: token::Span::default(),
+        };
+        message::set_locale(Locale::En);
+        assert_eq!(
+            error.help(),
+            Some("indices must be between -3 and 2".to_string())
+        );
+        message::set_locale(Locale::Ja);
+        assert_eq!(
+            error.help(),
+            Some("添字は -3 から 2 の範囲で指定してください。".to_string())
+        );
+    }
+
+    #[test]
+    fn test_error_code() {
+        // コードは CI などが参照するので、全てのバリアントの番号を固定する
+        let span = token::Span::default();
+        let token = token::Token::Illegal;
+        let parser_errors = [
+            (
+                ParserError::UnexpectedToken {
+                    actual_token: token.clone(),
+                    expected_token: token.clone(),
+                    span,
+                },
+                "P0001",
+            ),
+            (
+                ParserError::NotFoundInfixToken {
+                    found_token: token.clone(),
+                    span,
+                },
+                "P0002",
+            ),
+            (
+                ParserError::NotFoundLetIdentifier {
+                    found_token: token.clone(),
+                    span,
+                },
+                "P0003",
+            ),
+            (
+                ParserError::InvalidToken {
+                    error: LexerError::UnterminatedBlockComment,
+                    span,
+                },
+                "P0004",
+            ),
+            (ParserError::UnImplementationStatemant("", span), "P0005"),
+            (ParserError::UnImplementationParser("", span), "P0006"),
+            (
+                ParserError::InvalidToken {
+                    error: LexerError::UnterminatedString,
+                    span,
+                },
+                "P0007",
+            ),
+            (
+                ParserError::InvalidToken {
+                    error: LexerError::InvalidEscape(r"\q".into()),
+                    span,
+                },
+                "P0008",
+            ),
+        ];
+        for (error, code) in &parser_errors {
+            // バリアントを足したらここがコンパイルエラーになるので、表にも足す
+            match error {
+                ParserError::UnexpectedToken { .. }
+                | ParserError::NotFoundInfixToken { .. }
+                | ParserError::NotFoundLetIdentifier { .. }
+                | ParserError::InvalidToken { .. }
+                | ParserError::UnImplementationStatemant(..)
+                | ParserError::UnImplementationParser(..) => {}
+            }
+            assert_eq!(error.code(), *code, "{:?}", error);
+        }
+
+        let object = object::Object::Null;
+        let evaluator_errors = [
+            (
+                EvaluatorError::TypeMissMatch {
+                    left: object.clone(),
+                    operator: operator::Infix::Plus,
+                    right: object.clone(),
+                    span,
+                },
+                "E0001",
+            ),
+            (
+                EvaluatorError::UnknowInfixOperator {
+                    left: object.clone(),
+                    operator: operator::Infix::Plus,
+                    right: object.clone(),
+                    span,
+                },
+                "E0002",
+            ),
+            (
+                EvaluatorError::UnknowPrefixOperator {
+                    operator: operator::Prefix::Minus,
+                    right: object.clone(),
+                    span,
+                },
+                "E0003",
+            ),
+            (
+                EvaluatorError::NotFoundIdentifier {
+                    identifier: String::new(),
+                    span,
+                },
+                "E0004",
+            ),
+            (
+                EvaluatorError::UnknowIndexOperator {
+                    left: object.clone(),
+                    index: object.clone(),
+                    span,
+                },
+                "E0005",
+            ),
+            (
+                EvaluatorError::IndexOutOfRange {
+                    index: 0,
+                    length: 0,
+                    span,
+                },
+                "E0006",
+            ),
+            (
+                EvaluatorError::UnhashableKey {
+                    key: object.clone(),
+                    span,
+                },
+                "E0007",
+            ),
+            (
+                EvaluatorError::ArityMismatch {
+                    function: String::new(),
+                    expected: 0,
+                    actual: 0,
+                    span,
+                },
+                "E0008",
+            ),
+            (
+                EvaluatorError::UnsupportedArgument {
+                    function: String::new(),
+                    argument: object.clone(),
+                    span,
+                },
+                "E0009",
+            ),
+            (
+                EvaluatorError::HostFunctionError {
+                    function: String::new(),
+                    message: String::new(),
+                    span,
+                },
+                "E0010",
+            ),
+            (
+                EvaluatorError::NotCallable {
+                    object: object.clone(),
+                    span,
+                },
+                "E0011",
+            ),
+            (
+                EvaluatorError::DivisionByZero {
+                    left: object.clone(),
+                    span,
+                },
+                "E0012",
+            ),
+        ];
+        for (error, code) in &evaluator_errors {
+            // バリアントを足したらここがコンパイルエラーになるので、表にも足す
+            match error {
+                EvaluatorError::TypeMissMatch { .. }
+                | EvaluatorError::UnknowInfixOperator { .. }
+                | EvaluatorError::UnknowPrefixOperator { .. }
+                | EvaluatorError::NotFoundIdentifier { .. }
+                | EvaluatorError::UnknowIndexOperator { .. }
+                | EvaluatorError::IndexOutOfRange { .. }
+                | EvaluatorError::UnhashableKey { .. }
+                | EvaluatorError::ArityMismatch { .. }
+                | EvaluatorError::UnsupportedArgument { .. }
+                | EvaluatorError::HostFunctionError { .. }
+                | EvaluatorError::NotCallable { .. }
+                | EvaluatorError::DivisionByZero { .. } => {}
+            }
+            assert_eq!(error.code(), *code, "{:?}", error);
+        }
+
+        let compiler_error = CompilerError::OperandOverflow {
+            opcode: code::Opcode::Constant,
+            operand: 65536,
+            span,
+        };
+        assert_eq!(compiler_error.code(), "C0001");
+        assert_locales(
+            &compiler_error,
+            "1:1: Constant のオペランドが大きすぎます。: 65536",
+            "1:1: operand of Constant is too large: 65536",
+        );
+    }
+
+    #[test]
+    fn test_bytecode_file_error_message() {
+        let problem = [
+            (
+                BytecodeFileError::InvalidMagic,
+                "バイトコードのファイルではありません。",
+                "not a bytecode file",
+            ),
+            (
+                BytecodeFileError::UnsupportedVersion(9),
+                "対応していないバイトコードのバージョンです。: 9 (対応しているのは 3)",
+                "unsupported bytecode version: 9 (expected 3)",
+            ),
+            (
+                BytecodeFileError::Truncated(12),
+                "バイトコードのファイルが途中で終わっています。: 12 バイト目",
+                "bytecode file is truncated at byte 12",
+            ),
+            (
+                BytecodeFileError::InvalidData(7),
+                "バイトコードのファイルが壊れています。: 7 バイト目",
+                "corrupt bytecode file at byte 7",
+            ),
+            (
+                BytecodeFileError::InvalidInstruction {
+                    function: "main".to_string(),
+                    offset: 3,
+                },
+                "main の 3 番地の命令が不正です。",
+                "invalid instruction at 3 in main",
+            ),
+        ];
+
+        for (index, (error, ja, en)) in problem.iter().enumerate() {
+            assert_eq!(error.code(), format!("B000{}", index + 1));
+            assert_locales(error, ja, en);
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use crate::ast;
+use crate::bigint;
+use crate::builtin;
 use crate::environment;
 use crate::error;
 use crate::object;
 use crate::operator;
-use std::{cell::RefCell, rc::Rc};
+use crate::resolver;
+use crate::token;
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
 
 pub struct Evaluator {}
 
@@ -12,19 +16,27 @@ impl Evaluator {
         root: &ast::Program,
         env: &mut Rc<RefCell<environment::Environment>>,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        // 別の環境に対して静的解決した Program は、スロットの並びが合わないので解決し直す
+        if let Some(globals) = &root.globals {
+            if !env.borrow().names().starts_with(globals) {
+                let mut program = root.clone();
+                resolver::resolve_incremental(&mut program, &mut env.borrow_mut())?;
+                return Evaluator::eval_statements(&program.statements, true, env);
+            }
+        }
         Evaluator::eval_statements(&root.statements, true, env)
     }
 
     fn eval_statements(
-        statements: &Vec<ast::Statement>,
+        statements: &[ast::Statement],
         is_root: bool,
         env: &mut Rc<RefCell<environment::Environment>>,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
         let mut result = Ok(object::Object::Null);
         for statement in statements {
-            let object = Evaluator::eval_statement(&statement, env)?;
+            let object = Evaluator::eval_statement(statement, env)?;
             if let object::Object::ReturnValue(value) = object {
-                result = if is_root == true {
+                result = if is_root {
                     Ok(*value)
                 } else {
                     Ok(object::Object::ReturnValue(value))
@@ -35,31 +47,29 @@ impl Evaluator {
             }
         }
 
-        return result;
+        result
     }
 
     fn eval_statement(
         statement: &ast::Statement,
         env: &mut Rc<RefCell<environment::Environment>>,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
-        match statement {
-            ast::Statement::Let { identifier, value } => {
-                let identifier = if let ast::Expression::Identifier(ident) = identifier {
-                    ident
-                } else {
-                    unreachable!();
-                };
+        match &statement.kind {
+            ast::StatementKind::Let { identifier, value } => {
                 let value = Evaluator::eval_expression(value, env)?;
-                env.borrow_mut().set(identifier.clone(), value);
+                Evaluator::bind(identifier, value, env);
                 Ok(object::Object::Null)
             }
-            ast::Statement::Return(expression) => {
+            ast::StatementKind::Return(expression) => {
                 let ret_val = Evaluator::eval_expression(expression, env)?;
                 Ok(object::Object::ReturnValue(Box::new(ret_val)))
             }
-            ast::Statement::Expression(expression) => Evaluator::eval_expression(expression, env),
-            ast::Statement::Block(statements) => Evaluator::eval_statements(statements, false, env),
-            _ => Ok(object::Object::Null),
+            ast::StatementKind::Expression(expression) => {
+                Evaluator::eval_expression(expression, env)
+            }
+            ast::StatementKind::Block(statements) => {
+                Evaluator::eval_statements(statements, false, env)
+            }
         }
     }
 
@@ -67,58 +77,87 @@ impl Evaluator {
         expression: &ast::Expression,
         env: &mut Rc<RefCell<environment::Environment>>,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
-        match expression {
-            ast::Expression::Identifier(identifier) => {
-                if let Some(object) = env.borrow().get(identifier.clone()) {
+        match &expression.kind {
+            ast::ExpressionKind::Identifier(identifier, resolution) => {
+                let object = match resolution {
+                    Some(resolution) => {
+                        env.borrow()
+                            .get_slot(resolution.depth, resolution.slot, identifier)
+                    }
+                    None => env.borrow().get(identifier),
+                };
+                if let Some(object) = object {
                     Ok(object)
+                } else if let Some(builtin) = builtin::lookup(identifier) {
+                    Ok(builtin)
                 } else {
                     Err(error::EvaluatorError::NotFoundIdentifier {
                         identifier: identifier.clone(),
+                        span: expression.span,
                     })?
                 }
             }
-            ast::Expression::Integer(integer) => Ok(object::Object::Integer(*integer)),
-            ast::Expression::Boolean(boolean) => Ok(object::Object::Boolean(*boolean)),
-            ast::Expression::PrefixExpression {
+            ast::ExpressionKind::Integer(integer) => Ok(object::Object::Integer(*integer)),
+            ast::ExpressionKind::BigInt(bigint) => Ok(object::Object::BigInt(bigint.clone())),
+            ast::ExpressionKind::Float(float) => Ok(object::Object::Float(*float)),
+            ast::ExpressionKind::String(string) => Ok(object::Object::String(string.clone())),
+            ast::ExpressionKind::Boolean(boolean) => Ok(object::Object::Boolean(*boolean)),
+            ast::ExpressionKind::PrefixExpression {
                 operator,
-                expression,
+                expression: right,
             } => {
-                let object = Evaluator::eval_expression(expression, env);
-                Evaluator::eval_prefix_expression(operator.clone(), &(object?), env)
+                let object = Evaluator::eval_expression(right, env);
+                Evaluator::eval_prefix_expression(operator.clone(), &(object?), expression.span)
             }
-            ast::Expression::InfixExpression {
+            ast::ExpressionKind::InfixExpression {
                 left,
                 operator,
                 right,
             } => {
                 let left = Evaluator::eval_expression(left, env)?;
                 let right = Evaluator::eval_expression(right, env)?;
-                Evaluator::eval_infix_expression(&left, operator.clone(), &right, env)
+                Evaluator::eval_infix_expression(&left, operator.clone(), &right, expression.span)
             }
-            ast::Expression::IfExpression {
+            ast::ExpressionKind::IfExpression {
                 condition,
                 consequence,
                 alternative,
             } => {
                 let condition = Evaluator::eval_expression(condition, env)?;
-                Evaluator::eval_if_expression(&condition, consequence, &alternative, env)
+                Evaluator::eval_if_expression(&condition, consequence, alternative, env)
             }
-            ast::Expression::Function { parameters, body } => Ok(object::Object::Function {
+            ast::ExpressionKind::Function {
+                parameters,
+                body,
+                slots,
+            } => Ok(object::Object::Function {
                 parameters: parameters.clone(),
                 body: body.clone(),
-                environment: environment::Environment::create_enclosed_environment(env.clone()),
+                slots: slots.clone(),
+                environment: env.clone(),
             }),
-            ast::Expression::Call { function, args } => {
-                let function = Evaluator::eval_expression(function, env)?;
+            ast::ExpressionKind::Call { function, args } => {
+                let callee = function;
+                let function = Evaluator::eval_expression(callee, env)?;
                 let args = Evaluator::eval_expressions(args, env)?;
-                Evaluator::apply_function(function, args)
+                Evaluator::apply_function(function, args, callee, expression.span)
             }
-            _ => Ok(object::Object::Null),
+            ast::ExpressionKind::Array(elements) => {
+                let elements = Evaluator::eval_expressions(elements, env)?;
+                Ok(object::Object::Array(elements))
+            }
+            ast::ExpressionKind::Hash(pairs) => Evaluator::eval_hash_expression(pairs, env),
+            ast::ExpressionKind::Index { left, index } => {
+                let left = Evaluator::eval_expression(left, env)?;
+                let index = Evaluator::eval_expression(index, env)?;
+                Evaluator::eval_index_expression(&left, &index, expression.span)
+            }
+            ast::ExpressionKind::Illegal => Ok(object::Object::Null),
         }
     }
 
     fn eval_expressions(
-        expressions: &Vec<ast::Expression>,
+        expressions: &[ast::Expression],
         env: &mut Rc<RefCell<environment::Environment>>,
     ) -> Result<Vec<object::Object>, Box<dyn std::error::Error>> {
         let mut result = Vec::new();
@@ -131,49 +170,144 @@ impl Evaluator {
         Ok(result)
     }
 
-    fn eval_prefix_expression(
+    pub(crate) fn eval_prefix_expression(
         operator: operator::Prefix,
         object: &object::Object,
-        env: &mut Rc<RefCell<environment::Environment>>,
+        span: token::Span,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
         match operator {
-            operator::Prefix::Exclamation => Evaluator::eval_exclamation_operator(object, env),
-            operator::Prefix::Minus => Evaluator::eval_minus_prefix_operator(object, env),
+            operator::Prefix::Exclamation => Evaluator::eval_exclamation_operator(object),
+            operator::Prefix::Minus => Evaluator::eval_minus_prefix_operator(object, span),
         }
     }
 
-    fn eval_infix_expression(
+    pub(crate) fn eval_infix_expression(
         left: &object::Object,
         operator: operator::Infix,
         right: &object::Object,
-        env: &mut Rc<RefCell<environment::Environment>>,
+        span: token::Span,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
         match (left, right) {
             (object::Object::Integer(left_int), object::Object::Integer(right_int)) => {
-                Evaluator::eval_integer_infix_expression(*left_int, operator, *right_int, env)
+                Evaluator::eval_integer_infix_expression(*left_int, operator, *right_int, span)
+            }
+            (
+                object::Object::Integer(_) | object::Object::BigInt(_),
+                object::Object::Integer(_) | object::Object::BigInt(_),
+            ) => Evaluator::eval_bigint_infix_expression(
+                Evaluator::to_bigint(left),
+                operator,
+                Evaluator::to_bigint(right),
+                span,
+            ),
+            (
+                object::Object::Integer(_) | object::Object::BigInt(_) | object::Object::Float(_),
+                object::Object::Integer(_) | object::Object::BigInt(_) | object::Object::Float(_),
+            ) => Evaluator::eval_float_infix_expression(
+                Evaluator::to_f64(left),
+                operator,
+                Evaluator::to_f64(right),
+                span,
+            ),
+            (object::Object::String(left_str), object::Object::String(right_str)) => {
+                Evaluator::eval_string_infix_expression(left_str, operator, right_str, span)
             }
             (object::Object::Boolean(left_bool), object::Object::Boolean(right_bool)) => {
-                Evaluator::eval_boolean_infix_expression(*left_bool, operator, *right_bool, env)
+                Evaluator::eval_boolean_infix_expression(*left_bool, operator, *right_bool, span)
             }
             _ => Err(error::EvaluatorError::TypeMissMatch {
                 left: left.clone(),
-                operator: operator,
+                operator,
                 right: right.clone(),
+                span,
             })?,
         }
     }
 
     fn eval_integer_infix_expression(
-        left: i32,
+        left: i64,
         operator: operator::Infix,
-        right: i32,
-        env: &mut Rc<RefCell<environment::Environment>>,
+        right: i64,
+        span: token::Span,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        let calculated = match operator {
+            operator::Infix::Plus => left.checked_add(right),
+            operator::Infix::Minus => left.checked_sub(right),
+            operator::Infix::Asterisk => left.checked_mul(right),
+            operator::Infix::Slash => {
+                if right == 0 {
+                    return Err(error::EvaluatorError::DivisionByZero {
+                        left: object::Object::Integer(left),
+                        span,
+                    })?;
+                }
+                left.checked_div(right)
+            }
+            operator::Infix::LessThan => return Ok(object::Object::Boolean(left < right)),
+            operator::Infix::GreaterThan => return Ok(object::Object::Boolean(left > right)),
+            operator::Infix::Equal => return Ok(object::Object::Boolean(left == right)),
+            operator::Infix::NotEqual => return Ok(object::Object::Boolean(left != right)),
+        };
+
+        // 計算結果が i64 に収まらない場合は多倍長整数で計算し直す
+        match calculated {
+            Some(integer) => Ok(object::Object::Integer(integer)),
+            None => Evaluator::eval_bigint_infix_expression(
+                bigint::BigInt::from(left),
+                operator,
+                bigint::BigInt::from(right),
+                span,
+            ),
+        }
+    }
+
+    fn eval_bigint_infix_expression(
+        left: bigint::BigInt,
+        operator: operator::Infix,
+        right: bigint::BigInt,
+        span: token::Span,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        let calculated = match operator {
+            operator::Infix::Plus => &left + &right,
+            operator::Infix::Minus => &left - &right,
+            operator::Infix::Asterisk => &left * &right,
+            operator::Infix::Slash => match left.checked_div(&right) {
+                Some(quotient) => quotient,
+                None => Err(error::EvaluatorError::DivisionByZero {
+                    left: object::Object::from(left),
+                    span,
+                })?,
+            },
+            operator::Infix::LessThan => return Ok(object::Object::Boolean(left < right)),
+            operator::Infix::GreaterThan => return Ok(object::Object::Boolean(left > right)),
+            operator::Infix::Equal => return Ok(object::Object::Boolean(left == right)),
+            operator::Infix::NotEqual => return Ok(object::Object::Boolean(left != right)),
+        };
+
+        Ok(object::Object::from(calculated))
+    }
+
+    /// 整数と浮動小数点数の演算は浮動小数点数で行う
+    fn eval_float_infix_expression(
+        left: f64,
+        operator: operator::Infix,
+        right: f64,
+        span: token::Span,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
         match operator {
-            operator::Infix::Plus => Ok(object::Object::Integer(left + right)),
-            operator::Infix::Minus => Ok(object::Object::Integer(left - right)),
-            operator::Infix::Asterisk => Ok(object::Object::Integer(left * right)),
-            operator::Infix::Slash => Ok(object::Object::Integer(left / right)),
+            operator::Infix::Plus => Ok(object::Object::Float(left + right)),
+            operator::Infix::Minus => Ok(object::Object::Float(left - right)),
+            operator::Infix::Asterisk => Ok(object::Object::Float(left * right)),
+            operator::Infix::Slash => {
+                if right == 0.0 {
+                    Err(error::EvaluatorError::DivisionByZero {
+                        left: object::Object::Float(left),
+                        span,
+                    })?
+                } else {
+                    Ok(object::Object::Float(left / right))
+                }
+            }
             operator::Infix::LessThan => Ok(object::Object::Boolean(left < right)),
             operator::Infix::GreaterThan => Ok(object::Object::Boolean(left > right)),
             operator::Infix::Equal => Ok(object::Object::Boolean(left == right)),
@@ -181,19 +315,129 @@ impl Evaluator {
         }
     }
 
+    fn to_f64(object: &object::Object) -> f64 {
+        match object {
+            object::Object::Integer(integer) => *integer as f64,
+            object::Object::BigInt(bigint) => bigint.to_f64(),
+            object::Object::Float(float) => *float,
+            _ => unreachable!(),
+        }
+    }
+
+    fn to_bigint(object: &object::Object) -> bigint::BigInt {
+        match object {
+            object::Object::Integer(integer) => bigint::BigInt::from(*integer),
+            object::Object::BigInt(bigint) => bigint.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn eval_string_infix_expression(
+        left: &str,
+        operator: operator::Infix,
+        right: &str,
+        span: token::Span,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        match operator {
+            operator::Infix::Plus => Ok(object::Object::String(left.to_string() + right)),
+            operator::Infix::Equal => Ok(object::Object::Boolean(left == right)),
+            operator::Infix::NotEqual => Ok(object::Object::Boolean(left != right)),
+            _ => Err(error::EvaluatorError::UnknowInfixOperator {
+                left: object::Object::String(left.to_string()),
+                operator,
+                right: object::Object::String(right.to_string()),
+                span,
+            })?,
+        }
+    }
+
     fn eval_boolean_infix_expression(
         left: bool,
         operator: operator::Infix,
         right: bool,
-        env: &mut Rc<RefCell<environment::Environment>>,
+        span: token::Span,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
         match operator {
             operator::Infix::Equal => Ok(object::Object::Boolean(left == right)),
             operator::Infix::NotEqual => Ok(object::Object::Boolean(left != right)),
             _ => Err(error::EvaluatorError::UnknowInfixOperator {
                 left: object::Object::Boolean(left),
-                operator: operator,
+                operator,
                 right: object::Object::Boolean(right),
+                span,
+            })?,
+        }
+    }
+
+    fn eval_hash_expression(
+        pairs: &[(ast::Expression, ast::Expression)],
+        env: &mut Rc<RefCell<environment::Environment>>,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        let mut hash = BTreeMap::new();
+
+        for (key, value) in pairs {
+            let key_span = key.span;
+            let key = Evaluator::eval_expression(key, env)?;
+            let hash_key = match key.hash_key() {
+                Some(hash_key) => hash_key,
+                None => Err(error::EvaluatorError::UnhashableKey {
+                    key,
+                    span: key_span,
+                })?,
+            };
+            let value = Evaluator::eval_expression(value, env)?;
+            hash.insert(hash_key, value);
+        }
+
+        Ok(object::Object::Hash(hash))
+    }
+
+    pub(crate) fn eval_index_expression(
+        left: &object::Object,
+        index: &object::Object,
+        span: token::Span,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        match (left, index) {
+            (object::Object::Array(elements), object::Object::Integer(integer)) => {
+                Evaluator::eval_array_index_expression(elements, *integer, span)
+            }
+            (object::Object::Hash(pairs), _) => match index.hash_key() {
+                Some(hash_key) => Ok(pairs
+                    .get(&hash_key)
+                    .cloned()
+                    .unwrap_or(object::Object::Null)),
+                None => Err(error::EvaluatorError::UnhashableKey {
+                    key: index.clone(),
+                    span,
+                })?,
+            },
+            _ => Err(error::EvaluatorError::UnknowIndexOperator {
+                left: left.clone(),
+                index: index.clone(),
+                span,
+            })?,
+        }
+    }
+
+    /// 負の添字は末尾から数える (-1 が最後の要素)
+    fn eval_array_index_expression(
+        elements: &[object::Object],
+        index: i64,
+        span: token::Span,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        let length = elements.len();
+        let position = if index < 0 {
+            length.checked_sub(index.unsigned_abs() as usize)
+        } else {
+            Some(index as usize)
+        };
+
+        match position.and_then(|position| elements.get(position)) {
+            Some(element) => Ok(element.clone()),
+            None => Err(error::EvaluatorError::IndexOutOfRange {
+                index,
+                length,
+                span,
             })?,
         }
     }
@@ -204,38 +448,67 @@ impl Evaluator {
         alternative: &Option<Box<ast::Statement>>,
         env: &mut Rc<RefCell<environment::Environment>>,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
-        if condition.is_truthly() == true {
+        if condition.is_truthly() {
             Evaluator::eval_statement(consequence, env)
+        } else if let Some(alternative) = alternative {
+            Evaluator::eval_statement(alternative, env)
         } else {
-            if let Some(alternative) = alternative {
-                Evaluator::eval_statement(alternative, env)
-            } else {
-                Ok(object::Object::Null)
+            Ok(object::Object::Null)
+        }
+    }
+
+    /// let や引数の識別子に値を束縛する (静的解決済みならスロット番号で)
+    fn bind(
+        identifier: &ast::Expression,
+        value: object::Object,
+        env: &mut Rc<RefCell<environment::Environment>>,
+    ) {
+        match &identifier.kind {
+            ast::ExpressionKind::Identifier(_, Some(resolution)) => {
+                env.borrow_mut().set_slot(resolution.slot, value)
             }
+            ast::ExpressionKind::Identifier(name, None) => {
+                env.borrow_mut().set(name.clone(), value)
+            }
+            _ => unreachable!(),
         }
     }
 
+    /// callee は呼び出された式 (エラーメッセージ用)
     fn apply_function(
         object: object::Object,
         args: Vec<object::Object>,
+        callee: &ast::Expression,
+        span: token::Span,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        if let object::Object::Builtin(builtin) = object {
+            return builtin.call(&args, span);
+        }
+
         if let object::Object::Function {
             parameters,
             body,
+            slots,
             environment,
         } = object
         {
-            let new_env = environment::Environment::create_enclosed_environment(Rc::new(
-                RefCell::new(environment),
-            ));
+            if parameters.len() != args.len() {
+                return Err(error::EvaluatorError::ArityMismatch {
+                    function: callee.to_code(),
+                    expected: parameters.len(),
+                    actual: args.len(),
+                    span,
+                })?;
+            }
+
+            let new_env = match slots {
+                Some(names) => environment::Environment::with_slots(names, environment),
+                None => environment::Environment::create_enclosed_environment(environment),
+            };
             let mut new_env = Rc::new(RefCell::new(new_env));
 
-            for (parameter, arg) in parameters.iter().zip(args.iter()) {
-                if let ast::Expression::Identifier(identifier) = parameter {
-                    new_env.borrow_mut().set(identifier.clone(), arg.clone());
-                } else {
-                    unreachable!();
-                }
+            for (parameter, arg) in parameters.iter().zip(args) {
+                Evaluator::bind(parameter, arg, &mut new_env);
             }
 
             let evaluated = Evaluator::eval_statement(&body, &mut new_env)?;
@@ -245,13 +518,12 @@ impl Evaluator {
                 Ok(evaluated)
             }
         } else {
-            unreachable!();
+            Err(error::EvaluatorError::NotCallable { object, span })?
         }
     }
 
     fn eval_exclamation_operator(
         object: &object::Object,
-        env: &mut Rc<RefCell<environment::Environment>>,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
         match object {
             object::Object::Boolean(boolean) => Ok(object::Object::Boolean(!boolean)),
@@ -262,13 +534,19 @@ impl Evaluator {
 
     fn eval_minus_prefix_operator(
         object: &object::Object,
-        env: &mut Rc<RefCell<environment::Environment>>,
+        span: token::Span,
     ) -> Result<object::Object, Box<dyn std::error::Error>> {
         match object {
-            object::Object::Integer(integer) => Ok(object::Object::Integer(-integer)),
+            object::Object::Integer(integer) => match integer.checked_neg() {
+                Some(negated) => Ok(object::Object::Integer(negated)),
+                None => Ok(object::Object::from(-&bigint::BigInt::from(*integer))),
+            },
+            object::Object::BigInt(bigint) => Ok(object::Object::from(-bigint)),
+            object::Object::Float(float) => Ok(object::Object::Float(-float)),
             _ => Err(error::EvaluatorError::UnknowPrefixOperator {
                 operator: operator::Prefix::Minus,
                 right: object.clone(),
+                span,
             })?,
         }
     }
@@ -278,6 +556,7 @@ impl Evaluator {
 mod tests {
     use super::*;
     use crate::lexer;
+    use crate::message::{self, Locale};
     use crate::parser;
 
     #[test]
@@ -302,6 +581,108 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_eval_bigint_expression() {
+        let tests = [
+            ("2147483647 + 1", "2147483648"),
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("-9223372036854775807 - 2", "-9223372036854775809"),
+            ("4294967296 * 4294967296", "18446744073709551616"),
+            ("(-9223372036854775807 - 1) / -1", "9223372036854775808"),
+            (
+                "let min = -9223372036854775807 - 1; -min",
+                "9223372036854775808",
+            ),
+            (
+                "let big = 9223372036854775807 * 9223372036854775807; big * big",
+                "7237005577332262210834635695349653859421902880380109739573089701262786560001",
+            ),
+            (
+                "let big = 9223372036854775807 + 1; -big",
+                "-9223372036854775808",
+            ),
+            ("(9223372036854775807 + 10) / 10", "922337203685477581"),
+            ("(9223372036854775807 + 10) - 10", "9223372036854775807"),
+            // i64 に収まらないリテラルも書ける (表示した値をそのまま入力できる)
+            ("99999999999999999999", "99999999999999999999"),
+            ("99999999999999999999 * 10 + 9", "999999999999999999999"),
+            (
+                "[9223372036854775808][0] == 9223372036854775807 + 1",
+                "true",
+            ),
+        ];
+
+        for (input, result) in tests {
+            let evaluated = test_eval(input);
+            assert_eq!(evaluated.inspect(), result);
+        }
+
+        // i64 に収まる結果は Integer に戻る
+        test_integer_object(&test_eval("(9223372036854775807 + 1) - 1"), i64::MAX);
+        test_integer_object(&test_eval("-9223372036854775808"), i64::MIN);
+        test_integer_object(
+            &test_eval("let big = 9223372036854775807 + 1; -big"),
+            i64::MIN,
+        );
+
+        let tests = [
+            ("9223372036854775807 + 1 > 9223372036854775807", true),
+            ("9223372036854775807 + 1 == 9223372036854775807 + 1", true),
+            (
+                "9223372036854775807 * 2 != 9223372036854775807 + 9223372036854775807",
+                false,
+            ),
+            ("-9223372036854775807 * 2 < 1", true),
+        ];
+
+        for (input, result) in tests {
+            let evaluated = test_eval(input);
+            test_boolean_object(&evaluated, result);
+        }
+
+        test_integer_object(
+            &test_eval("let big = 9223372036854775807 * 2; {big: 1}[9223372036854775807 * 2]"),
+            1,
+        );
+    }
+
+    #[test]
+    fn test_eval_float_expression() {
+        let tests = [
+            ("3.14", "3.14"),
+            ("1.0", "1.0"),
+            (".5", "0.5"),
+            ("1e-9", "1e-9"),
+            ("-2.5", "-2.5"),
+            ("1 + 2.5", "3.5"),
+            ("2.5 + 1", "3.5"),
+            ("10 / 4.0", "2.5"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("3 * 1.5 - 0.5", "4.0"),
+            ("1e300 * 10", "1e301"),
+            ("9223372036854775807 * 2 + 0.5", "1.8446744073709552e19"),
+        ];
+
+        for (input, result) in tests {
+            let evaluated = test_eval(input);
+            assert_eq!(evaluated.inspect(), result);
+        }
+
+        let tests = [
+            ("1 < 1.5", true),
+            ("2.5 > 3", false),
+            ("1 == 1.0", true),
+            ("1.0 != 1", false),
+            ("0.1 + 0.2 == 0.3", false),
+            ("9223372036854775807 * 2 > 1e18", true),
+        ];
+
+        for (input, result) in tests {
+            let evaluated = test_eval(input);
+            test_boolean_object(&evaluated, result);
+        }
+    }
+
     #[test]
     fn test_eval_boolean_expression() {
         let tests = [
@@ -331,6 +712,170 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_eval_string_expression() {
+        let tests = [
+            (r#""hello""#, object::Object::String("hello".to_string())),
+            (
+                r#""hello" + " " + "world""#,
+                object::Object::String("hello world".to_string()),
+            ),
+            (
+                r#"let greet = fn(name) { "hi, " + name }; greet("monkey")"#,
+                object::Object::String("hi, monkey".to_string()),
+            ),
+            (r#""a" == "a""#, object::Object::Boolean(true)),
+            (r#""a" == "b""#, object::Object::Boolean(false)),
+            (r#""a" != "b""#, object::Object::Boolean(true)),
+            (r#""\u{3042}" == "あ""#, object::Object::Boolean(true)),
+        ];
+
+        for (input, result) in tests {
+            let evaluated = test_eval(input);
+            assert_eq!(evaluated, result);
+        }
+    }
+
+    #[test]
+    fn test_eval_array_expression() {
+        let tests = [
+            (
+                "[1, 2 * 2, 3 + 3]",
+                object::Object::Array(vec![
+                    object::Object::Integer(1),
+                    object::Object::Integer(4),
+                    object::Object::Integer(6),
+                ]),
+            ),
+            ("[1, 2, 3][0]", object::Object::Integer(1)),
+            ("[1, 2, 3][1]", object::Object::Integer(2)),
+            ("let i = 0; [1][i];", object::Object::Integer(1)),
+            ("[1, 2, 3][1 + 1];", object::Object::Integer(3)),
+            (
+                "let a = [1, 2, 3]; a[0] + a[1] + a[2];",
+                object::Object::Integer(6),
+            ),
+            ("[1, 2, 3][-1]", object::Object::Integer(3)),
+            ("[1, 2, 3][-3]", object::Object::Integer(1)),
+            ("[[1, 2], [3]][0][1]", object::Object::Integer(2)),
+        ];
+
+        for (input, result) in tests {
+            let evaluated = test_eval(input);
+            assert_eq!(evaluated, result);
+        }
+
+        assert_eq!(
+            test_eval(r#"[1, "a", [true]]"#).inspect(),
+            r#"[1, "a", [true]]"#
+        );
+    }
+
+    #[test]
+    fn test_eval_hash_expression() {
+        let input = r#"let two = "two";
+        {
+            "one": 10 - 9,
+            two: 1 + 1,
+            "thr" + "ee": 6 / 2,
+            4: 4,
+            true: 5,
+            false: 6
+        }"#;
+
+        let evaluated = test_eval(input);
+        let pairs = if let object::Object::Hash(pairs) = evaluated {
+            pairs
+        } else {
+            panic!("Object::Hash を期待しましたが、{:?}でした。", evaluated);
+        };
+
+        let expected = [
+            (object::HashKey::String("one".to_string()), 1),
+            (object::HashKey::String("two".to_string()), 2),
+            (object::HashKey::String("three".to_string()), 3),
+            (object::HashKey::Integer(4), 4),
+            (object::HashKey::Boolean(true), 5),
+            (object::HashKey::Boolean(false), 6),
+        ];
+
+        assert_eq!(pairs.len(), expected.len());
+        for (key, value) in expected {
+            test_integer_object(&pairs[&key], value);
+        }
+
+        let tests = [
+            (r#"{"foo": 5}["foo"]"#, object::Object::Integer(5)),
+            (r#"{"foo": 5}["bar"]"#, object::Object::Null),
+            (
+                r#"let key = "foo"; {"foo": 5}[key]"#,
+                object::Object::Integer(5),
+            ),
+            (r#"{}["foo"]"#, object::Object::Null),
+            ("{5: 5}[5]", object::Object::Integer(5)),
+            ("{true: 5}[true]", object::Object::Integer(5)),
+            ("{false: 5}[false]", object::Object::Integer(5)),
+        ];
+
+        for (input, result) in tests {
+            let evaluated = test_eval(input);
+            assert_eq!(evaluated, result);
+        }
+
+        assert_eq!(
+            test_eval(r#"{"b": [1], 2: "a", true: {}}"#).inspect(),
+            r#"{2: "a", true: {}, "b": [1]}"#
+        );
+    }
+
+    #[test]
+    fn test_eval_builtin_function() {
+        let tests = [
+            (r#"len("")"#, object::Object::Integer(0)),
+            (r#"len("four")"#, object::Object::Integer(4)),
+            (r#"len("こんにちは")"#, object::Object::Integer(5)),
+            ("len([1, 2, 3])", object::Object::Integer(3)),
+            (r#"len({"a": 1})"#, object::Object::Integer(1)),
+            ("first([1, 2, 3])", object::Object::Integer(1)),
+            ("first([])", object::Object::Null),
+            ("last([1, 2, 3])", object::Object::Integer(3)),
+            ("last([])", object::Object::Null),
+            (
+                "rest([1, 2, 3])",
+                object::Object::Array(vec![object::Object::Integer(2), object::Object::Integer(3)]),
+            ),
+            ("rest([])", object::Object::Null),
+            (
+                "push([], 1)",
+                object::Object::Array(vec![object::Object::Integer(1)]),
+            ),
+            ("let a = [1]; push(a, 2); a", object::Object::Array(vec![object::Object::Integer(1)])),
+            ("puts(1, \"a\")", object::Object::Null),
+            ("let len = fn(x) { 42 }; len([1])", object::Object::Integer(42)),
+            (
+                "let map = fn(arr, f) {
+                    let iter = fn(arr, acc) {
+                        if (len(arr) == 0) { acc } else { iter(rest(arr), push(acc, f(first(arr)))) }
+                    };
+                    iter(arr, [])
+                };
+                map([1, 2, 3], fn(x) { x * 2 })",
+                object::Object::Array(vec![
+                    object::Object::Integer(2),
+                    object::Object::Integer(4),
+                    object::Object::Integer(6),
+                ]),
+            ),
+        ];
+
+        for (input, result) in tests {
+            let evaluated = test_eval(input);
+            assert_eq!(evaluated, result);
+        }
+
+        assert_eq!(test_eval("len").inspect(), "builtin function: len");
+    }
+
     #[test]
     fn test_eval_if_expression() {
         let tests = [
@@ -429,12 +974,16 @@ mod tests {
 
     #[test]
     fn test_eval_error() {
+        message::set_locale(Locale::Ja);
         let tests = [
-            ("5 + true;", "型のミスマッチ: 5 + true"),
-            ("5 + true; 5;", "型のミスマッチ: 5 + true"),
-            ("-true", "未知の演算子: -true"),
-            ("true + false", "未知の演算子: true + false"),
-            ("if (true) { true * false; }", "未知の演算子: true * false"),
+            ("5 + true;", "1:1: 型のミスマッチ: 5 + true"),
+            ("5 + true; 5;", "1:1: 型のミスマッチ: 5 + true"),
+            ("-true", "1:1: 未知の演算子: -true"),
+            ("true + false", "1:1: 未知の演算子: true + false"),
+            (
+                "if (true) { true * false; }",
+                "1:13: 未知の演算子: true * false",
+            ),
             (
                 "if (true) {
                     if (true) {
@@ -442,10 +991,56 @@ mod tests {
                     }
                     0;
                 }",
-                "未知の演算子: false / false",
+                "3:32: 未知の演算子: false / false",
+            ),
+            ("-true + 100", "1:1: 未知の演算子: -true"),
+            ("foo", "1:1: 識別子が見つかりません。: foo"),
+            (r#""a" - "b""#, r#"1:1: 未知の演算子: "a" - "b""#),
+            (r#""a" + 1"#, r#"1:1: 型のミスマッチ: "a" + 1"#),
+            ("[1, 2, 3][3]", "1:1: 添字が範囲外です。: 3 (長さ 3)"),
+            ("[1, 2, 3][-4]", "1:1: 添字が範囲外です。: -4 (長さ 3)"),
+            ("[][0]", "1:1: 添字が範囲外です。: 0 (長さ 0)"),
+            ("1[0]", "1:1: 未知の添字演算子: 1[0]"),
+            ("[1][true]", "1:1: 未知の添字演算子: [1][true]"),
+            (
+                r#"{"name": "Monkey"}[fn(x) { x }];"#,
+                "1:1: ハッシュのキーに使えません。: fn(x){\nx;\n}\n",
+            ),
+            ("len(1)", "1:1: 引数の型が不正です。: len(1)"),
+            (
+                r#"len("one", "two")"#,
+                "1:1: 引数の数が合いません。: len (1個を期待しましたが、2個でした。)",
+            ),
+            ("first(1)", "1:1: 引数の型が不正です。: first(1)"),
+            ("last(true)", "1:1: 引数の型が不正です。: last(true)"),
+            (
+                "rest()",
+                "1:1: 引数の数が合いません。: rest (1個を期待しましたが、0個でした。)",
+            ),
+            ("push(1, 1)", "1:1: 引数の型が不正です。: push(1)"),
+            ("1 / 0", "1:1: ゼロで除算しました。: 1 / 0"),
+            ("let a = 0; 10 / a", "1:12: ゼロで除算しました。: 10 / 0"),
+            (
+                "let big = 9223372036854775807 * 2; big / (big - big)",
+                "1:36: ゼロで除算しました。: 18446744073709551614 / 0",
+            ),
+            ("1.5 / 0", "1:1: ゼロで除算しました。: 1.5 / 0"),
+            ("1 / 0.0", "1:1: ゼロで除算しました。: 1.0 / 0"),
+            ("1.5 + true", "1:1: 型のミスマッチ: 1.5 + true"),
+            ("{1.5: 1}", "1:2: ハッシュのキーに使えません。: 1.5"),
+            ("5(1)", "1:1: 関数ではありません。: 5"),
+            ("true()", "1:1: 関数ではありません。: true"),
+            ("let a = [1]; a(); 1", "1:14: 関数ではありません。: [1]"),
+            (
+                "let add = fn(x, y) { x + y }; add(1)",
+                "1:31: 引数の数が合いません。: add (2個を期待しましたが、1個でした。)",
+            ),
+            (
+                "fn() { 1 }(1, 2)",
+                "1:1: 引数の数が合いません。: fn(){\n1;\n} (0個を期待しましたが、2個でした。)",
             ),
-            ("-true + 100", "未知の演算子: -true"),
-            ("foo", "識別子が見つかりません。: foo"),
+            ("{1: 2, [1]: 3}", "1:8: ハッシュのキーに使えません。: [1]"),
+            ("let a = 1;\n  a + b;", "2:7: 識別子が見つかりません。: b"),
         ];
 
         for (input, result) in tests {
@@ -455,6 +1050,8 @@ mod tests {
 
             let environment = environment::Environment::new();
             let evaluated = Evaluator::eval(&program, &mut Rc::new(RefCell::new(environment)));
+            assert_same_as_vm(&program, &evaluated);
+            assert_same_as_resolved(&program, &evaluated);
 
             match evaluated {
                 Ok(ok) => panic!("エラーを期待しましたが、{:?}でした。", ok),
@@ -488,10 +1085,55 @@ mod tests {
         let mut parser = parser::Parser::new(lexer);
         let program = parser.parse_program().expect("parser error");
         let environment = environment::Environment::new();
-        Evaluator::eval(&program, &mut Rc::new(RefCell::new(environment))).expect("evaluator error")
+        let evaluated = Evaluator::eval(&program, &mut Rc::new(RefCell::new(environment)));
+        assert_same_as_vm(&program, &evaluated);
+        assert_same_as_resolved(&program, &evaluated);
+        evaluated.expect("evaluator error")
+    }
+
+    /// バイトコード VM でも同じ結果・同じエラーになることを確かめる
+    fn assert_same_as_vm(
+        program: &ast::Program,
+        evaluated: &Result<object::Object, Box<dyn std::error::Error>>,
+    ) {
+        let input = program.to_code();
+        match (evaluated, crate::vm::eval(program)) {
+            // 関数は表現が異なるので表示で比べる
+            (Ok(expected @ object::Object::Function { .. }), Ok(actual)) => {
+                assert_eq!(actual.type_name(), "function", "{}", input);
+                assert_eq!(actual.inspect(), expected.inspect(), "{}", input);
+            }
+            (Ok(expected), Ok(actual)) => assert_eq!(&actual, expected, "{}", input),
+            (Err(expected), Err(actual)) => {
+                assert_eq!(actual.to_string(), expected.to_string(), "{}", input)
+            }
+            (expected, actual) => panic!("{}: {:?} != {:?}", input, expected, actual),
+        }
+    }
+
+    /// 静的解決してから評価しても同じ結果・同じエラーになることを確かめる
+    fn assert_same_as_resolved(
+        program: &ast::Program,
+        evaluated: &Result<object::Object, Box<dyn std::error::Error>>,
+    ) {
+        let input = program.to_code();
+        let mut resolved = program.clone();
+        let mut environment = environment::Environment::new();
+        let actual = crate::resolver::resolve(&mut resolved, &mut environment)
+            .and_then(|_| Evaluator::eval(&resolved, &mut Rc::new(RefCell::new(environment))));
+        match (evaluated, actual) {
+            (Ok(expected @ object::Object::Function { .. }), Ok(actual)) => {
+                assert_eq!(actual.inspect(), expected.inspect(), "{}", input)
+            }
+            (Ok(expected), Ok(actual)) => assert_eq!(&actual, expected, "{}", input),
+            (Err(expected), Err(actual)) => {
+                assert_eq!(actual.to_string(), expected.to_string(), "{}", input)
+            }
+            (expected, actual) => panic!("{}: {:?} != {:?}", input, expected, actual),
+        }
     }
 
-    fn test_integer_object(object: &object::Object, expected: i32) {
+    fn test_integer_object(object: &object::Object, expected: i64) {
         let integer = if let object::Object::Integer(integer) = object {
             integer
         } else {
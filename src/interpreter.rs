@@ -0,0 +1,254 @@
+use crate::ast;
+use crate::builtin;
+use crate::environment;
+use crate::error;
+use crate::evaluator;
+use crate::lexer;
+use crate::object;
+use crate::parser;
+use crate::resolver;
+use std::{cell::RefCell, rc::Rc};
+
+/// 字句解析・構文解析・評価とグローバル環境をまとめた、ホストアプリケーション向けの窓口
+///
+/// 値は `From` / `TryFrom` で Rust の型と相互に変換できる。
+pub struct Interpreter {
+    environment: Rc<RefCell<environment::Environment>>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter {
+            environment: Rc::new(RefCell::new(environment::Environment::new())),
+        }
+    }
+
+    /// ソースを解析・評価し、最後の文の値を返す (束縛はセッションをまたいで残る)
+    ///
+    /// 定義されていない名前は実行時に名前で探すので、後の呼び出しで定義してもよい。
+    pub fn eval_str(&mut self, input: &str) -> Result<object::Object, Box<dyn std::error::Error>> {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = parser::Parser::new(lexer);
+        let mut program = parser.parse_program()?;
+        resolver::resolve_incremental(&mut program, &mut self.environment.borrow_mut())?;
+        self.eval_program(&program)
+    }
+
+    /// 識別子をこのインタプリタのグローバル環境に対して静的解決する
+    ///
+    /// 定義されていない名前を参照していればエラーになる。解決した Program を別の
+    /// インタプリタで評価した場合は、評価するインタプリタに対して解決し直す。
+    pub fn resolve(
+        &mut self,
+        program: &mut ast::Program,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        resolver::resolve(program, &mut self.environment.borrow_mut())
+    }
+
+    /// 解析済みの Program を評価する (静的解決していなければ、名前で変数を探す)
+    pub fn eval_program(
+        &mut self,
+        program: &ast::Program,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        evaluator::Evaluator::eval(program, &mut self.environment)
+    }
+
+    pub fn set_global<T>(&mut self, name: &str, value: T)
+    where
+        T: Into<object::Object>,
+    {
+        self.environment
+            .borrow_mut()
+            .set(name.to_string(), value.into());
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<object::Object> {
+        self.environment.borrow().get(name)
+    }
+
+    /// グローバル環境の束縛を名前順で返す
+    pub fn globals(&self) -> Vec<(String, object::Object)> {
+        self.environment.borrow().bindings()
+    }
+
+    /// スクリプトから呼び出せるネイティブ関数を登録する
+    ///
+    /// 同名の組み込み関数よりも優先される。関数が返したエラーは呼び出し位置付きの
+    /// `EvaluatorError::HostFunctionError` に変換される。
+    pub fn register_fn<F>(&mut self, name: &str, function: F)
+    where
+        F: Fn(&[object::Object]) -> Result<object::Object, Box<dyn std::error::Error>> + 'static,
+    {
+        let function_name = name.to_string();
+        let builtin = builtin::Builtin::new(name, move |args, span| {
+            function(args).map_err(|err| {
+                error::EvaluatorError::HostFunctionError {
+                    function: function_name.clone(),
+                    message: err.to_string(),
+                    span,
+                }
+                .into()
+            })
+        });
+        self.set_global(name, object::Object::Builtin(builtin));
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{self, Locale};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_eval_str_keeps_bindings() {
+        let mut interpreter = Interpreter::new();
+        interpreter.eval_str("let a = 5;").expect("evaluator error");
+        let result = interpreter.eval_str("a * 2").expect("evaluator error");
+
+        assert_eq!(result, object::Object::Integer(10));
+        assert_eq!(
+            interpreter.get_global("a"),
+            Some(object::Object::Integer(5))
+        );
+        assert_eq!(interpreter.get_global("b"), None);
+
+        interpreter.eval_str("let b = 1;").expect("evaluator error");
+        let names = interpreter
+            .globals()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_eval_str_undefined_name() {
+        message::set_locale(Locale::Ja);
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("limit", 3);
+        let result = interpreter
+            .eval_str("let f = fn(n) { if (n > limit) { n } else { g(n) } }; let g = fn(n) { f(n + 1) }; f(0)")
+            .expect("evaluator error");
+        assert_eq!(result, object::Object::Integer(4));
+
+        // 後の入力で定義する名前を関数の中から参照できる
+        interpreter
+            .eval_str("let h = fn() { later() };")
+            .expect("evaluator error");
+        match interpreter.eval_str("h()") {
+            Ok(ok) => panic!("エラーを期待しましたが、{:?}でした。", ok),
+            Err(err) => assert_eq!(format!("{}", err), "1:16: 識別子が見つかりません。: later"),
+        }
+        interpreter
+            .eval_str("let later = fn() { 1 };")
+            .expect("evaluator error");
+        assert_eq!(
+            interpreter.eval_str("h()").expect("evaluator error"),
+            object::Object::Integer(1)
+        );
+        assert_eq!(
+            interpreter
+                .eval_str("if (false) { missing } else { f(2) }")
+                .expect("evaluator error"),
+            object::Object::Integer(4)
+        );
+
+        // 束縛より前に読んだローカル変数は、後の入力で定義したグローバル変数を見る
+        interpreter
+            .eval_str("let k = fn(c) { if (c) { let v = 1; }; v };")
+            .expect("evaluator error");
+        interpreter.eval_str("let v = 5;").expect("evaluator error");
+        assert_eq!(
+            interpreter
+                .eval_str("[k(false), k(true)]")
+                .expect("evaluator error")
+                .inspect(),
+            "[5, 1]"
+        );
+    }
+
+    #[test]
+    fn test_resolve_other_interpreter() {
+        // 別のインタプリタに対して解決した Program は、評価するインタプリタに対して解決し直す
+        let mut program = parser::Parser::new(lexer::Lexer::new(
+            "let b = 2; let f = fn(x) { a + b + x }; f(3)",
+        ))
+        .parse_program()
+        .expect("parser error");
+        let mut resolving = Interpreter::new();
+        resolving.set_global("a", 1);
+        resolving.resolve(&mut program).expect("resolver error");
+
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("z", 0);
+        interpreter.set_global("a", 10);
+        assert_eq!(
+            interpreter.eval_program(&program).expect("evaluator error"),
+            object::Object::Integer(15)
+        );
+        assert_eq!(
+            interpreter.get_global("b"),
+            Some(object::Object::Integer(2))
+        );
+    }
+
+    #[test]
+    fn test_set_global() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_global("count", 3);
+        interpreter.set_global("name", "monkey");
+        interpreter.set_global("flags", vec![true, false]);
+
+        let result = interpreter
+            .eval_str(r#"if (count > 2) { name + ":many" } else { name }"#)
+            .expect("evaluator error");
+        assert_eq!(String::try_from(result), Ok("monkey:many".to_string()));
+
+        let flags = interpreter.get_global("flags").expect("flags");
+        assert_eq!(
+            Vec::<object::Object>::try_from(flags),
+            Ok(vec![
+                object::Object::Boolean(true),
+                object::Object::Boolean(false)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_register_fn() {
+        message::set_locale(Locale::Ja);
+        let mut interpreter = Interpreter::new();
+        interpreter.register_fn("add", |args| {
+            let mut sum = 0;
+            for arg in args {
+                sum += i64::try_from(arg.clone()).map_err(|_| "整数ではありません。")?;
+            }
+            Ok(sum.into())
+        });
+        interpreter.register_fn("len", |_| Ok(object::Object::Integer(-1)));
+
+        let result = interpreter
+            .eval_str("add(1, 2, 3)")
+            .expect("evaluator error");
+        assert_eq!(i64::try_from(result.clone()), Ok(6));
+        assert_eq!(i32::try_from(result), Ok(6));
+
+        let result = interpreter.eval_str("len([1])").expect("evaluator error");
+        assert_eq!(result, object::Object::Integer(-1));
+
+        match interpreter.eval_str("\n add(1, true)") {
+            Ok(ok) => panic!("エラーを期待しましたが、{:?}でした。", ok),
+            Err(err) => assert_eq!(
+                format!("{}", err),
+                "2:2: add の実行に失敗しました。: 整数ではありません。"
+            ),
+        }
+    }
+}
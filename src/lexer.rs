@@ -1,27 +1,110 @@
-use crate::token::Token;
+use crate::bigint;
+use crate::error::LexerError;
+use crate::token::{Position, Span, SpannedToken, Token};
+
+/// 予約語 (識別子に使えない) と、そのトークン
+pub const KEYWORDS: [(&str, Token); 7] = [
+    ("fn", Token::Function),
+    ("let", Token::Let),
+    ("true", Token::True),
+    ("false", Token::False),
+    ("if", Token::If),
+    ("else", Token::Else),
+    ("return", Token::Return),
+];
 
 pub struct Lexer<'a> {
     input: std::str::Chars<'a>,
     current_char: char,
     next_char: char,
+    position: Position,  // current_char の位置
+    keep_comments: bool, // コメントを Token::Comment として返すか
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
-        let mut lexer = Lexer {
-            input: input.chars(),
-            current_char: '\u{0}',
-            next_char: '\u{0}',
-        };
+        let mut chars = input.chars();
         // 準備
-        lexer.seek_char();
-        lexer.seek_char();
+        let current_char = chars.next().unwrap_or('\u{0}');
+        let next_char = chars.next().unwrap_or('\u{0}');
+
+        Lexer {
+            input: chars,
+            current_char,
+            next_char,
+            position: Position::new(),
+            keep_comments: false,
+        }
+    }
 
+    /// コメントを読み飛ばさずに Token::Comment として返す字句解析器を作る (整形ツール向け)
+    pub fn with_comments(input: &'a str) -> Self {
+        let mut lexer = Lexer::new(input);
+        lexer.keep_comments = true;
         lexer
     }
 
-    pub fn read_next_token(&mut self) -> Token {
-        self.skip_whitespace();
+    pub fn read_next_token(&mut self) -> SpannedToken {
+        loop {
+            self.skip_whitespace();
+            let start = self.position;
+            let token = match (self.current_char, self.next_char) {
+                ('/', '/') => self.read_line_comment(),
+                ('/', '*') => self.read_block_comment(),
+                _ => self.read_token(),
+            };
+
+            if let Token::Comment(_) = token {
+                if !self.keep_comments {
+                    continue;
+                }
+            }
+
+            return SpannedToken {
+                token,
+                span: Span::new(start, self.position),
+            };
+        }
+    }
+
+    /// `//` から行末まで (改行は含まない)
+    fn read_line_comment(&mut self) -> Token {
+        let mut comment = String::new();
+        while self.current_char != '\n' && self.current_char != '\u{0}' {
+            comment.push(self.current_char);
+            self.seek_char();
+        }
+        Token::Comment(comment)
+    }
+
+    /// `/*` から対応する `*/` まで (入れ子可)
+    fn read_block_comment(&mut self) -> Token {
+        let mut comment = String::new();
+        let mut depth = 0;
+        loop {
+            match (self.current_char, self.next_char) {
+                ('\u{0}', _) => return Token::Error(LexerError::UnterminatedBlockComment),
+                ('/', '*') => {
+                    depth += 1;
+                    comment.push_str("/*");
+                    self.seek_char();
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    comment.push_str("*/");
+                    self.seek_char();
+                    if depth == 0 {
+                        self.seek_char(); // コメントの次の文字 に進む
+                        return Token::Comment(comment);
+                    }
+                }
+                (ch, _) => comment.push(ch),
+            }
+            self.seek_char();
+        }
+    }
+
+    fn read_token(&mut self) -> Token {
         let token = match self.current_char {
             '=' => {
                 if self.next_char == '=' {
@@ -35,6 +118,7 @@ impl<'a> Lexer<'a> {
             '(' => Token::Lparentheses,
             ')' => Token::Rparentheses,
             ',' => Token::Comma,
+            ':' => Token::Colon,
             '+' => Token::Plus,
             '-' => Token::Minus,
             '!' => {
@@ -49,16 +133,18 @@ impl<'a> Lexer<'a> {
             '*' => Token::Asterisk,
             '<' => Token::LessThan,
             '>' => Token::GreaterThan,
+            '"' => self.read_string(),
             '{' => Token::Lbrace,
             '}' => Token::Rbrace,
+            '[' => Token::Lbracket,
+            ']' => Token::Rbracket,
             '\u{0}' => Token::EndOfFile,
             ch => {
                 if is_letter(ch) {
                     let identifier = self.read_by_checker(is_letter);
                     return Lexer::lookup_identifier(identifier.as_str());
-                } else if is_digit(ch) {
-                    let number_str = self.read_by_checker(is_digit);
-                    return Token::Integer(number_str.parse().unwrap());
+                } else if is_digit(ch) || (ch == '.' && is_digit(self.next_char)) {
+                    return self.read_number();
                 }
                 Token::Illegal
             }
@@ -68,6 +154,9 @@ impl<'a> Lexer<'a> {
     }
 
     fn seek_char(&mut self) {
+        if self.current_char != '\u{0}' {
+            self.position = self.position.advance(self.current_char);
+        }
         self.current_char = self.next_char;
         self.next_char = self.input.next().unwrap_or('\u{0}');
     }
@@ -84,6 +173,116 @@ impl<'a> Lexer<'a> {
         letter
     }
 
+    /// 数値リテラルを読み取る (小数点か指数部があれば浮動小数点数)
+    fn read_number(&mut self) -> Token {
+        let mut number_str = self.read_by_checker(is_digit);
+        let mut is_float = false;
+
+        if self.current_char == '.' && is_digit(self.next_char) {
+            is_float = true;
+            self.seek_char(); // 小数部 に進む
+            number_str.push('.');
+            number_str.push_str(&self.read_by_checker(is_digit));
+        }
+
+        if self.current_char == 'e' || self.current_char == 'E' {
+            is_float = true;
+            number_str.push(self.current_char);
+            self.seek_char(); // 符号 or 指数 に進む
+            if self.current_char == '+' || self.current_char == '-' {
+                number_str.push(self.current_char);
+                self.seek_char(); // 指数 に進む
+            }
+            let exponent = self.read_by_checker(is_digit);
+            if exponent.is_empty() {
+                return Token::Error(LexerError::InvalidNumber(number_str));
+            }
+            number_str.push_str(&exponent);
+        }
+
+        if !is_float {
+            // i64 に収まらない整数は多倍長整数のリテラルにする
+            return match number_str.parse() {
+                Ok(integer) => Token::Integer(integer),
+                Err(_) => match bigint::BigInt::from_decimal(&number_str) {
+                    Some(bigint) => Token::BigInt(bigint),
+                    None => Token::Error(LexerError::InvalidNumber(number_str)),
+                },
+            };
+        }
+
+        match number_str.parse::<f64>() {
+            Ok(float) if float.is_finite() => Token::Float(float),
+            _ => Token::Error(LexerError::InvalidNumber(number_str)),
+        }
+    }
+
+    /// 文字列リテラルを読み取る (current_char は開始の `"`、終了時は閉じの `"`)
+    fn read_string(&mut self) -> Token {
+        let mut value = String::new();
+        let mut invalid_escape = None;
+        loop {
+            self.seek_char(); // 次の文字 に進む
+            match self.current_char {
+                '"' => break,
+                '\u{0}' => return Token::Error(LexerError::UnterminatedString),
+                '\\' => {
+                    self.seek_char(); // エスケープ文字 に進む
+                    match self.read_escape() {
+                        Ok(ch) => value.push(ch),
+                        Err(sequence) => {
+                            invalid_escape.get_or_insert(sequence);
+                        }
+                    }
+                }
+                ch => value.push(ch),
+            }
+        }
+
+        match invalid_escape {
+            None => Token::String(value),
+            Some(sequence) => Token::Error(LexerError::InvalidEscape(sequence.into())),
+        }
+    }
+
+    /// `\` に続くエスケープ文字を読み取る (current_char はエスケープの最後の文字で終わる)
+    ///
+    /// 不正なら、そこまでに読んだエスケープシーケンス (`\q` など) を返す
+    fn read_escape(&mut self) -> Result<char, String> {
+        let mut sequence = format!("\\{}", self.current_char);
+        match self.current_char {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            '"' => Ok('"'),
+            '\\' => Ok('\\'),
+            'u' => {
+                if self.next_char != '{' {
+                    return Err(sequence);
+                }
+                self.seek_char(); // { に進む
+
+                let mut code = String::new();
+                while self.next_char.is_ascii_hexdigit() {
+                    self.seek_char(); // 16進数 に進む
+                    code.push(self.current_char);
+                }
+                sequence.push('{');
+                sequence.push_str(&code);
+                if self.next_char != '}' || code.is_empty() || code.len() > 6 {
+                    return Err(sequence);
+                }
+                self.seek_char(); // } に進む
+                sequence.push('}');
+
+                u32::from_str_radix(&code, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or(sequence)
+            }
+            _ => Err(sequence),
+        }
+    }
+
     fn skip_whitespace(&mut self) {
         while (self.current_char == ' ')
             || (self.current_char == '\t')
@@ -94,27 +293,40 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn lookup_identifier(identifier: &str) -> Token {
-        match identifier {
-            "fn" => Token::Function,
-            "let" => Token::Let,
-            "true" => Token::True,
-            "false" => Token::False,
-            "if" => Token::If,
-            "else" => Token::Else,
-            "return" => Token::Return,
-            _ => Token::Identifier(identifier.to_string()),
+    /// 予約語ならそのトークン、それ以外は識別子にする
+    pub fn lookup_identifier(identifier: &str) -> Token {
+        KEYWORDS
+            .iter()
+            .find(|(keyword, _)| *keyword == identifier)
+            .map(|(_, token)| token.clone())
+            .unwrap_or_else(|| Token::Identifier(identifier.to_string()))
+    }
+}
+
+/// 文字列を、字句解析器で読み戻せる `"..."` 形式のリテラルに変換する
+pub fn quote_string(value: &str) -> String {
+    let mut literal = String::from('"');
+    for ch in value.chars() {
+        match ch {
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            ch if ch.is_control() => literal.push_str(&format!("\\u{{{:x}}}", ch as u32)),
+            ch => literal.push(ch),
         }
     }
+    literal.push('"');
+    literal
 }
 
 fn is_digit(ch: char) -> bool {
-    ('0' <= ch) && (ch <= '9')
+    ch.is_ascii_digit()
 }
 
 fn is_letter(ch: char) -> bool {
-    let is_lower_alpha = 'a' <= ch && ch <= 'z';
-    let is_upper_alpha = 'A' <= ch && ch <= 'Z';
+    let is_lower_alpha = ch.is_ascii_lowercase();
+    let is_upper_alpha = ch.is_ascii_uppercase();
     let is_under_score = ch == '_';
     is_lower_alpha || is_upper_alpha || is_under_score
 }
@@ -134,7 +346,7 @@ x + y;
 
 let result = add(five, ten);
 
-!-/*5;
+!-/ *5;
 5 < 10 > 5;
 
 if (5 < 10) {
@@ -145,6 +357,8 @@ if (5 < 10) {
 
 10 == 10;
 10 != 9;
+[1, 2];
+{\"foo\": \"bar\"}
 ";
 
         let tokens = [
@@ -221,14 +435,256 @@ if (5 < 10) {
             Token::NotEqual,
             Token::Integer(9),
             Token::Semicolon,
+            Token::Lbracket,
+            Token::Integer(1),
+            Token::Comma,
+            Token::Integer(2),
+            Token::Rbracket,
+            Token::Semicolon,
+            Token::Lbrace,
+            Token::String("foo".to_string()),
+            Token::Colon,
+            Token::String("bar".to_string()),
+            Token::Rbrace,
             Token::EndOfFile,
         ];
 
         let mut lexer = Lexer::new(input);
 
         for tok in tokens.iter() {
-            let next_token = &lexer.read_next_token();
+            let next_token = &lexer.read_next_token().token;
             assert_eq!(next_token, tok);
         }
     }
+
+    #[test]
+    fn test_next_token_span() {
+        let input = "let five = 5;
+  five == 10;";
+
+        let spans = [
+            (Token::Let, (1, 1), (1, 4)),
+            (Token::Identifier("five".to_string()), (1, 5), (1, 9)),
+            (Token::Assign, (1, 10), (1, 11)),
+            (Token::Integer(5), (1, 12), (1, 13)),
+            (Token::Semicolon, (1, 13), (1, 14)),
+            (Token::Identifier("five".to_string()), (2, 3), (2, 7)),
+            (Token::Equal, (2, 8), (2, 10)),
+            (Token::Integer(10), (2, 11), (2, 13)),
+            (Token::Semicolon, (2, 13), (2, 14)),
+            (Token::EndOfFile, (2, 14), (2, 14)),
+        ];
+
+        let mut lexer = Lexer::new(input);
+
+        for (tok, start, end) in spans.iter() {
+            let next_token = lexer.read_next_token();
+            assert_eq!(next_token.token, *tok);
+            assert_eq!(
+                (next_token.span.start.line, next_token.span.start.column),
+                *start
+            );
+            assert_eq!((next_token.span.end.line, next_token.span.end.column), *end);
+        }
+
+        let mut lexer = Lexer::new("a\n  b");
+        lexer.read_next_token();
+        let token = lexer.read_next_token();
+        assert_eq!(token.span.start.offset, 4);
+        assert_eq!(token.span.end.offset, 5);
+    }
+
+    #[test]
+    fn test_string_token() {
+        let problem = [
+            (r#""foobar""#, Token::String("foobar".to_string())),
+            (r#""foo bar""#, Token::String("foo bar".to_string())),
+            (r#""""#, Token::String("".to_string())),
+            (
+                r#""a\nb\tc\"d\\e""#,
+                Token::String("a\nb\tc\"d\\e".to_string()),
+            ),
+            (r#""\u{41}\u{1F600}""#, Token::String("A😀".to_string())),
+            (
+                r#""unterminated"#,
+                Token::Error(LexerError::UnterminatedString),
+            ),
+            (
+                r#""\q""#,
+                Token::Error(LexerError::InvalidEscape(r"\q".into())),
+            ),
+            (
+                r#""\u{110000}""#,
+                Token::Error(LexerError::InvalidEscape(r"\u{110000}".into())),
+            ),
+            (
+                r#""\u41""#,
+                Token::Error(LexerError::InvalidEscape(r"\u".into())),
+            ),
+            (r#""\q"#, Token::Error(LexerError::UnterminatedString)),
+        ];
+
+        for (input, result) in problem {
+            let mut lexer = Lexer::new(input);
+            assert_eq!(lexer.read_next_token().token, result);
+        }
+
+        let mut lexer = Lexer::new(r#""\q" 5"#);
+        lexer.read_next_token();
+        assert_eq!(lexer.read_next_token().token, Token::Integer(5));
+    }
+
+    #[test]
+    fn test_bigint_token() {
+        let mut lexer = Lexer::new("9223372036854775807 9223372036854775808 99999999999999999999;");
+        assert_eq!(lexer.read_next_token().token, Token::Integer(i64::MAX));
+        assert_eq!(
+            lexer.read_next_token().token,
+            Token::BigInt(&bigint::BigInt::from(i64::MAX) + &bigint::BigInt::from(1))
+        );
+        let token = lexer.read_next_token();
+        assert_eq!(
+            token.token,
+            Token::BigInt(bigint::BigInt::from_decimal("99999999999999999999").unwrap())
+        );
+        assert_eq!((token.span.start.column, token.span.end.column), (41, 61));
+        assert_eq!(lexer.read_next_token().token, Token::Semicolon);
+    }
+
+    #[test]
+    fn test_float_token() {
+        let problem = [
+            ("2.75", Token::Float(2.75)),
+            ("0.5", Token::Float(0.5)),
+            (".5", Token::Float(0.5)),
+            ("1e-9", Token::Float(1e-9)),
+            ("2.5E+3", Token::Float(2500.0)),
+            ("10e2", Token::Float(1000.0)),
+            (
+                "1e",
+                Token::Error(LexerError::InvalidNumber("1e".to_string())),
+            ),
+            (
+                "1e+",
+                Token::Error(LexerError::InvalidNumber("1e+".to_string())),
+            ),
+            (
+                "1e999",
+                Token::Error(LexerError::InvalidNumber("1e999".to_string())),
+            ),
+        ];
+
+        for (input, result) in problem {
+            let mut lexer = Lexer::new(input);
+            assert_eq!(lexer.read_next_token().token, result);
+            assert_eq!(lexer.read_next_token().token, Token::EndOfFile);
+        }
+
+        let mut lexer = Lexer::new("[1.5, .25];");
+        let tokens = [
+            Token::Lbracket,
+            Token::Float(1.5),
+            Token::Comma,
+            Token::Float(0.25),
+            Token::Rbracket,
+            Token::Semicolon,
+        ];
+        for tok in tokens.iter() {
+            assert_eq!(&lexer.read_next_token().token, tok);
+        }
+    }
+
+    #[test]
+    fn test_comment() {
+        let input = "let a = 1; // 行コメント
+/* ブロック
+   /* 入れ子 */ コメント */ a / 2 /**/;
+// 最後の行";
+
+        let tokens = [
+            Token::Let,
+            Token::Identifier("a".to_string()),
+            Token::Assign,
+            Token::Integer(1),
+            Token::Semicolon,
+            Token::Identifier("a".to_string()),
+            Token::Slash,
+            Token::Integer(2),
+            Token::Semicolon,
+            Token::EndOfFile,
+        ];
+
+        let mut lexer = Lexer::new(input);
+        for tok in tokens.iter() {
+            assert_eq!(&lexer.read_next_token().token, tok);
+        }
+
+        let mut lexer = Lexer::with_comments(input);
+        let mut comments = Vec::new();
+        loop {
+            let token = lexer.read_next_token();
+            match token.token {
+                Token::Comment(comment) => comments.push((comment, token.span)),
+                Token::EndOfFile => break,
+                _ => {}
+            }
+        }
+
+        assert_eq!(comments.len(), 4);
+        assert_eq!(comments[0].0, "// 行コメント");
+        assert_eq!(comments[1].0, "/* ブロック\n   /* 入れ子 */ コメント */");
+        assert_eq!(
+            (
+                comments[1].1.start.line,
+                comments[1].1.end.line,
+                comments[1].1.end.column
+            ),
+            (2, 3, 21)
+        );
+        assert_eq!(comments[2].0, "/**/");
+        assert_eq!(comments[3].0, "// 最後の行");
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut lexer = Lexer::new("1 /* /* */ 2;");
+        assert_eq!(lexer.read_next_token().token, Token::Integer(1));
+        let token = lexer.read_next_token();
+        assert_eq!(
+            token.token,
+            Token::Error(LexerError::UnterminatedBlockComment)
+        );
+        assert_eq!((token.span.start.column, token.span.end.column), (3, 14));
+        assert_eq!(lexer.read_next_token().token, Token::EndOfFile);
+    }
+
+    #[test]
+    fn test_keywords() {
+        for (keyword, token) in KEYWORDS {
+            assert_eq!(Lexer::lookup_identifier(keyword), token, "{}", keyword);
+        }
+        assert_eq!(
+            Lexer::lookup_identifier("lets"),
+            Token::Identifier("lets".to_string())
+        );
+    }
+
+    #[test]
+    fn test_quote_string() {
+        let problem = [
+            ("foobar", r#""foobar""#),
+            ("a\nb\tc\"d\\e", r#""a\nb\tc\"d\\e""#),
+            ("\u{7}", r#""\u{7}""#),
+        ];
+
+        for (input, result) in problem {
+            assert_eq!(quote_string(input), result);
+
+            let mut lexer = Lexer::new(result);
+            assert_eq!(
+                lexer.read_next_token().token,
+                Token::String(input.to_string())
+            );
+        }
+    }
 }
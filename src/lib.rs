@@ -1,9 +1,22 @@
 pub mod ast;
+pub mod bigint;
+pub mod builtin;
+pub mod bytecode_file;
+pub mod code;
+pub mod compiler;
+pub mod diagnostic;
 pub mod environment;
 pub mod error;
 pub mod evaluator;
+pub mod interpreter;
 pub mod lexer;
+pub mod line_editor;
+pub mod message;
 pub mod object;
 pub mod operator;
+pub mod optimizer;
 pub mod parser;
+pub mod repl;
+pub mod resolver;
 pub mod token;
+pub mod vm;
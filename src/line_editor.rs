@@ -0,0 +1,806 @@
+use crate::diagnostic;
+use std::fs::OpenOptions;
+use std::io::{stdin, stdout, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// 履歴ファイルの名前 (ホームディレクトリに置く)
+pub const HISTORY_FILE: &str = ".go_interpreter_history";
+
+/// 履歴に残す最大の行数
+pub const HISTORY_LIMIT: usize = 1000;
+
+/// 端末から読んだキー入力
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Tab,
+    Interrupt,     // Ctrl-C
+    Eof,           // Ctrl-D
+    ReverseSearch, // Ctrl-R
+    KillToEnd,     // Ctrl-K
+    KillToStart,   // Ctrl-U
+    DeleteWord,    // Ctrl-W
+    ClearScreen,   // Ctrl-L
+    Escape,
+    Unknown,
+}
+
+/// キー入力を処理した結果
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    Edited,
+    Submit(String),
+    Eof,
+    Interrupt,
+    ClearScreen,
+    Candidates(Vec<String>), // 補完候補が複数あって絞り込めない
+}
+
+/// 入力した行の履歴 (古い順)
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History {
+            entries: Vec::new(),
+            path: None,
+        }
+    }
+
+    /// 履歴ファイルを読み込み、以後追加した行をそのファイルに書き足す
+    ///
+    /// ファイルが無ければ空の履歴から始める。
+    pub fn with_file(path: &Path) -> Self {
+        let mut history = History::new();
+        if let Ok(contents) = std::fs::read_to_string(path) {
+            history.entries = contents.lines().map(|line| line.to_string()).collect();
+            if history.entries.len() > HISTORY_LIMIT {
+                history
+                    .entries
+                    .drain(..history.entries.len() - HISTORY_LIMIT);
+                // 古い行を捨てた内容で書き直す (失敗しても履歴が長くなるだけ)
+                let _ = std::fs::write(path, history.entries.join("\n") + "\n");
+            }
+        }
+        history.path = Some(path.to_path_buf());
+        history
+    }
+
+    /// 空行と直前と同じ行は追加しない
+    pub fn add(&mut self, line: &str) {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() || self.entries.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.entries.push(line.to_string());
+        if self.entries.len() > HISTORY_LIMIT {
+            self.entries.remove(0);
+        }
+        if let Some(path) = &self.path {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// before より前の履歴を新しい方から探し、query を含む行の位置を返す
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History::new()
+    }
+}
+
+/// 履歴の逆方向検索 (Ctrl-R) の状態
+struct Search {
+    query: String,
+    found: Option<usize>,
+}
+
+/// 編集中の1行
+pub struct LineState {
+    buffer: Vec<char>,
+    cursor: usize,
+    history_index: Option<usize>, // 履歴を辿っている位置
+    draft: Vec<char>,             // 履歴を辿る前に入力していた内容
+    search: Option<Search>,
+}
+
+impl LineState {
+    pub fn new() -> Self {
+        LineState {
+            buffer: Vec::new(),
+            cursor: 0,
+            history_index: None,
+            draft: Vec::new(),
+            search: None,
+        }
+    }
+
+    pub fn line(&self) -> String {
+        self.buffer.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// キー入力を1つ処理する。candidates は Tab で補完する候補
+    pub fn handle(&mut self, key: Key, history: &History, candidates: &[String]) -> Event {
+        if self.search.is_some() {
+            match self.handle_search(key, history) {
+                Some(event) => return event,
+                None if key == Key::Escape => return Event::Edited,
+                None => {} // 検索結果を確定してから通常のキーとして処理する
+            }
+        }
+
+        match key {
+            Key::Char(c) => {
+                self.buffer.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Key::Enter => return Event::Submit(self.line()),
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.buffer.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.buffer.len() => {
+                self.buffer.remove(self.cursor);
+            }
+            Key::Left if self.cursor > 0 => self.cursor -= 1,
+            Key::Right if self.cursor < self.buffer.len() => self.cursor += 1,
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.buffer.len(),
+            Key::Up => self.history_previous(history),
+            Key::Down => self.history_next(history),
+            Key::Tab => return self.complete(candidates),
+            Key::Interrupt => return Event::Interrupt,
+            Key::Eof if self.buffer.is_empty() => return Event::Eof,
+            Key::Eof if self.cursor < self.buffer.len() => {
+                self.buffer.remove(self.cursor);
+            }
+            Key::ReverseSearch => {
+                self.search = Some(Search {
+                    query: String::new(),
+                    found: None,
+                })
+            }
+            Key::KillToEnd => self.buffer.truncate(self.cursor),
+            Key::KillToStart => {
+                self.buffer.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::DeleteWord => {
+                // 直前の空白と、その前の単語を消す
+                let mut start = self.cursor;
+                while start > 0 && self.buffer[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+                while start > 0 && !self.buffer[start - 1].is_whitespace() {
+                    start -= 1;
+                }
+                self.buffer.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            Key::ClearScreen => return Event::ClearScreen,
+            _ => {}
+        }
+        Event::Edited
+    }
+
+    /// 検索中のキー入力。検索を終えて通常の処理に回す場合は None
+    fn handle_search(&mut self, key: Key, history: &History) -> Option<Event> {
+        let search = self.search.as_mut()?;
+        match key {
+            Key::Char(c) => {
+                search.query.push(c);
+                let before = search
+                    .found
+                    .map_or(history.entries().len(), |found| found + 1);
+                search.found = history.search(&search.query, before);
+            }
+            Key::Backspace => {
+                search.query.pop();
+                search.found = history.search(&search.query, history.entries().len());
+            }
+            Key::ReverseSearch => {
+                let before = search.found.unwrap_or(history.entries().len());
+                if let Some(found) = history.search(&search.query, before) {
+                    search.found = Some(found);
+                }
+            }
+            Key::Interrupt => self.search = None,
+            _ => {
+                if let Some(found) = search.found {
+                    self.buffer = history.entries()[found].chars().collect();
+                    self.cursor = self.buffer.len();
+                }
+                self.search = None;
+                return None;
+            }
+        }
+        Some(Event::Edited)
+    }
+
+    fn history_previous(&mut self, history: &History) {
+        let index = self.history_index.unwrap_or(history.entries().len());
+        if index == 0 {
+            return;
+        }
+        if self.history_index.is_none() {
+            self.draft = self.buffer.clone();
+        }
+        self.history_index = Some(index - 1);
+        self.buffer = history.entries()[index - 1].chars().collect();
+        self.cursor = self.buffer.len();
+    }
+
+    fn history_next(&mut self, history: &History) {
+        let index = match self.history_index {
+            Some(index) => index + 1,
+            None => return,
+        };
+        if index < history.entries().len() {
+            self.history_index = Some(index);
+            self.buffer = history.entries()[index].chars().collect();
+        } else {
+            self.history_index = None;
+            self.buffer = std::mem::take(&mut self.draft);
+        }
+        self.cursor = self.buffer.len();
+    }
+
+    /// カーソルの直前の単語を候補の共通部分まで補完する
+    ///
+    /// 行頭の `:` から始まる場合は REPL のコマンド名として補完する。
+    fn complete(&mut self, candidates: &[String]) -> Event {
+        let mut start = self.word_start(|c| c.is_alphanumeric() || c == '_');
+        if start == 1 && self.buffer[0] == ':' {
+            start = 0;
+        }
+        let prefix: String = self.buffer[start..self.cursor].iter().collect();
+        if prefix.is_empty() {
+            return Event::Edited;
+        }
+
+        let mut matches = candidates
+            .iter()
+            .filter(|candidate| candidate.starts_with(&prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        matches.sort();
+        matches.dedup();
+        let common = match matches.split_first() {
+            Some((first, rest)) => rest.iter().fold(first.clone(), |common, candidate| {
+                common
+                    .chars()
+                    .zip(candidate.chars())
+                    .take_while(|(left, right)| left == right)
+                    .map(|(c, _)| c)
+                    .collect()
+            }),
+            None => return Event::Edited,
+        };
+
+        if common.chars().count() > prefix.chars().count() {
+            for c in common.chars().skip(prefix.chars().count()) {
+                self.buffer.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            Event::Edited
+        } else if matches.len() > 1 {
+            Event::Candidates(matches)
+        } else {
+            Event::Edited
+        }
+    }
+
+    /// カーソルから前に向かって is_word を満たす文字が続く先頭の位置
+    fn word_start<F>(&self, is_word: F) -> usize
+    where
+        F: Fn(char) -> bool,
+    {
+        self.buffer[..self.cursor]
+            .iter()
+            .rposition(|c| !is_word(*c))
+            .map_or(0, |position| position + 1)
+    }
+
+    /// 表示する1行と、その中でのカーソルの桁位置 (全角文字は2桁)
+    pub fn render(&self, prompt: &str, history: &History) -> (String, usize) {
+        let (before, after): (String, String) = match &self.search {
+            Some(search) => {
+                let found = search
+                    .found
+                    .map_or("", |found| history.entries()[found].as_str());
+                (
+                    format!("(reverse-i-search)'{}': ", search.query),
+                    found.to_string(),
+                )
+            }
+            None => (
+                prompt.to_string() + &self.buffer[..self.cursor].iter().collect::<String>(),
+                self.buffer[self.cursor..].iter().collect(),
+            ),
+        };
+        (before.clone() + &after, display_width(&before))
+    }
+}
+
+impl Default for LineState {
+    fn default() -> Self {
+        LineState::new()
+    }
+}
+
+/// 端末の桁数
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if diagnostic::is_wide(c) { 2 } else { 1 })
+        .sum()
+}
+
+/// バイト列から1つのキー入力を読む (入力の終わりなら None)
+pub fn read_key<R: Read>(input: &mut R) -> std::io::Result<Option<Key>> {
+    let byte = match read_byte(input)? {
+        Some(byte) => byte,
+        None => return Ok(None),
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::Eof,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x08 | 0x7f => Key::Backspace,
+        0x0b => Key::KillToEnd,
+        0x0c => Key::ClearScreen,
+        0x0e => Key::Down,
+        0x10 => Key::Up,
+        0x12 => Key::ReverseSearch,
+        0x15 => Key::KillToStart,
+        0x17 => Key::DeleteWord,
+        0x1b => read_escape_sequence(input)?,
+        byte if byte < 0x20 => Key::Unknown,
+        byte => {
+            // UTF-8 の残りのバイトを読む
+            let length = match byte {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => 1,
+            };
+            let mut bytes = vec![byte];
+            for _ in 1..length {
+                match read_byte(input)? {
+                    Some(byte) => bytes.push(byte),
+                    None => break,
+                }
+            }
+            match std::str::from_utf8(&bytes)
+                .ok()
+                .and_then(|s| s.chars().next())
+            {
+                Some(c) => Key::Char(c),
+                None => Key::Unknown,
+            }
+        }
+    };
+    Ok(Some(key))
+}
+
+/// `ESC [ A` や `ESC [ 3 ~` などの矢印・編集キー
+fn read_escape_sequence<R: Read>(input: &mut R) -> std::io::Result<Key> {
+    match read_byte(input)? {
+        Some(b'[') | Some(b'O') => {}
+        _ => return Ok(Key::Escape),
+    }
+    let mut parameter = String::new();
+    while let Some(byte) = read_byte(input)? {
+        match byte {
+            b'0'..=b'9' | b';' => parameter.push(byte as char),
+            b'A' => return Ok(Key::Up),
+            b'B' => return Ok(Key::Down),
+            b'C' => return Ok(Key::Right),
+            b'D' => return Ok(Key::Left),
+            b'H' => return Ok(Key::Home),
+            b'F' => return Ok(Key::End),
+            b'~' => {
+                return Ok(match parameter.as_str() {
+                    "1" | "7" => Key::Home,
+                    "4" | "8" => Key::End,
+                    "3" => Key::Delete,
+                    _ => Key::Unknown,
+                })
+            }
+            _ => return Ok(Key::Unknown),
+        }
+    }
+    Ok(Key::Escape)
+}
+
+fn read_byte<R: Read>(input: &mut R) -> std::io::Result<Option<u8>> {
+    let mut byte = [0u8];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// 端末を1文字ずつ読めるモードにし、drop で元に戻す
+///
+/// 外部クレートを使わずに済むよう `stty` コマンドで切り替える。
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        let output = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let saved = String::from_utf8(output.stdout).ok()?.trim().to_string();
+        let status = Command::new("stty")
+            .args(["-icanon", "-echo", "-isig", "-ixon", "min", "1"])
+            .stdin(Stdio::inherit())
+            .status()
+            .ok()?;
+        if status.success() {
+            Some(RawMode { saved })
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = Command::new("stty")
+            .arg(&self.saved)
+            .stdin(Stdio::inherit())
+            .status();
+    }
+}
+
+/// 入力した行の結果
+#[derive(Debug, PartialEq)]
+pub enum ReadLine {
+    Line(String),
+    Interrupted,
+    Eof,
+}
+
+/// 履歴と補完付きで端末から1行ずつ読む
+///
+/// 標準入力が端末でない (パイプなど) 場合は編集せずにそのまま読む。
+/// 端末は最初の read_line で1文字ずつ読めるモードにし、LineEditor を drop するまで戻さない。
+pub struct LineEditor {
+    history: History,
+    raw_mode: Option<Option<RawMode>>, // まだ切り替えていなければ None
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        LineEditor {
+            history: History::new(),
+            raw_mode: None,
+        }
+    }
+
+    /// ホームディレクトリの履歴ファイルを使う
+    pub fn with_history_file(mut self, path: &Path) -> Self {
+        self.history = History::with_file(path);
+        self
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// プロンプトを表示して1行読む。candidates は Tab で補完する候補
+    pub fn read_line(&mut self, prompt: &str, candidates: &[String]) -> std::io::Result<ReadLine> {
+        let raw_mode = self.raw_mode.get_or_insert_with(|| {
+            if stdin().is_terminal() {
+                RawMode::enable()
+            } else {
+                None
+            }
+        });
+        match raw_mode {
+            Some(_) => {
+                let line = self.read_line_raw(prompt, candidates)?;
+                if let ReadLine::Line(line) = &line {
+                    self.history.add(line);
+                }
+                Ok(line)
+            }
+            None => read_line_plain(prompt), // パイプからの入力は履歴に残さない
+        }
+    }
+
+    fn read_line_raw(&mut self, prompt: &str, candidates: &[String]) -> std::io::Result<ReadLine> {
+        let mut state = LineState::new();
+        let mut input = stdin().lock();
+        let mut output = stdout();
+        self.refresh(&mut output, prompt, &state)?;
+        loop {
+            let key = match read_key(&mut input)? {
+                Some(key) => key,
+                None => {
+                    writeln!(output)?;
+                    return Ok(ReadLine::Eof);
+                }
+            };
+            match state.handle(key, &self.history, candidates) {
+                Event::Edited => {}
+                Event::Submit(line) => {
+                    writeln!(output)?;
+                    return Ok(ReadLine::Line(line + "\n"));
+                }
+                Event::Eof => {
+                    writeln!(output)?;
+                    return Ok(ReadLine::Eof);
+                }
+                Event::Interrupt => {
+                    writeln!(output, "^C")?;
+                    return Ok(ReadLine::Interrupted);
+                }
+                Event::ClearScreen => write!(output, "\x1b[H\x1b[2J")?,
+                Event::Candidates(matches) => writeln!(output, "\n{}", matches.join("  "))?,
+            }
+            self.refresh(&mut output, prompt, &state)?;
+        }
+    }
+
+    /// 行を書き直してカーソルを合わせる
+    fn refresh<W: Write>(
+        &self,
+        output: &mut W,
+        prompt: &str,
+        state: &LineState,
+    ) -> std::io::Result<()> {
+        let (line, column) = state.render(prompt, &self.history);
+        write!(output, "\r{}\x1b[K\r", line)?;
+        if column > 0 {
+            write!(output, "\x1b[{}C", column)?;
+        }
+        output.flush()
+    }
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        LineEditor::new()
+    }
+}
+
+fn read_line_plain(prompt: &str) -> std::io::Result<ReadLine> {
+    print!("{}", prompt);
+    stdout().flush()?;
+    let mut line = String::new();
+    if stdin().read_line(&mut line)? == 0 {
+        println!();
+        return Ok(ReadLine::Eof);
+    }
+    Ok(ReadLine::Line(line))
+}
+
+/// ホームディレクトリの履歴ファイルのパス (ホームディレクトリが分からなければ None)
+pub fn default_history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> History {
+        let mut history = History::new();
+        for entry in entries {
+            history.add(entry);
+        }
+        history
+    }
+
+    fn type_keys(state: &mut LineState, text: &str, history: &History) {
+        for c in text.chars() {
+            state.handle(Key::Char(c), history, &[]);
+        }
+    }
+
+    #[test]
+    fn test_read_key() {
+        let mut input: &[u8] = b"a\x1b[A\x1b[3~\x1bOH\x7f\x12\r\xe3\x81\x82\t\x03";
+        let mut keys = Vec::new();
+        while let Some(key) = read_key(&mut input).expect("読み込みエラー") {
+            keys.push(key);
+        }
+        assert_eq!(
+            keys,
+            vec![
+                Key::Char('a'),
+                Key::Up,
+                Key::Delete,
+                Key::Home,
+                Key::Backspace,
+                Key::ReverseSearch,
+                Key::Enter,
+                Key::Char('あ'),
+                Key::Tab,
+                Key::Interrupt,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_edit() {
+        let history = History::new();
+        let mut state = LineState::new();
+        type_keys(&mut state, "let x = 1;", &history);
+        state.handle(Key::Home, &history, &[]);
+        state.handle(Key::Delete, &history, &[]);
+        state.handle(Key::Delete, &history, &[]);
+        state.handle(Key::Delete, &history, &[]);
+        type_keys(&mut state, "const", &history);
+        assert_eq!(state.line(), "const x = 1;");
+        assert_eq!(state.cursor(), 5);
+
+        state.handle(Key::End, &history, &[]);
+        state.handle(Key::Backspace, &history, &[]);
+        state.handle(Key::DeleteWord, &history, &[]);
+        assert_eq!(state.line(), "const x = ");
+        state.handle(Key::Left, &history, &[]);
+        state.handle(Key::KillToStart, &history, &[]);
+        assert_eq!(state.line(), " ");
+        assert_eq!(
+            state.handle(Key::Enter, &history, &[]),
+            Event::Submit(" ".to_string())
+        );
+    }
+
+    #[test]
+    fn test_history_navigation() {
+        let history = history(&["let a = 1;", "a + 1", "a + 1", ""]);
+        assert_eq!(history.entries(), ["let a = 1;", "a + 1"]);
+
+        let mut state = LineState::new();
+        type_keys(&mut state, "dr", &history);
+        state.handle(Key::Up, &history, &[]);
+        assert_eq!(state.line(), "a + 1");
+        state.handle(Key::Up, &history, &[]);
+        state.handle(Key::Up, &history, &[]);
+        assert_eq!(state.line(), "let a = 1;");
+        state.handle(Key::Down, &history, &[]);
+        assert_eq!(state.line(), "a + 1");
+        state.handle(Key::Down, &history, &[]);
+        assert_eq!(state.line(), "dr");
+    }
+
+    #[test]
+    fn test_reverse_search() {
+        let history = history(&["let add = fn(a, b) { a + b };", "add(1, 2)", "let x = 5;"]);
+        let mut state = LineState::new();
+        state.handle(Key::ReverseSearch, &history, &[]);
+        type_keys(&mut state, "add", &history);
+        assert_eq!(
+            state.render(">> ", &history).0,
+            "(reverse-i-search)'add': add(1, 2)"
+        );
+        state.handle(Key::ReverseSearch, &history, &[]);
+        assert_eq!(
+            state.render(">> ", &history).0,
+            "(reverse-i-search)'add': let add = fn(a, b) { a + b };"
+        );
+        state.handle(Key::ReverseSearch, &history, &[]); // それより古い候補は無い
+        state.handle(Key::Right, &history, &[]);
+        assert_eq!(state.line(), "let add = fn(a, b) { a + b };");
+        assert_eq!(state.render(">> ", &history).1, 32);
+
+        state.handle(Key::ReverseSearch, &history, &[]);
+        type_keys(&mut state, "x", &history);
+        assert_eq!(
+            state.handle(Key::Enter, &history, &[]),
+            Event::Submit("let x = 5;".to_string())
+        );
+    }
+
+    #[test]
+    fn test_complete() {
+        let history = History::new();
+        let candidates = ["let", "len", "last", "length", ":load"]
+            .iter()
+            .map(|candidate| candidate.to_string())
+            .collect::<Vec<_>>();
+
+        let mut state = LineState::new();
+        type_keys(&mut state, "1 + la", &history);
+        state.handle(Key::Tab, &history, &candidates);
+        assert_eq!(state.line(), "1 + last");
+
+        let mut state = LineState::new();
+        type_keys(&mut state, "le", &history);
+        assert_eq!(
+            state.handle(Key::Tab, &history, &candidates),
+            Event::Candidates(vec![
+                "len".to_string(),
+                "length".to_string(),
+                "let".to_string()
+            ])
+        );
+        type_keys(&mut state, "n", &history);
+        state.handle(Key::Tab, &history, &candidates);
+        assert_eq!(state.line(), "len");
+
+        let mut state = LineState::new();
+        type_keys(&mut state, ":lo", &history);
+        state.handle(Key::Tab, &history, &candidates);
+        assert_eq!(state.line(), ":load");
+    }
+
+    #[test]
+    fn test_render_wide_chars() {
+        let history = History::new();
+        let mut state = LineState::new();
+        type_keys(&mut state, "\"あい\"", &history);
+        state.handle(Key::Left, &history, &[]);
+        assert_eq!(
+            state.render(">> ", &history),
+            (">> \"あい\"".to_string(), 8)
+        );
+    }
+
+    #[test]
+    fn test_history_file() {
+        let path = std::env::temp_dir().join(format!("history_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut history = History::with_file(&path);
+        history.add("let a = 1;\n");
+        history.add("a");
+        let history = History::with_file(&path);
+        assert_eq!(history.entries(), ["let a = 1;", "a"]);
+        assert_eq!(history.search("let", 2), Some(0));
+        assert_eq!(history.search("let", 0), None);
+
+        std::fs::remove_file(&path).expect("一時ファイル");
+    }
+}
@@ -1,36 +1,434 @@
-use go_interpreter::environment::Environment;
-use go_interpreter::evaluator::Evaluator;
+use go_interpreter::bytecode_file;
+use go_interpreter::code::Bytecode;
+use go_interpreter::compiler::Compiler;
+use go_interpreter::diagnostic::{self, Diagnostic, Renderer};
+use go_interpreter::interpreter::Interpreter;
 use go_interpreter::lexer::Lexer;
+use go_interpreter::message::{self, Locale};
+use go_interpreter::object::Object;
+use go_interpreter::optimizer;
 use go_interpreter::parser::Parser;
-use std::io::{stdin, stdout, Write};
-use std::{cell::RefCell, rc::Rc};
+use go_interpreter::repl::Repl;
+use go_interpreter::vm;
+use std::io::{stderr, stdin, Read, Write};
+
+// 終了コード (sysexits.h に合わせる)
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_USAGE: i32 = 64;
+const EXIT_PARSE_ERROR: i32 = 65;
+const EXIT_NO_INPUT: i32 = 66;
+const EXIT_RUNTIME_ERROR: i32 = 70;
+const EXIT_CANNOT_CREATE: i32 = 73;
+
+const USAGE: &str = "\
+使い方: go_interpreter [オプション] [<ファイル> | -e <コード> | -]
+
+  <ファイル>             スクリプトを実行する
+  -e <コード>            コードを評価して結果を表示する
+  -                      標準入力からプログラムを読んで実行する
+  (指定なし)             対話モード (REPL) を起動する
+
+オプション:
+  --lang <ja|en>         エラーメッセージの言語 (環境変数 GO_INTERPRETER_LANG より優先)
+  --error-format <text|json>
+                         エラーの出力形式 (json は1行1件の JSON Lines)
+  --vm                   バイトコードにコンパイルして VM で実行する (REPL では無視)
+  --no-optimize          定数の畳み込みなど、実行前の構文木の最適化をしない
+  --dump-bytecode        実行せずにバイトコードを逆アセンブルして表示する
+  --emit-bytecode <出力ファイル>
+                         実行せずにバイトコードをファイルに書き出す
+                         (書き出したファイルは <ファイル> に指定すると VM で実行できる)
+  --no-debug-info        書き出すファイルにエラー位置の情報を含めない
+  -h, --help             この使い方を表示する
+
+終了コード:
+  0   成功
+  64  引数の誤り
+  65  構文エラー・定義されていない名前・不正なバイトコードのファイル
+  66  ファイルを読み込めない
+  70  実行時エラー
+  73  ファイルを書き込めない";
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    Repl,
+    File(String),
+    Eval(String),
+    Stdin,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum ErrorFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    input: Input,
+    locale: Option<Locale>,
+    error_format: ErrorFormat,
+    vm: bool,
+    optimize: bool,
+    dump_bytecode: bool,
+    emit_bytecode: Option<String>,
+    debug_info: bool,
+    help: bool,
+}
 
 fn main() {
-    let prompt = ">> ";
-    let mut environment = Rc::new(RefCell::new(Environment::new()));
-    loop {
-        print!("{}", prompt);
-        stdout().flush().unwrap();
-        let mut scan = String::new();
-        stdin().read_line(&mut scan).expect("Failed to read line.");
-        let lexer = Lexer::new(&scan);
-        let mut parser = Parser::new(lexer);
-        let ast_root = match parser.parse_program() {
-            Ok(ast) => ast,
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return;
+    }
+    if let Some(locale) = options.locale {
+        message::set_locale(locale);
+    }
+
+    let code = match &options.input {
+        Input::Repl => {
+            Repl::new().with_color(diagnostic::color_enabled()).run();
+            EXIT_SUCCESS
+        }
+        Input::File(path) => match std::fs::read(path) {
+            Ok(bytes) => run_bytes(bytes, path, false, &options),
             Err(err) => {
-                println!("{}", err);
-                continue;
+                eprintln!("ファイルを読み込めません。: {}: {}", path, err);
+                EXIT_NO_INPUT
             }
+        },
+        Input::Eval(code) => run(code, "<-e>", true, &options),
+        Input::Stdin => {
+            let mut bytes = Vec::new();
+            match stdin().read_to_end(&mut bytes) {
+                Ok(_) => run_bytes(bytes, "<stdin>", false, &options),
+                Err(err) => {
+                    eprintln!("標準入力を読み込めません。: {}", err);
+                    EXIT_NO_INPUT
+                }
+            }
+        }
+    };
+    std::process::exit(code);
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        input: Input::Repl,
+        locale: None,
+        error_format: ErrorFormat::Text,
+        vm: false,
+        optimize: true,
+        dump_bytecode: false,
+        emit_bytecode: None,
+        debug_info: true,
+        help: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        // --name=value と --name value の両方を受け付ける
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name, Some(value.to_string())),
+            _ => (arg.as_str(), None),
         };
-        let evaluated = match Evaluator::eval(&ast_root, &mut environment) {
-            Ok(evaluated) => evaluated,
+        let mut value = |option: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next().cloned())
+                .ok_or(format!("{} には値が必要です。", option))
+        };
+
+        match name {
+            "-h" | "--help" => options.help = true,
+            "--vm" => options.vm = true,
+            "--no-optimize" => options.optimize = false,
+            "--dump-bytecode" => options.dump_bytecode = true,
+            "--emit-bytecode" => options.emit_bytecode = Some(value("--emit-bytecode")?),
+            "--no-debug-info" => options.debug_info = false,
+            "--lang" => {
+                let name = value("--lang")?;
+                options.locale = Some(Locale::from_name(&name).ok_or(format!(
+                    "--lang には ja か en を指定してください。: {}",
+                    name
+                ))?);
+            }
+            "--error-format" => {
+                options.error_format = match value("--error-format")?.as_str() {
+                    "text" => ErrorFormat::Text,
+                    "json" => ErrorFormat::Json,
+                    other => {
+                        return Err(format!(
+                            "--error-format には text か json を指定してください。: {}",
+                            other
+                        ))
+                    }
+                }
+            }
+            "-e" => set_input(&mut options, Input::Eval(value("-e")?))?,
+            "-" => set_input(&mut options, Input::Stdin)?,
+            _ if name.starts_with('-') => return Err(format!("不明なオプションです。: {}", arg)),
+            _ => set_input(&mut options, Input::File(arg.to_string()))?,
+        }
+    }
+    Ok(options)
+}
+
+fn set_input(options: &mut Options, input: Input) -> Result<(), String> {
+    if options.input != Input::Repl {
+        return Err("実行するプログラムは1つだけ指定してください。".to_string());
+    }
+    options.input = input;
+    Ok(())
+}
+
+/// バイトコードのファイルなら読み込んで VM で、それ以外はソースとして実行する
+fn run_bytes(bytes: Vec<u8>, file: &str, print_result: bool, options: &Options) -> i32 {
+    if bytecode_file::is_bytecode_file(&bytes) {
+        return match bytecode_file::read(&bytes) {
+            Ok(bytecode) => run_bytecode(&bytecode, None, file, print_result, options),
             Err(err) => {
-                println!("{}", err);
-                continue;
+                report(&[Diagnostic::from(&err)], None, file, options.error_format);
+                EXIT_PARSE_ERROR
             }
         };
-        if evaluated.inspect() != "" {
-            println!("{}", evaluated.inspect());
+    }
+    match String::from_utf8(bytes) {
+        Ok(source) => run(&source, file, print_result, options),
+        Err(err) => {
+            eprintln!("ファイルを読み込めません。: {}: {}", file, err);
+            EXIT_NO_INPUT
+        }
+    }
+}
+
+/// プログラムを解析・評価して終了コードを返す (エラーは標準エラー出力へ)
+fn run(source: &str, file: &str, print_result: bool, options: &Options) -> i32 {
+    let error_format = options.error_format;
+    let lexer = Lexer::new(source);
+    let mut parser = Parser::new(lexer);
+    let (mut program, errors) = parser.parse_program_with_errors();
+    if !errors.is_empty() {
+        let diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+        report(&diagnostics, Some(source), file, error_format);
+        return EXIT_PARSE_ERROR;
+    }
+    // 定義されていない名前の参照は、どちらの実行方法でも実行前にエラーにする
+    // (最適化で取り除かれる分岐の中も調べるため、最適化より先に解決する)
+    let mut interpreter = Interpreter::new();
+    if let Err(err) = interpreter.resolve(&mut program) {
+        report_error(err.as_ref(), Some(source), file, error_format);
+        return EXIT_PARSE_ERROR;
+    }
+    let program = if options.optimize {
+        optimizer::optimize(program)
+    } else {
+        program
+    };
+
+    if !(options.vm || options.dump_bytecode || options.emit_bytecode.is_some()) {
+        let evaluated = interpreter.eval_program(&program);
+        return finish(evaluated, Some(source), file, print_result, error_format);
+    }
+    match Compiler::new().compile(&program) {
+        Ok(bytecode) => run_bytecode(&bytecode, Some(source), file, print_result, options),
+        Err(err) => {
+            report_error(err.as_ref(), Some(source), file, error_format);
+            EXIT_PARSE_ERROR
+        }
+    }
+}
+
+/// バイトコードを表示・書き出し・実行する (読み込んだファイルのときは source が None)
+fn run_bytecode(
+    bytecode: &Bytecode,
+    source: Option<&str>,
+    file: &str,
+    print_result: bool,
+    options: &Options,
+) -> i32 {
+    if options.dump_bytecode {
+        print!("{}", bytecode.disassemble());
+    }
+    if let Some(path) = &options.emit_bytecode {
+        let bytes = bytecode_file::write(bytecode, options.debug_info);
+        if let Err(err) = std::fs::write(path, bytes) {
+            eprintln!("ファイルに書き込めません。: {}: {}", path, err);
+            return EXIT_CANNOT_CREATE;
+        }
+    }
+    if options.dump_bytecode || options.emit_bytecode.is_some() {
+        return EXIT_SUCCESS;
+    }
+
+    let evaluated = vm::Vm::new().run(bytecode);
+    finish(evaluated, source, file, print_result, options.error_format)
+}
+
+/// 評価結果を表示して終了コードを返す
+fn finish(
+    evaluated: Result<Object, Box<dyn std::error::Error>>,
+    source: Option<&str>,
+    file: &str,
+    print_result: bool,
+    error_format: ErrorFormat,
+) -> i32 {
+    match evaluated {
+        Ok(evaluated) => {
+            if print_result && evaluated.inspect() != "" {
+                println!("{}", evaluated.inspect());
+            }
+            EXIT_SUCCESS
+        }
+        Err(err) => {
+            report_error(err.as_ref(), source, file, error_format);
+            EXIT_RUNTIME_ERROR
+        }
+    }
+}
+
+/// 位置の分かるエラーは診断として、それ以外はメッセージだけを表示する
+fn report_error(
+    err: &(dyn std::error::Error + 'static),
+    source: Option<&str>,
+    file: &str,
+    error_format: ErrorFormat,
+) {
+    match Diagnostic::from_error(err) {
+        Some(diagnostic) => report(&[diagnostic], source, file, error_format),
+        None => eprintln!("{}", err),
+    }
+}
+
+/// 診断を表示する (ソースが無ければ該当行を省く)
+fn report(diagnostics: &[Diagnostic], source: Option<&str>, file: &str, error_format: ErrorFormat) {
+    let mut stderr = stderr();
+    match error_format {
+        ErrorFormat::Text => {
+            let renderer = source
+                .map_or_else(Renderer::without_source, Renderer::new)
+                .with_file(file)
+                .with_color(diagnostic::color_enabled_for(&stderr));
+            for diagnostic in diagnostics {
+                let _ = writeln!(stderr, "{}\n", renderer.render(diagnostic));
+            }
+        }
+        ErrorFormat::Json => {
+            let _ = write!(
+                stderr,
+                "{}",
+                diagnostic::to_json_lines(diagnostics, Some(file))
+            );
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let problem = [
+            (vec![], Input::Repl),
+            (vec!["main.monkey"], Input::File("main.monkey".to_string())),
+            (vec!["-e", "1 + 2"], Input::Eval("1 + 2".to_string())),
+            (vec!["-"], Input::Stdin),
+            (
+                vec!["--lang", "en", "--error-format=json", "a.monkey"],
+                Input::File("a.monkey".to_string()),
+            ),
+        ];
+
+        for (input, expected) in problem {
+            let options = parse_args(&args(&input)).expect("引数エラー");
+            assert_eq!(options.input, expected, "{:?}", input);
+        }
+
+        let options = parse_args(&args(&["--lang=en", "--error-format", "json"])).unwrap();
+        assert_eq!(options.locale, Some(Locale::En));
+        assert_eq!(options.error_format, ErrorFormat::Json);
+        assert!(!options.vm);
+        assert!(options.optimize);
+        assert!(!parse_args(&args(&["--no-optimize"])).unwrap().optimize);
+        assert!(parse_args(&args(&["--vm", "a.monkey"])).unwrap().vm);
+        assert!(
+            parse_args(&args(&["--dump-bytecode", "-e", "1"]))
+                .unwrap()
+                .dump_bytecode
+        );
+    }
+
+    #[test]
+    fn test_parse_args_error() {
+        let problem = [
+            vec!["-e"],
+            vec!["--lang", "fr"],
+            vec!["--error-format=xml"],
+            vec!["--verbose"],
+            vec!["a.monkey", "-"],
+        ];
+
+        for input in problem {
+            assert!(parse_args(&args(&input)).is_err(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_run_exit_code() {
+        let problem = [
+            ("let x = 1; x * 2", EXIT_SUCCESS),
+            ("let x = ;\nlet y 1;", EXIT_PARSE_ERROR),
+            ("1 + true", EXIT_RUNTIME_ERROR),
+            ("5 / 0", EXIT_RUNTIME_ERROR),
+            ("if (false) { undefined }", EXIT_PARSE_ERROR),
+        ];
+
+        for vm in [false, true] {
+            let mut options = parse_args(&args(&["--error-format=json"])).unwrap();
+            options.vm = vm;
+            for (input, code) in &problem {
+                assert_eq!(run(input, "test", false, &options), *code, "{}", input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_emit_bytecode() {
+        let path = std::env::temp_dir().join(format!("emit_{}.mkbc", std::process::id()));
+        let path = path.to_str().expect("一時ファイル").to_string();
+        let mut options =
+            parse_args(&args(&["--emit-bytecode", &path, "--no-debug-info"])).expect("引数エラー");
+        assert!(!options.debug_info);
+        assert_eq!(
+            run("let x = 1; x + true", "test", false, &options),
+            EXIT_SUCCESS
+        );
+
+        let bytes = std::fs::read(&path).expect("書き出したファイル");
+        assert!(bytecode_file::is_bytecode_file(&bytes));
+        options.emit_bytecode = None;
+        options.error_format = ErrorFormat::Json;
+        assert_eq!(
+            run_bytes(bytes.clone(), &path, false, &options),
+            EXIT_RUNTIME_ERROR
+        );
+        assert_eq!(
+            run_bytes(bytes[..bytes.len() - 1].to_vec(), &path, false, &options),
+            EXIT_PARSE_ERROR
+        );
+        std::fs::remove_file(&path).expect("一時ファイルの削除");
+    }
+}
@@ -0,0 +1,269 @@
+use std::cell::Cell;
+
+/// メッセージのロケールを選ぶ環境変数
+pub const LOCALE_ENV: &str = "GO_INTERPRETER_LANG";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Locale {
+    Ja,
+    En,
+}
+
+impl Locale {
+    /// "ja", "en", "en_US.UTF-8" などのロケール名から選ぶ
+    pub fn from_name(name: &str) -> Option<Locale> {
+        let language = name.split(['_', '-', '.']).next().unwrap_or("");
+        match language.to_ascii_lowercase().as_str() {
+            "ja" => Some(Locale::Ja),
+            "en" => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    /// 環境変数 GO_INTERPRETER_LANG から選ぶ
+    pub fn from_env() -> Option<Locale> {
+        std::env::var(LOCALE_ENV)
+            .ok()
+            .and_then(|name| Locale::from_name(&name))
+    }
+}
+
+thread_local! {
+    static LOCALE: Cell<Locale> = Cell::new(Locale::from_env().unwrap_or(Locale::Ja));
+}
+
+/// エラーメッセージの表示に使うロケールを設定する (スレッドごと)
+pub fn set_locale(locale: Locale) {
+    LOCALE.with(|current| current.set(locale));
+}
+
+/// 現在のロケール (未設定なら環境変数、それも無ければ日本語)
+pub fn locale() -> Locale {
+    LOCALE.with(|current| current.get())
+}
+
+/// エラーの種類ごとのメッセージ。`{0}` `{1}` … に引数が入る
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Message {
+    InvalidNumber,
+    UnterminatedBlockComment,
+    UnterminatedString,
+    InvalidEscape,
+    UnexpectedToken,
+    NotFoundInfixToken,
+    NotFoundLetIdentifier,
+    UnImplementationStatemant,
+    UnImplementationParser,
+    TypeMissMatch,
+    UnknowInfixOperator,
+    UnknowPrefixOperator,
+    NotFoundIdentifier,
+    UnknowIndexOperator,
+    IndexOutOfRange,
+    UnhashableKey,
+    ArityMismatch,
+    UnsupportedArgument,
+    HostFunctionError,
+    NotCallable,
+    DivisionByZero,
+    OperandOverflow,
+    InvalidMagic,
+    UnsupportedVersion,
+    TruncatedBytecode,
+    InvalidBytecodeData,
+    InvalidInstruction,
+    HelpUnexpectedToken,
+    HelpNotFoundLetIdentifier,
+    HelpUnterminatedBlockComment,
+    HelpUnterminatedString,
+    HelpInvalidEscape,
+    HelpTypeMissMatch,
+    HelpNotFoundIdentifier,
+    HelpEmptyIndex,
+    HelpIndexOutOfRange,
+    HelpUnhashableKey,
+    HelpDivisionByZero,
+    HelpOperandOverflow,
+}
+
+impl Message {
+    pub fn template(self, locale: Locale) -> &'static str {
+        match locale {
+            Locale::Ja => self.ja(),
+            Locale::En => self.en(),
+        }
+    }
+
+    /// 現在のロケールのテンプレートに引数を埋め込む
+    pub fn format(self, args: &[&dyn std::fmt::Display]) -> String {
+        self.format_in(locale(), args)
+    }
+
+    pub fn format_in(self, locale: Locale, args: &[&dyn std::fmt::Display]) -> String {
+        // 引数の中の "{1}" などを置き換えないよう、テンプレートを1回だけ走査する
+        let mut message = String::new();
+        let mut rest = self.template(locale);
+        while let Some(start) = rest.find('{') {
+            message.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let placeholder = after
+                .find('}')
+                .and_then(|end| Some((after[..end].parse::<usize>().ok()?, end)));
+            match placeholder {
+                Some((i, end)) if i < args.len() => {
+                    message.push_str(&args[i].to_string());
+                    rest = &after[end + 1..];
+                }
+                _ => {
+                    message.push('{');
+                    rest = after;
+                }
+            }
+        }
+        message.push_str(rest);
+        message
+    }
+
+    fn ja(self) -> &'static str {
+        match self {
+            Message::InvalidNumber => "数値リテラルが不正です。: {0}",
+            Message::UnterminatedBlockComment => "ブロックコメントが閉じられていません。",
+            Message::UnterminatedString => "文字列が閉じられていません。",
+            Message::InvalidEscape => "不正なエスケープシーケンスです。: {0}",
+            Message::UnexpectedToken => "{0}を期待しましたが、{1}でした。",
+            Message::NotFoundInfixToken => "Infixを期待しましたが、{0}でした。",
+            Message::NotFoundLetIdentifier => "Identifierを期待しましたが、{0}でした。",
+            Message::UnImplementationStatemant => "{0}",
+            Message::UnImplementationParser => "{0}",
+            Message::TypeMissMatch => "型のミスマッチ: {0} {1} {2}",
+            Message::UnknowInfixOperator => "未知の演算子: {0} {1} {2}",
+            Message::UnknowPrefixOperator => "未知の演算子: {0}{1}",
+            Message::NotFoundIdentifier => "識別子が見つかりません。: {0}",
+            Message::UnknowIndexOperator => "未知の添字演算子: {0}[{1}]",
+            Message::IndexOutOfRange => "添字が範囲外です。: {0} (長さ {1})",
+            Message::UnhashableKey => "ハッシュのキーに使えません。: {0}",
+            Message::ArityMismatch => {
+                "引数の数が合いません。: {0} ({1}個を期待しましたが、{2}個でした。)"
+            }
+            Message::UnsupportedArgument => "引数の型が不正です。: {0}({1})",
+            Message::HostFunctionError => "{0} の実行に失敗しました。: {1}",
+            Message::NotCallable => "関数ではありません。: {0}",
+            Message::DivisionByZero => "ゼロで除算しました。: {0} / 0",
+            Message::OperandOverflow => "{0} のオペランドが大きすぎます。: {1}",
+            Message::InvalidMagic => "バイトコードのファイルではありません。",
+            Message::UnsupportedVersion => {
+                "対応していないバイトコードのバージョンです。: {0} (対応しているのは {1})"
+            }
+            Message::TruncatedBytecode => {
+                "バイトコードのファイルが途中で終わっています。: {0} バイト目"
+            }
+            Message::InvalidBytecodeData => "バイトコードのファイルが壊れています。: {0} バイト目",
+            Message::InvalidInstruction => "{0} の {1} 番地の命令が不正です。",
+            Message::HelpUnexpectedToken => "ここに {0} が必要です。",
+            Message::HelpNotFoundLetIdentifier => "let の後には変数名を書きます。例: let x = 5;",
+            Message::HelpUnterminatedBlockComment => "*/ でコメントを閉じてください。",
+            Message::HelpUnterminatedString => "\" で文字列を閉じてください。",
+            Message::HelpInvalidEscape => "使えるエスケープは \\n \\t \\\" \\\\ \\u{16進数} です。",
+            Message::HelpTypeMissMatch => "演算子の両辺の型を揃えてください。",
+            Message::HelpNotFoundIdentifier => "let で定義されているか確認してください。",
+            Message::HelpEmptyIndex => "空の配列には添字でアクセスできません。",
+            Message::HelpIndexOutOfRange => "添字は -{0} から {1} の範囲で指定してください。",
+            Message::HelpUnhashableKey => "キーには整数・真偽値・文字列が使えます。",
+            Message::HelpDivisionByZero => "除数が 0 にならないことを確認してください。",
+            Message::HelpOperandOverflow => "関数・配列・ハッシュを小さく分けてください。",
+        }
+    }
+
+    fn en(self) -> &'static str {
+        match self {
+            Message::InvalidNumber => "invalid numeric literal: {0}",
+            Message::UnterminatedBlockComment => "unterminated block comment",
+            Message::UnterminatedString => "unterminated string literal",
+            Message::InvalidEscape => "invalid escape sequence: {0}",
+            Message::UnexpectedToken => "expected {0}, found {1}",
+            Message::NotFoundInfixToken => "expected an infix operator, found {0}",
+            Message::NotFoundLetIdentifier => "expected an identifier, found {0}",
+            Message::UnImplementationStatemant => {
+                "no parser is implemented for this statement: {0}"
+            }
+            Message::UnImplementationParser => "no parser is implemented for this expression: {0}",
+            Message::TypeMissMatch => "type mismatch: {0} {1} {2}",
+            Message::UnknowInfixOperator => "unknown operator: {0} {1} {2}",
+            Message::UnknowPrefixOperator => "unknown operator: {0}{1}",
+            Message::NotFoundIdentifier => "identifier not found: {0}",
+            Message::UnknowIndexOperator => "unknown index operator: {0}[{1}]",
+            Message::IndexOutOfRange => "index out of range: {0} (length {1})",
+            Message::UnhashableKey => "unusable as hash key: {0}",
+            Message::ArityMismatch => "wrong number of arguments: {0} (expected {1}, found {2})",
+            Message::UnsupportedArgument => "unsupported argument: {0}({1})",
+            Message::HostFunctionError => "{0} failed: {1}",
+            Message::NotCallable => "not a function: {0}",
+            Message::DivisionByZero => "division by zero: {0} / 0",
+            Message::OperandOverflow => "operand of {0} is too large: {1}",
+            Message::InvalidMagic => "not a bytecode file",
+            Message::UnsupportedVersion => "unsupported bytecode version: {0} (expected {1})",
+            Message::TruncatedBytecode => "bytecode file is truncated at byte {0}",
+            Message::InvalidBytecodeData => "corrupt bytecode file at byte {0}",
+            Message::InvalidInstruction => "invalid instruction at {1} in {0}",
+            Message::HelpUnexpectedToken => "add {0} here",
+            Message::HelpNotFoundLetIdentifier => "let must be followed by a name, e.g. let x = 5;",
+            Message::HelpUnterminatedBlockComment => "close the comment with */",
+            Message::HelpUnterminatedString => "close the string with \"",
+            Message::HelpInvalidEscape => "valid escapes are \\n, \\t, \\\", \\\\ and \\u{hex}",
+            Message::HelpTypeMissMatch => "both operands must have the same type",
+            Message::HelpNotFoundIdentifier => "check that the name is defined with let",
+            Message::HelpEmptyIndex => "an empty array cannot be indexed",
+            Message::HelpIndexOutOfRange => "indices must be between -{0} and {1}",
+            Message::HelpUnhashableKey => "only integers, booleans and strings can be hash keys",
+            Message::HelpDivisionByZero => "make sure the divisor is not 0",
+            Message::HelpOperandOverflow => "split the function, array or hash into smaller parts",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_from_name() {
+        let problem = [
+            ("ja", Some(Locale::Ja)),
+            ("ja_JP.UTF-8", Some(Locale::Ja)),
+            ("en", Some(Locale::En)),
+            ("EN-us", Some(Locale::En)),
+            ("fr", None),
+            ("", None),
+        ];
+
+        for (name, locale) in problem {
+            assert_eq!(Locale::from_name(name), locale, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        let message = Message::ArityMismatch;
+        assert_eq!(
+            message.format_in(Locale::Ja, &[&"len", &1, &2]),
+            "引数の数が合いません。: len (1個を期待しましたが、2個でした。)"
+        );
+        assert_eq!(
+            message.format_in(Locale::En, &[&"len", &1, &2]),
+            "wrong number of arguments: len (expected 1, found 2)"
+        );
+        assert_eq!(
+            Message::UnexpectedToken.format_in(Locale::En, &[&"{1}", &"}"]),
+            "expected {1}, found }"
+        );
+
+        set_locale(Locale::En);
+        assert_eq!(locale(), Locale::En);
+        assert_eq!(Message::NotCallable.format(&[&5]), "not a function: 5");
+        set_locale(Locale::Ja);
+        assert_eq!(
+            Message::NotCallable.format(&[&5]),
+            "関数ではありません。: 5"
+        );
+    }
+}
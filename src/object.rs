@@ -1,20 +1,217 @@
 use crate::ast;
+use crate::bigint;
+use crate::builtin;
 use crate::environment;
+use crate::lexer;
+use crate::vm;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Object {
-    Integer(i32),
+    Integer(i64),
+    BigInt(bigint::BigInt), // i64 に収まらない整数
+    Float(f64),
+    String(String),
     Boolean(bool),
+    Array(Vec<Object>),
+    Hash(BTreeMap<HashKey, Object>),
     Null,
     ReturnValue(Box<Object>),
     Function {
         parameters: Vec<ast::Expression>,
         body: Box<ast::Statement>,
-        environment: environment::Environment,
+        slots: Option<Rc<environment::SlotNames>>, // 静的解決したスコープの名前
+        environment: Rc<RefCell<environment::Environment>>, // 関数を作った環境
     },
+    Builtin(builtin::Builtin),
+    Closure(Rc<vm::Closure>), // バイトコード VM の関数
+}
+
+/// ハッシュのキーとして使えるオブジェクト
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HashKey {
+    Integer(i64),
+    BigInt(bigint::BigInt),
+    Boolean(bool),
+    String(String),
+}
+
+impl HashKey {
+    pub fn to_object(&self) -> Object {
+        match self {
+            HashKey::Integer(integer) => Object::Integer(*integer),
+            HashKey::BigInt(bigint) => Object::BigInt(bigint.clone()),
+            HashKey::Boolean(boolean) => Object::Boolean(*boolean),
+            HashKey::String(string) => Object::String(string.clone()),
+        }
+    }
+}
+
+impl From<i32> for Object {
+    fn from(integer: i32) -> Self {
+        Object::Integer(integer as i64)
+    }
+}
+
+impl From<i64> for Object {
+    fn from(integer: i64) -> Self {
+        Object::Integer(integer)
+    }
+}
+
+// i64 に収まる場合は Integer にする
+impl From<bigint::BigInt> for Object {
+    fn from(bigint: bigint::BigInt) -> Self {
+        match bigint.to_i64() {
+            Some(integer) => Object::Integer(integer),
+            None => Object::BigInt(bigint),
+        }
+    }
+}
+
+impl From<f64> for Object {
+    fn from(float: f64) -> Self {
+        Object::Float(float)
+    }
+}
+
+impl From<bool> for Object {
+    fn from(boolean: bool) -> Self {
+        Object::Boolean(boolean)
+    }
+}
+
+impl From<String> for Object {
+    fn from(string: String) -> Self {
+        Object::String(string)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(string: &str) -> Self {
+        Object::String(string.to_string())
+    }
+}
+
+impl<T: Into<Object>> From<Vec<T>> for Object {
+    fn from(elements: Vec<T>) -> Self {
+        Object::Array(elements.into_iter().map(|element| element.into()).collect())
+    }
+}
+
+// 変換できない場合は元のオブジェクトをそのまま返す
+impl TryFrom<Object> for i64 {
+    type Error = Object;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        match object {
+            Object::Integer(integer) => Ok(integer),
+            other => Err(other),
+        }
+    }
+}
+
+impl TryFrom<Object> for i32 {
+    type Error = Object;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        match object {
+            Object::Integer(integer) => i32::try_from(integer).map_err(|_| object),
+            other => Err(other),
+        }
+    }
+}
+
+impl TryFrom<Object> for bigint::BigInt {
+    type Error = Object;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        match object {
+            Object::Integer(integer) => Ok(bigint::BigInt::from(integer)),
+            Object::BigInt(bigint) => Ok(bigint),
+            other => Err(other),
+        }
+    }
+}
+
+impl TryFrom<Object> for f64 {
+    type Error = Object;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        match object {
+            Object::Float(float) => Ok(float),
+            Object::Integer(integer) => Ok(integer as f64),
+            Object::BigInt(bigint) => Ok(bigint.to_f64()),
+            other => Err(other),
+        }
+    }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = Object;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        match object {
+            Object::Boolean(boolean) => Ok(boolean),
+            other => Err(other),
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = Object;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        match object {
+            Object::String(string) => Ok(string),
+            other => Err(other),
+        }
+    }
+}
+
+impl TryFrom<Object> for Vec<Object> {
+    type Error = Object;
+
+    fn try_from(object: Object) -> Result<Self, Self::Error> {
+        match object {
+            Object::Array(elements) => Ok(elements),
+            other => Err(other),
+        }
+    }
 }
 
 impl Object {
+    /// ハッシュのキーに変換する (キーにできないオブジェクトは None)
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer(integer) => Some(HashKey::Integer(*integer)),
+            Object::BigInt(bigint) => Some(HashKey::BigInt(bigint.clone())),
+            Object::Boolean(boolean) => Some(HashKey::Boolean(*boolean)),
+            Object::String(string) => Some(HashKey::String(string.clone())),
+            _ => None,
+        }
+    }
+
+    /// 型名 (REPL の :type 向け)
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) => "integer",
+            Object::BigInt(_) => "bigint",
+            Object::Float(_) => "float",
+            Object::String(_) => "string",
+            Object::Boolean(_) => "boolean",
+            Object::Array(_) => "array",
+            Object::Hash(_) => "hash",
+            Object::Null => "null",
+            Object::ReturnValue(object) => object.type_name(),
+            Object::Function { .. } | Object::Closure(_) => "function",
+            Object::Builtin(_) => "builtin",
+        }
+    }
+
     pub fn is_truthly(&self) -> bool {
         match self {
             Object::Boolean(boolean) => *boolean,
@@ -25,24 +222,47 @@ impl Object {
     pub fn inspect(&self) -> String {
         match self {
             Object::Integer(integer) => integer.to_string(),
+            Object::BigInt(bigint) => bigint.to_string(),
+            // Debug 表記は 1.0 や 1e-9 のように整数と区別でき、字句解析器で読み戻せる
+            Object::Float(float) => format!("{:?}", float),
+            Object::String(string) => lexer::quote_string(string),
             Object::Boolean(boolean) => boolean.to_string(),
+            Object::Array(elements) => {
+                let element_list = elements
+                    .iter()
+                    .map(|element| element.inspect())
+                    .collect::<Vec<String>>();
+                "[".to_string() + &element_list.join(", ") + "]"
+            }
+            Object::Hash(pairs) => {
+                let pair_list = pairs
+                    .iter()
+                    .map(|(key, value)| key.to_object().inspect() + ": " + &value.inspect())
+                    .collect::<Vec<String>>();
+                "{".to_string() + &pair_list.join(", ") + "}"
+            }
             Object::Null => "".to_string(),
             Object::ReturnValue(object) => object.inspect(),
+            Object::Builtin(builtin) => format!("builtin function: {}", builtin.name),
             Object::Function {
                 parameters, body, ..
-            } => {
-                let param_list = parameters
-                    .iter()
-                    .map(|param| param.to_code())
-                    .collect::<Vec<String>>();
-                let mut result = "fn(".to_string();
-                result.push_str(param_list.join(", ").as_str());
-                result.push_str(")");
-                result.push_str(body.to_code().as_str());
-                result.push_str("\n");
-
-                return result;
-            }
+            } => function_code(parameters, body),
+            Object::Closure(closure) => closure.function.code.clone(),
         }
     }
 }
+
+/// 関数の表示 (`fn(x){...}` の後に改行)
+pub fn function_code(parameters: &[ast::Expression], body: &ast::Statement) -> String {
+    let param_list = parameters
+        .iter()
+        .map(|param| param.to_code())
+        .collect::<Vec<String>>();
+    let mut result = "fn(".to_string();
+    result.push_str(param_list.join(", ").as_str());
+    result.push(')');
+    result.push_str(body.to_code().as_str());
+    result.push('\n');
+
+    result
+}
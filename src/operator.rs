@@ -49,6 +49,7 @@ pub enum Precedences {
     Product,
     Prefix,
     Call,
+    Index,
 }
 
 #[cfg(test)]
@@ -57,11 +58,12 @@ mod tests {
 
     #[test]
     fn test_compare_precedence() {
-        assert_eq!(Precedences::Lowest < Precedences::Equals, true);
-        assert_eq!(Precedences::Equals < Precedences::LessGreater, true);
-        assert_eq!(Precedences::LessGreater < Precedences::Sum, true);
-        assert_eq!(Precedences::Sum < Precedences::Product, true);
-        assert_eq!(Precedences::Product < Precedences::Prefix, true);
-        assert_eq!(Precedences::Prefix < Precedences::Call, true);
+        assert!(Precedences::Lowest < Precedences::Equals);
+        assert!(Precedences::Equals < Precedences::LessGreater);
+        assert!(Precedences::LessGreater < Precedences::Sum);
+        assert!(Precedences::Sum < Precedences::Product);
+        assert!(Precedences::Product < Precedences::Prefix);
+        assert!(Precedences::Prefix < Precedences::Call);
+        assert!(Precedences::Call < Precedences::Index);
     }
 }
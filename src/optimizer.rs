@@ -0,0 +1,350 @@
+use crate::ast;
+use crate::evaluator;
+use crate::object;
+use crate::token;
+
+/// 評価の前に構文木を書き換える
+///
+/// - 整数・真偽値の定数同士の演算を畳み込む (ゼロ除算などエラーになる式はそのまま残す)
+/// - 条件が定数の if は、選ばれない側の分岐を取り除く
+/// - return の後の到達しない文を取り除く
+///
+/// 結果とエラーは書き換える前と同じになる。関数の表示とエラーメッセージ中の呼び出し式は
+/// コードをそのまま見せるので、関数リテラルと呼び出す式は書き換えない。
+pub fn optimize(program: ast::Program) -> ast::Program {
+    ast::Program {
+        statements: optimize_statements(program.statements),
+        globals: program.globals,
+    }
+}
+
+fn optimize_statements(statements: Vec<ast::Statement>) -> Vec<ast::Statement> {
+    let mut optimized = Vec::new();
+    let length = statements.len();
+    for (i, statement) in statements.into_iter().enumerate() {
+        let is_last = i + 1 == length;
+        let start = optimized.len();
+        let statement = optimize_statement(statement);
+        match statement.kind {
+            // 条件が真の定数の if 文は、中の文を外側に並べる (ブロックはスコープを作らない)
+            // 空のブロックは値が null になるので、最後の文でなければ取り除く
+            ast::StatementKind::Expression(ast::Expression {
+                kind:
+                    ast::ExpressionKind::IfExpression {
+                        condition,
+                        consequence,
+                        alternative: None,
+                    },
+                span,
+            }) if truthiness(&condition).is_some() => {
+                let statements = match (truthiness(&condition), consequence.kind) {
+                    (Some(true), ast::StatementKind::Block(statements)) => statements,
+                    _ => Vec::new(),
+                };
+                if statements.is_empty() && is_last {
+                    let kind = ast::ExpressionKind::IfExpression {
+                        condition,
+                        consequence: Box::new(ast::Statement::new(
+                            ast::StatementKind::Block(Vec::new()),
+                            span,
+                        )),
+                        alternative: None,
+                    };
+                    optimized.push(ast::Statement::new(
+                        ast::StatementKind::Expression(ast::Expression::new(kind, span)),
+                        statement.span,
+                    ));
+                }
+                optimized.extend(statements);
+            }
+            kind => optimized.push(ast::Statement::new(kind, statement.span)),
+        }
+
+        // 並べた文の中に return があれば、それより後ろには到達しない
+        if let Some(position) = optimized[start..]
+            .iter()
+            .position(|statement| matches!(statement.kind, ast::StatementKind::Return(_)))
+        {
+            optimized.truncate(start + position + 1);
+            break;
+        }
+    }
+    optimized
+}
+
+fn optimize_statement(statement: ast::Statement) -> ast::Statement {
+    let kind = match statement.kind {
+        ast::StatementKind::Let { identifier, value } => ast::StatementKind::Let {
+            identifier,
+            value: optimize_expression(value),
+        },
+        ast::StatementKind::Return(expression) => {
+            ast::StatementKind::Return(optimize_expression(expression))
+        }
+        ast::StatementKind::Expression(expression) => {
+            ast::StatementKind::Expression(optimize_expression(expression))
+        }
+        ast::StatementKind::Block(statements) => {
+            ast::StatementKind::Block(optimize_statements(statements))
+        }
+    };
+    ast::Statement::new(kind, statement.span)
+}
+
+fn optimize_expression(expression: ast::Expression) -> ast::Expression {
+    let span = expression.span;
+    let kind = match expression.kind {
+        ast::ExpressionKind::PrefixExpression {
+            operator,
+            expression: right,
+        } => {
+            let right = optimize_expression(*right);
+            let folded = literal(&right).and_then(|right| {
+                evaluator::Evaluator::eval_prefix_expression(operator.clone(), &right, span).ok()
+            });
+            match folded.and_then(from_object) {
+                Some(kind) => kind,
+                None => ast::ExpressionKind::PrefixExpression {
+                    operator,
+                    expression: Box::new(right),
+                },
+            }
+        }
+        ast::ExpressionKind::InfixExpression {
+            left,
+            operator,
+            right,
+        } => {
+            let left = optimize_expression(*left);
+            let right = optimize_expression(*right);
+            let folded = match (literal(&left), literal(&right)) {
+                (Some(left), Some(right)) => evaluator::Evaluator::eval_infix_expression(
+                    &left,
+                    operator.clone(),
+                    &right,
+                    span,
+                )
+                .ok(),
+                _ => None,
+            };
+            match folded.and_then(from_object) {
+                Some(kind) => kind,
+                None => ast::ExpressionKind::InfixExpression {
+                    left: Box::new(left),
+                    operator,
+                    right: Box::new(right),
+                },
+            }
+        }
+        ast::ExpressionKind::IfExpression {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            let condition = optimize_expression(*condition);
+            let consequence = optimize_statement(*consequence);
+            let alternative = alternative.map(|alternative| optimize_statement(*alternative));
+            match truthiness(&condition) {
+                Some(truthy) => {
+                    let chosen = if truthy {
+                        Some(consequence)
+                    } else {
+                        alternative
+                    };
+                    return prune_if(condition, chosen, span);
+                }
+                None => ast::ExpressionKind::IfExpression {
+                    condition: Box::new(condition),
+                    consequence: Box::new(consequence),
+                    alternative: alternative.map(Box::new),
+                },
+            }
+        }
+        // 関数の本体は関数を表示したときに見えるので書き換えない
+        kind @ ast::ExpressionKind::Function { .. } => kind,
+        // 呼び出す式は引数の数が合わない場合のエラーメッセージに使うので書き換えない
+        ast::ExpressionKind::Call { function, args } => ast::ExpressionKind::Call {
+            function,
+            args: args.into_iter().map(optimize_expression).collect(),
+        },
+        ast::ExpressionKind::Array(elements) => {
+            ast::ExpressionKind::Array(elements.into_iter().map(optimize_expression).collect())
+        }
+        ast::ExpressionKind::Hash(pairs) => ast::ExpressionKind::Hash(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (optimize_expression(key), optimize_expression(value)))
+                .collect(),
+        ),
+        ast::ExpressionKind::Index { left, index } => ast::ExpressionKind::Index {
+            left: Box::new(optimize_expression(*left)),
+            index: Box::new(optimize_expression(*index)),
+        },
+        kind => kind,
+    };
+    ast::Expression::new(kind, span)
+}
+
+/// 条件が定数の if を、選ばれた分岐だけにする
+///
+/// 分岐が式1つならその式に置き換える。そうでなければ常に真の条件で分岐を残す
+/// (選ばれる分岐が無ければ、null になる空の分岐を残す)。
+fn prune_if(
+    condition: ast::Expression,
+    chosen: Option<ast::Statement>,
+    span: token::Span,
+) -> ast::Expression {
+    let chosen = match chosen {
+        Some(chosen) => chosen,
+        None => {
+            let kind = ast::ExpressionKind::IfExpression {
+                condition: Box::new(ast::Expression::new(
+                    ast::ExpressionKind::Boolean(false),
+                    condition.span,
+                )),
+                consequence: Box::new(ast::Statement::new(
+                    ast::StatementKind::Block(Vec::new()),
+                    span,
+                )),
+                alternative: None,
+            };
+            return ast::Expression::new(kind, span);
+        }
+    };
+    if let ast::StatementKind::Block(statements) = &chosen.kind {
+        if let [ast::Statement {
+            kind: ast::StatementKind::Expression(expression),
+            ..
+        }] = statements.as_slice()
+        {
+            return expression.clone();
+        }
+    }
+    let kind = ast::ExpressionKind::IfExpression {
+        condition: Box::new(ast::Expression::new(
+            ast::ExpressionKind::Boolean(true),
+            condition.span,
+        )),
+        consequence: Box::new(chosen),
+        alternative: None,
+    };
+    ast::Expression::new(kind, span)
+}
+
+/// 定数の式の値
+fn literal(expression: &ast::Expression) -> Option<object::Object> {
+    match &expression.kind {
+        ast::ExpressionKind::Integer(integer) => Some(object::Object::Integer(*integer)),
+        ast::ExpressionKind::BigInt(bigint) => Some(object::Object::BigInt(bigint.clone())),
+        ast::ExpressionKind::Boolean(boolean) => Some(object::Object::Boolean(*boolean)),
+        _ => None,
+    }
+}
+
+/// 畳み込んだ値を式に戻す (整数・真偽値のみ。多倍長整数などは畳み込まない)
+fn from_object(object: object::Object) -> Option<ast::ExpressionKind> {
+    match object {
+        object::Object::Integer(integer) => Some(ast::ExpressionKind::Integer(integer)),
+        object::Object::Boolean(boolean) => Some(ast::ExpressionKind::Boolean(boolean)),
+        _ => None,
+    }
+}
+
+/// 条件として評価したときの真偽 (定数でなければ None)
+fn truthiness(expression: &ast::Expression) -> Option<bool> {
+    match &expression.kind {
+        ast::ExpressionKind::Boolean(boolean) => Some(*boolean),
+        ast::ExpressionKind::Integer(_)
+        | ast::ExpressionKind::BigInt(_)
+        | ast::ExpressionKind::Float(_)
+        | ast::ExpressionKind::String(_) => Some(true),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter;
+    use crate::lexer;
+    use crate::parser;
+
+    fn parse(input: &str) -> ast::Program {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = parser::Parser::new(lexer);
+        parser.parse_program().expect("parser error")
+    }
+
+    #[test]
+    fn test_optimize() {
+        let problem = [
+            ("(1 + 2) * 3", "9;\n"),
+            ("-(2 - 5) == 3", "true;\n"),
+            ("!(1 < 2) != false", "false;\n"),
+            ("x * (4 / 2)", "(x * 2);\n"),
+            // エラーや多倍長整数になる式は畳み込まない
+            ("1 / 0", "(1 / 0);\n"),
+            ("1 + true", "(1 + true);\n"),
+            ("9223372036854775807 + 1", "(9223372036854775807 + 1);\n"),
+            ("1.5 + 1", "(1.5 + 1);\n"),
+            ("if (true) { a } else { b }", "a;\n"),
+            ("let x = if (1 > 2) { a } else { b };", "let x = b;\n"),
+            ("let x = if (false) { a };", "let x = if false {\n} ;\n"),
+            ("if (1) { let y = 1; y }; z", "let y = 1;\ny;\nz;\n"),
+            ("a; if (false) { b }", "a;\nif false {\n} ;\n"),
+            ("if (false) { b }; a", "a;\n"),
+            (
+                "if (c) { 1 + 1 } else { !true }",
+                "if c {\n2;\n} else {\nfalse;\n};\n",
+            ),
+            ("return 1; puts(2); 3", "return 1;\n"),
+            ("[1 + 1, {2 * 2: f(3 - 3)}[4]]", "[2, ({4: f(0)}[4])];\n"),
+        ];
+
+        for (input, expected) in problem {
+            assert_eq!(optimize(parse(input)).to_code(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_optimize_function() {
+        // 関数は表示されるので、本体も呼び出す式も書き換えない
+        let input = "fn(x) { if (true) { return x; }; x + 1 }(1 + 2)";
+        let program = optimize(parse(input));
+        assert_eq!(
+            program.to_code(),
+            "fn(x){\nif true {\nreturn x;\n} ;\n(x + 1);\n}(3);\n"
+        );
+    }
+
+    #[test]
+    fn test_optimize_same_result() {
+        let problem = [
+            "let f = fn(n) { if (true) { return n * (2 + 3); } n }; f(4)",
+            "let x = 10; if (1 < 2) { let y = x * 2; y + 1 }",
+            "if (false) { 1 }",
+            "5; if (false) { 1 }",
+            "let f = fn() { if (false) { return 1; }; 2 }; f()",
+            "[1 / 0]",
+            "-true",
+            "fn(x) { if (true) { x } else { 1 / 0 } }",
+            "[fn() { fn(y) { 1 + 2 } }()]",
+            "fn(x) { if (true) { x } }(1, 2)",
+            "(if (true) { fn() { 1 } } else { 2 })(3)",
+        ];
+
+        for input in problem {
+            let expected = interpreter::Interpreter::new().eval_program(&parse(input));
+            let actual = interpreter::Interpreter::new().eval_program(&optimize(parse(input)));
+            match (expected, actual) {
+                (Ok(expected), Ok(actual)) => {
+                    assert_eq!(actual.inspect(), expected.inspect(), "{}", input)
+                }
+                (Err(expected), Err(actual)) => {
+                    assert_eq!(actual.to_string(), expected.to_string(), "{}", input)
+                }
+                (expected, actual) => panic!("{}: {:?} != {:?}", input, expected, actual),
+            }
+        }
+    }
+}
@@ -7,15 +7,21 @@ use crate::token;
 pub struct Parser<'a> {
     lexer: lexer::Lexer<'a>,
     current_token: token::Token,
+    current_span: token::Span,
     next_token: token::Token,
+    next_span: token::Span,
+    errors: Vec<error::ParserError<'static>>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(lexer: lexer::Lexer<'a>) -> Self {
         let mut parser = Parser {
-            lexer: lexer,
+            lexer,
             current_token: token::Token::Illegal,
+            current_span: token::Span::default(),
             next_token: token::Token::Illegal,
+            next_span: token::Span::default(),
+            errors: Vec::new(),
         };
 
         parser.seek_token();
@@ -24,36 +30,81 @@ impl<'a> Parser<'a> {
     }
 
     fn seek_token(&mut self) {
-        self.current_token = self.next_token.clone();
-        self.next_token = self.lexer.read_next_token();
+        let next = self.lexer.read_next_token();
+        self.current_token = std::mem::replace(&mut self.next_token, next.token);
+        self.current_span = std::mem::replace(&mut self.next_span, next.span);
     }
 
+    /// 最初の構文エラーで失敗する。全てのエラーが必要な場合は parse_program_with_errors を使う
     pub fn parse_program(&mut self) -> Result<ast::Program, Box<dyn std::error::Error>> {
+        let (program, errors) = self.parse_program_with_errors();
+        match errors.into_iter().next() {
+            Some(error) => Err(Box::new(error)),
+            None => Ok(program),
+        }
+    }
+
+    /// 構文エラーの後も文の境界で同期して読み進め、部分的な Program と全てのエラーを返す
+    pub fn parse_program_with_errors(
+        &mut self,
+    ) -> (ast::Program, Vec<error::ParserError<'static>>) {
         let mut program = ast::Program::new();
         while self.current_token != token::Token::EndOfFile {
-            let statement = self.parse_statement()?;
-            program.statements.push(statement);
+            match self.parse_statement() {
+                Ok(statement) => program.statements.push(statement),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize();
+                }
+            }
             self.seek_token(); // 次の文 へ進む
         }
-        Ok(program)
+        (program, std::mem::take(&mut self.errors))
     }
 
-    fn parse_block_statement(&mut self) -> Result<ast::Statement, Box<dyn std::error::Error>> {
+    fn parse_block_statement(&mut self) -> Result<ast::Statement, error::ParserError<'static>> {
+        let start = self.current_span;
         let mut statements: Vec<ast::Statement> = Vec::new();
 
         self.seek_token(); // Block内の文 に進む
         while (self.current_token != token::Token::Rbrace)
             && (self.current_token != token::Token::EndOfFile)
         {
-            let statement = self.parse_statement()?;
-            statements.push(statement);
+            match self.parse_statement() {
+                Ok(statement) => statements.push(statement),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.synchronize();
+                    if self.current_token == token::Token::Rbrace {
+                        break; // Block の終わりで同期した
+                    }
+                }
+            }
             self.seek_token(); // 次の文 に進む
         }
 
-        Ok(ast::Statement::Block(statements))
+        Ok(ast::Statement::new(
+            ast::StatementKind::Block(statements),
+            start.to(self.current_span),
+        ))
+    }
+
+    /// エラーの後、文の境界 (`;` `}` の上、または `let` `return` の直前) までトークンを読み飛ばす
+    fn synchronize(&mut self) {
+        loop {
+            match self.current_token {
+                token::Token::Semicolon | token::Token::Rbrace | token::Token::EndOfFile => return,
+                _ => {}
+            }
+            match self.next_token {
+                token::Token::Let | token::Token::Return => return,
+                _ => {}
+            }
+            self.seek_token();
+        }
     }
 
-    fn parse_statement(&mut self) -> Result<ast::Statement, Box<dyn std::error::Error>> {
+    fn parse_statement(&mut self) -> Result<ast::Statement, error::ParserError<'static>> {
         match self.current_token {
             token::Token::Let => self.parse_let_statement(),
             token::Token::Return => self.parse_return_statement(),
@@ -61,14 +112,19 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_let_statement(&mut self) -> Result<ast::Statement, Box<dyn std::error::Error>> {
+    fn parse_let_statement(&mut self) -> Result<ast::Statement, error::ParserError<'static>> {
+        let start = self.current_span;
         self.seek_token(); // Identifier に進む
         let identifier = if let token::Token::Identifier(identifier) = &self.current_token {
-            ast::Expression::Identifier(identifier.to_owned())
+            ast::Expression::new(
+                ast::ExpressionKind::Identifier(identifier.to_owned(), None),
+                self.current_span,
+            )
         } else {
             return Err(error::ParserError::NotFoundLetIdentifier {
-                found_token: self.next_token.clone(),
-            })?;
+                found_token: self.current_token.clone(),
+                span: self.current_span,
+            });
         };
 
         self.seek_token(); // Assign に進む
@@ -82,13 +138,17 @@ impl<'a> Parser<'a> {
             self.seek_token(); // Semicolon に進む
         }
 
-        Ok(ast::Statement::Let {
-            identifier: identifier,
-            value: expression,
-        })
+        Ok(ast::Statement::new(
+            ast::StatementKind::Let {
+                identifier,
+                value: expression,
+            },
+            start.to(self.current_span),
+        ))
     }
 
-    fn parse_return_statement(&mut self) -> Result<ast::Statement, Box<dyn std::error::Error>> {
+    fn parse_return_statement(&mut self) -> Result<ast::Statement, error::ParserError<'static>> {
+        let start = self.current_span;
         self.seek_token(); // 式 に進む
         let expression = self.parse_expression(operator::Precedences::Lowest)?;
 
@@ -97,11 +157,17 @@ impl<'a> Parser<'a> {
             self.seek_token(); // Semicolon に進む
         }
 
-        Ok(ast::Statement::Return(expression))
+        Ok(ast::Statement::new(
+            ast::StatementKind::Return(expression),
+            start.to(self.current_span),
+        ))
     }
 
-    fn parse_expression_statement(&mut self) -> Result<ast::Statement, Box<dyn std::error::Error>> {
+    fn parse_expression_statement(
+        &mut self,
+    ) -> Result<ast::Statement, error::ParserError<'static>> {
         // 式文は文のトークンが無いのでここでseek不要
+        let start = self.current_span;
         let expression = self.parse_expression(operator::Precedences::Lowest)?;
 
         if self.next_token == token::Token::Semicolon {
@@ -109,22 +175,26 @@ impl<'a> Parser<'a> {
             self.seek_token(); // Semicolon に進む
         }
 
-        Ok(ast::Statement::Expression(expression))
+        Ok(ast::Statement::new(
+            ast::StatementKind::Expression(expression),
+            start.to(self.current_span),
+        ))
     }
 
     fn parse_expression(
         &mut self,
         precedence: operator::Precedences,
-    ) -> Result<ast::Expression, Box<dyn std::error::Error>> {
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
         let mut expression = match self.current_token.clone() {
             token::Token::Identifier(identifier) => self.parse_identifier(identifier.as_str())?,
             token::Token::Integer(integer) => self.parse_integer(integer)?,
-            token::Token::Minus => {
-                self.seek_token(); // Prefix の右辺式 に進む
-                self.parse_prefix_expression(operator::Prefix::Minus)?
+            token::Token::BigInt(bigint) => {
+                ast::Expression::new(ast::ExpressionKind::BigInt(bigint), self.current_span)
             }
+            token::Token::Float(float) => self.parse_float(float)?,
+            token::Token::String(string) => self.parse_string(string)?,
+            token::Token::Minus => self.parse_prefix_expression(operator::Prefix::Minus)?,
             token::Token::Exclamation => {
-                self.seek_token(); // Prefix の右辺式 に進む
                 self.parse_prefix_expression(operator::Prefix::Exclamation)?
             }
             token::Token::True => self.parse_boolean(true)?,
@@ -132,10 +202,19 @@ impl<'a> Parser<'a> {
             token::Token::Lparentheses => self.parse_grouped_expression()?,
             token::Token::If => self.parse_if_expression()?,
             token::Token::Function => self.parse_function_expression()?,
+            token::Token::Lbracket => self.parse_array_expression()?,
+            token::Token::Lbrace => self.parse_hash_expression()?,
+            token::Token::Error(error) => {
+                return Err(error::ParserError::InvalidToken {
+                    error,
+                    span: self.current_span,
+                });
+            }
             _ => {
                 return Err(error::ParserError::UnImplementationParser(
                     "式のパーサーが未実装です。",
-                ))?;
+                    self.current_span,
+                ));
             }
         };
 
@@ -143,7 +222,7 @@ impl<'a> Parser<'a> {
             && (precedence < self.next_token.precedence())
         {
             self.seek_token(); // Infix に進む
-            expression = self.parse_infix_expression(&expression)?;
+            expression = self.parse_infix_expression(expression)?;
         }
 
         Ok(expression)
@@ -152,41 +231,75 @@ impl<'a> Parser<'a> {
     fn parse_identifier(
         &mut self,
         identifier: &str,
-    ) -> Result<ast::Expression, Box<dyn std::error::Error>> {
-        Ok(ast::Expression::Identifier(identifier.to_string()))
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Identifier(identifier.to_string(), None),
+            self.current_span,
+        ))
     }
 
     fn parse_integer(
         &mut self,
-        identifier: i32,
-    ) -> Result<ast::Expression, Box<dyn std::error::Error>> {
-        Ok(ast::Expression::Integer(identifier))
+        identifier: i64,
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Integer(identifier),
+            self.current_span,
+        ))
+    }
+
+    fn parse_float(&mut self, float: f64) -> Result<ast::Expression, error::ParserError<'static>> {
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Float(float),
+            self.current_span,
+        ))
+    }
+
+    fn parse_string(
+        &mut self,
+        string: String,
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::String(string),
+            self.current_span,
+        ))
     }
 
     fn parse_boolean(
         &mut self,
         boolean: bool,
-    ) -> Result<ast::Expression, Box<dyn std::error::Error>> {
-        Ok(ast::Expression::Boolean(boolean))
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Boolean(boolean),
+            self.current_span,
+        ))
     }
 
     fn parse_prefix_expression(
         &mut self,
         operator: operator::Prefix,
-    ) -> Result<ast::Expression, Box<dyn std::error::Error>> {
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
+        let start = self.current_span;
+        self.seek_token(); // Prefix の右辺式 に進む
         let expression = self.parse_expression(operator::Precedences::Prefix)?;
-        Ok(ast::Expression::PrefixExpression {
-            operator: operator,
-            expression: Box::new(expression),
-        })
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::PrefixExpression {
+                operator,
+                expression: Box::new(expression),
+            },
+            start.to(self.current_span),
+        ))
     }
 
     fn parse_infix_expression(
         &mut self,
-        left: &ast::Expression,
-    ) -> Result<ast::Expression, Box<dyn std::error::Error>> {
+        left: ast::Expression,
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
         if self.current_token == token::Token::Lparentheses {
-            return self.parse_call_expression(&left);
+            return self.parse_call_expression(left);
+        }
+        if self.current_token == token::Token::Lbracket {
+            return self.parse_index_expression(left);
         }
         let infix = match self.current_token {
             token::Token::Plus => operator::Infix::Plus,
@@ -197,37 +310,49 @@ impl<'a> Parser<'a> {
             token::Token::GreaterThan => operator::Infix::GreaterThan,
             token::Token::Equal => operator::Infix::Equal,
             token::Token::NotEqual => operator::Infix::NotEqual,
-            _ => Err(error::ParserError::NotFoundInfixToken {
-                found_token: self.current_token.clone(),
-            })?,
+            _ => {
+                return Err(error::ParserError::NotFoundInfixToken {
+                    found_token: self.current_token.clone(),
+                    span: self.current_span,
+                })
+            }
         };
 
         let precedence = self.current_token.precedence(); // 中置演算子の優先度
         self.seek_token(); // infix の右辺式 に進む
         let right = self.parse_expression(precedence)?;
 
-        Ok(ast::Expression::InfixExpression {
-            left: Box::new(left.clone()),
-            operator: infix,
-            right: Box::new(right),
-        })
+        let span = left.span.to(self.current_span);
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::InfixExpression {
+                left: Box::new(left),
+                operator: infix,
+                right: Box::new(right),
+            },
+            span,
+        ))
     }
 
     fn parse_call_expression(
         &mut self,
-        function: &ast::Expression,
-    ) -> Result<ast::Expression, Box<dyn std::error::Error>> {
+        function: ast::Expression,
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
         self.seek_token(); // 引数 or Rparenthesesに進む
-        let args = self.parse_function_parameters()?;
+        let args = self.parse_expression_list(token::Token::Rparentheses)?;
         self.expect_current(token::Token::Rparentheses)?;
 
-        Ok(ast::Expression::Call {
-            function: Box::new(function.clone()),
-            args: args,
-        })
+        let span = function.span.to(self.current_span);
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Call {
+                function: Box::new(function),
+                args,
+            },
+            span,
+        ))
     }
 
-    fn parse_if_expression(&mut self) -> Result<ast::Expression, Box<dyn std::error::Error>> {
+    fn parse_if_expression(&mut self) -> Result<ast::Expression, error::ParserError<'static>> {
+        let start = self.current_span;
         self.seek_token(); // Lparentheses に進む
         self.expect_current(token::Token::Lparentheses)?;
 
@@ -252,71 +377,149 @@ impl<'a> Parser<'a> {
             None
         };
 
-        Ok(ast::Expression::IfExpression {
-            condition: Box::new(condition),
-            consequence: Box::new(consequence),
-            alternative: alternative,
-        })
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::IfExpression {
+                condition: Box::new(condition),
+                consequence: Box::new(consequence),
+                alternative,
+            },
+            start.to(self.current_span),
+        ))
     }
 
-    fn parse_function_expression(&mut self) -> Result<ast::Expression, Box<dyn std::error::Error>> {
+    fn parse_function_expression(
+        &mut self,
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
+        let start = self.current_span;
         self.seek_token(); // Lparentheses に進む
         self.expect_current(token::Token::Lparentheses)?;
 
         self.seek_token(); // パラメータ or Rparentheses に進む
-        let parameters = self.parse_function_parameters()?;
+        let parameters = self.parse_expression_list(token::Token::Rparentheses)?;
         self.expect_current(token::Token::Rparentheses)?;
 
         self.seek_token(); // Lbrace に進む
         self.expect_current(token::Token::Lbrace)?;
         let body = self.parse_block_statement()?;
 
-        Ok(ast::Expression::Function {
-            parameters: parameters,
-            body: Box::new(body),
-        })
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Function {
+                parameters,
+                body: Box::new(body),
+                slots: None,
+            },
+            start.to(self.current_span),
+        ))
     }
 
-    fn parse_function_parameters(
+    fn parse_array_expression(&mut self) -> Result<ast::Expression, error::ParserError<'static>> {
+        let start = self.current_span;
+        self.seek_token(); // 要素 or Rbracket に進む
+        let elements = self.parse_expression_list(token::Token::Rbracket)?;
+        self.expect_current(token::Token::Rbracket)?;
+
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Array(elements),
+            start.to(self.current_span),
+        ))
+    }
+
+    fn parse_hash_expression(&mut self) -> Result<ast::Expression, error::ParserError<'static>> {
+        let start = self.current_span;
+        let mut pairs = Vec::new();
+
+        self.seek_token(); // キー or Rbrace に進む
+        while self.current_token != token::Token::Rbrace {
+            let key = self.parse_expression(operator::Precedences::Lowest)?;
+
+            self.seek_token(); // Colon に進む
+            self.expect_current(token::Token::Colon)?;
+
+            self.seek_token(); // 値 に進む
+            let value = self.parse_expression(operator::Precedences::Lowest)?;
+            pairs.push((key, value));
+
+            self.seek_token(); // Comma or Rbrace に進む
+            if self.current_token == token::Token::Comma {
+                self.seek_token(); // 次のキー に進む
+            } else {
+                self.expect_current(token::Token::Rbrace)?;
+            }
+        }
+
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Hash(pairs),
+            start.to(self.current_span),
+        ))
+    }
+
+    fn parse_index_expression(
+        &mut self,
+        left: ast::Expression,
+    ) -> Result<ast::Expression, error::ParserError<'static>> {
+        self.seek_token(); // 添字の式 に進む
+        let index = self.parse_expression(operator::Precedences::Lowest)?;
+
+        self.seek_token(); // Rbracket に進む
+        self.expect_current(token::Token::Rbracket)?;
+
+        let span = left.span.to(self.current_span);
+        Ok(ast::Expression::new(
+            ast::ExpressionKind::Index {
+                left: Box::new(left),
+                index: Box::new(index),
+            },
+            span,
+        ))
+    }
+
+    /// カンマ区切りの式の列を end の直前まで読む (終了時は end の位置)
+    fn parse_expression_list(
         &mut self,
-    ) -> Result<Vec<ast::Expression>, Box<dyn std::error::Error>> {
-        let mut parameters: Vec<ast::Expression> = Vec::new();
+        end: token::Token,
+    ) -> Result<Vec<ast::Expression>, error::ParserError<'static>> {
+        let mut list: Vec<ast::Expression> = Vec::new();
 
-        if self.current_token == token::Token::Rparentheses {
-            return Ok(parameters);
+        if self.current_token == end {
+            return Ok(list);
         }
 
-        // 1つ目のパラメータ
-        parameters.push(self.parse_expression(operator::Precedences::Lowest)?);
+        // 1つ目の式
+        list.push(self.parse_expression(operator::Precedences::Lowest)?);
 
-        self.seek_token(); // Comma or Rparentheses に進む
+        self.seek_token(); // Comma or end に進む
         while self.current_token == token::Token::Comma {
-            self.seek_token(); // パラメータに進む
-            parameters.push(self.parse_expression(operator::Precedences::Lowest)?);
-            self.seek_token(); // Comma or Rparentheses に進む
+            self.seek_token(); // 次の式に進む
+            list.push(self.parse_expression(operator::Precedences::Lowest)?);
+            self.seek_token(); // Comma or end に進む
         }
 
-        Ok(parameters)
+        Ok(list)
     }
 
-    fn parse_grouped_expression(&mut self) -> Result<ast::Expression, Box<dyn std::error::Error>> {
+    fn parse_grouped_expression(&mut self) -> Result<ast::Expression, error::ParserError<'static>> {
+        let start = self.current_span;
         self.seek_token(); // 式 に進む
         let expression = self.parse_expression(operator::Precedences::Lowest)?;
 
         self.seek_token(); // Rparentheses に進む
         self.expect_current(token::Token::Rparentheses)?;
 
-        Ok(expression)
+        Ok(ast::Expression::new(
+            expression.kind,
+            start.to(self.current_span),
+        ))
     }
 
-    fn expect_current(&mut self, token: token::Token) -> Result<(), Box<dyn std::error::Error>> {
+    fn expect_current(&mut self, token: token::Token) -> Result<(), error::ParserError<'static>> {
         if self.current_token == token {
             Ok(())
         } else {
             Err(error::ParserError::UnexpectedToken {
                 actual_token: self.current_token.clone(),
                 expected_token: token,
-            })?
+                span: self.current_span,
+            })
         }
     }
 }
@@ -324,6 +527,7 @@ impl<'a> Parser<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::message::{self, Locale};
 
     #[test]
     fn test_let_statements() {
@@ -334,7 +538,7 @@ mod tests {
         ];
 
         for (input, name, num) in problem {
-            let lexer = lexer::Lexer::new(&input);
+            let lexer = lexer::Lexer::new(input);
             let mut parser = Parser::new(lexer);
 
             let program = match parser.parse_program() {
@@ -348,12 +552,12 @@ mod tests {
         }
     }
 
-    fn test_let_statement(statement: &ast::Statement, name: &str, num: i32) {
-        if let ast::Statement::Let { identifier, value } = statement {
+    fn test_let_statement(statement: &ast::Statement, name: &str, num: i64) {
+        if let ast::StatementKind::Let { identifier, value } = &statement.kind {
             test_identifier_literal(identifier, name);
             test_integer_literal(value, num);
         } else {
-            panic!("expected ast::Statement::Let, but got {:?}", statement);
+            panic!("expected ast::StatementKind::Let, but got {:?}", statement);
         }
     }
 
@@ -366,7 +570,7 @@ mod tests {
         ];
 
         for (input, result_num) in problem {
-            let lexer = lexer::Lexer::new(&input);
+            let lexer = lexer::Lexer::new(input);
             let mut parser = Parser::new(lexer);
 
             let program = match parser.parse_program() {
@@ -380,11 +584,14 @@ mod tests {
         }
     }
 
-    fn test_return_statement(statement: &ast::Statement, num: i32) {
-        if let ast::Statement::Return(exp) = statement {
+    fn test_return_statement(statement: &ast::Statement, num: i64) {
+        if let ast::StatementKind::Return(exp) = &statement.kind {
             test_integer_literal(exp, num);
         } else {
-            panic!("expected ast::Statement::Return, but got {:?}", statement);
+            panic!(
+                "expected ast::StatementKind::Return, but got {:?}",
+                statement
+            );
         }
     }
 
@@ -452,22 +659,22 @@ mod tests {
     }
 
     fn test_expression_statement(statement: &ast::Statement) -> ast::Expression {
-        if let ast::Statement::Expression(expression) = statement {
+        if let ast::StatementKind::Expression(expression) = &statement.kind {
             expression.clone()
         } else {
             panic!(
-                "expected ast::Statement::Expression, but got {:?}",
+                "expected ast::StatementKind::Expression, but got {:?}",
                 statement
             );
         }
     }
 
-    fn test_integer_literal(expression: &ast::Expression, cmp_num: i32) {
-        let integer = if let ast::Expression::Integer(integer) = expression {
+    fn test_integer_literal(expression: &ast::Expression, cmp_num: i64) {
+        let integer = if let ast::ExpressionKind::Integer(integer) = &expression.kind {
             integer
         } else {
             panic!(
-                "expected ast::Expression::Integer, but got {:?}",
+                "expected ast::ExpressionKind::Integer, but got {:?}",
                 expression
             );
         };
@@ -476,11 +683,11 @@ mod tests {
     }
 
     fn test_identifier_literal(expression: &ast::Expression, cmp_str: &str) {
-        let identifier = if let ast::Expression::Identifier(identifier) = expression {
+        let identifier = if let ast::ExpressionKind::Identifier(identifier, _) = &expression.kind {
             identifier
         } else {
             panic!(
-                "expected ast::Expression::Identifier, but got {:?}",
+                "expected ast::ExpressionKind::Identifier, but got {:?}",
                 expression
             );
         };
@@ -489,11 +696,11 @@ mod tests {
     }
 
     fn test_boolean_literal(expression: &ast::Expression, cmp_bool: bool) {
-        let boolean = if let ast::Expression::Boolean(boolean) = expression {
+        let boolean = if let ast::ExpressionKind::Boolean(boolean) = &expression.kind {
             boolean
         } else {
             panic!(
-                "expected ast::Expression::Boolean, but got {:?}",
+                "expected ast::ExpressionKind::Boolean, but got {:?}",
                 expression
             );
         };
@@ -522,15 +729,15 @@ mod tests {
 
             let expression = test_expression_statement(statement);
 
-            let (operator, expression_right) = if let ast::Expression::PrefixExpression {
+            let (operator, expression_right) = if let ast::ExpressionKind::PrefixExpression {
                 operator,
                 expression,
-            } = expression
+            } = expression.kind
             {
                 (operator, expression)
             } else {
                 panic!(
-                    "expected ast::Expression::PrefixExpression, but got {:?}",
+                    "expected ast::ExpressionKind::PrefixExpression, but got {:?}",
                     expression
                 );
             };
@@ -568,16 +775,16 @@ mod tests {
             let expression = test_expression_statement(statement);
 
             let (expression_left, operator, expression_right) =
-                if let ast::Expression::InfixExpression {
+                if let ast::ExpressionKind::InfixExpression {
                     left,
                     operator,
                     right,
-                } = expression
+                } = expression.kind
                 {
                     (left, operator, right)
                 } else {
                     panic!(
-                        "expected ast::Expression::InfixExpression, but got {:?}",
+                        "expected ast::ExpressionKind::InfixExpression, but got {:?}",
                         expression
                     );
                 };
@@ -606,32 +813,32 @@ mod tests {
         let expression = test_expression_statement(statement);
 
         // if式 確認
-        let (condition, consequence, alternative) = if let ast::Expression::IfExpression {
+        let (condition, consequence, alternative) = if let ast::ExpressionKind::IfExpression {
             condition,
             consequence,
             alternative,
-        } = expression
+        } = expression.kind
         {
             (condition, consequence, alternative)
         } else {
             panic!(
-                "expected ast::Expression::IfExpression, but got {:?}",
+                "expected ast::ExpressionKind::IfExpression, but got {:?}",
                 expression
             );
         };
 
         // condition 確認
         let (expression_left, operator, expression_right) =
-            if let ast::Expression::InfixExpression {
+            if let ast::ExpressionKind::InfixExpression {
                 left,
                 operator,
                 right,
-            } = *condition
+            } = condition.kind
             {
                 (left, operator, right)
             } else {
                 panic!(
-                    "expected ast::Expression::InfixExpression, but got {:?}",
+                    "expected ast::ExpressionKind::InfixExpression, but got {:?}",
                     condition
                 );
             };
@@ -641,27 +848,29 @@ mod tests {
         test_identifier_literal(&expression_right, "y");
 
         // consequence 確認
-        let statement = if let ast::Statement::Block(statements) = *consequence {
+        let statement = if let ast::StatementKind::Block(statements) = consequence.kind {
             assert_eq!(statements.len(), 1);
             statements[0].clone()
         } else {
-            panic!("expected ast::Statement::Block, but got {:?}", consequence);
+            panic!(
+                "expected ast::StatementKind::Block, but got {:?}",
+                consequence
+            );
         };
 
-        let expression = if let ast::Statement::Expression(expression) = statement {
+        let expression = if let ast::StatementKind::Expression(expression) = &statement.kind {
             expression
         } else {
             panic!(
-                "expected ast::Statement::Expression, but got {:?}",
+                "expected ast::StatementKind::Expression, but got {:?}",
                 statement
             );
         };
 
-        test_identifier_literal(&expression, "x");
+        test_identifier_literal(expression, "x");
 
         // alternative 確認
-        if let None = alternative {
-        } else {
+        if alternative.is_some() {
             panic!("expected None, but got {:?}", alternative);
         }
     }
@@ -684,32 +893,32 @@ mod tests {
         let expression = test_expression_statement(statement);
 
         // if式 確認
-        let (condition, consequence, alternative) = if let ast::Expression::IfExpression {
+        let (condition, consequence, alternative) = if let ast::ExpressionKind::IfExpression {
             condition,
             consequence,
             alternative,
-        } = expression
+        } = expression.kind
         {
             (condition, consequence, alternative)
         } else {
             panic!(
-                "expected ast::Expression::IfExpression, but got {:?}",
+                "expected ast::ExpressionKind::IfExpression, but got {:?}",
                 expression
             );
         };
 
         // condition 確認
         let (expression_left, operator, expression_right) =
-            if let ast::Expression::InfixExpression {
+            if let ast::ExpressionKind::InfixExpression {
                 left,
                 operator,
                 right,
-            } = *condition
+            } = condition.kind
             {
                 (left, operator, right)
             } else {
                 panic!(
-                    "expected ast::Expression::InfixExpression, but got {:?}",
+                    "expected ast::ExpressionKind::InfixExpression, but got {:?}",
                     condition
                 );
             };
@@ -719,23 +928,26 @@ mod tests {
         test_identifier_literal(&expression_right, "y");
 
         // consequence 確認
-        let statement = if let ast::Statement::Block(statements) = *consequence {
+        let statement = if let ast::StatementKind::Block(statements) = consequence.kind {
             assert_eq!(statements.len(), 1);
             statements[0].clone()
         } else {
-            panic!("expected ast::Statement::Block, but got {:?}", consequence);
+            panic!(
+                "expected ast::StatementKind::Block, but got {:?}",
+                consequence
+            );
         };
 
-        let expression = if let ast::Statement::Expression(expression) = statement {
+        let expression = if let ast::StatementKind::Expression(expression) = &statement.kind {
             expression
         } else {
             panic!(
-                "expected ast::Statement::Expression, but got {:?}",
+                "expected ast::StatementKind::Expression, but got {:?}",
                 statement
             );
         };
 
-        test_identifier_literal(&expression, "x");
+        test_identifier_literal(expression, "x");
 
         // alternative 確認
         let alternative = if let Some(alternative) = alternative {
@@ -744,23 +956,26 @@ mod tests {
             panic!("expected alternative");
         };
 
-        let statement = if let ast::Statement::Block(statements) = *alternative {
+        let statement = if let ast::StatementKind::Block(statements) = alternative.kind {
             assert_eq!(statements.len(), 1);
             statements[0].clone()
         } else {
-            panic!("expected ast::Statement::Block, but got {:?}", alternative);
+            panic!(
+                "expected ast::StatementKind::Block, but got {:?}",
+                alternative
+            );
         };
 
-        let expression = if let ast::Statement::Expression(expression) = statement {
+        let expression = if let ast::StatementKind::Expression(expression) = &statement.kind {
             expression
         } else {
             panic!(
-                "expected ast::Statement::Expression, but got {:?}",
+                "expected ast::StatementKind::Expression, but got {:?}",
                 statement
             );
         };
 
-        test_identifier_literal(&expression, "y");
+        test_identifier_literal(expression, "y");
     }
 
     #[test]
@@ -781,12 +996,14 @@ mod tests {
         let expression = test_expression_statement(statement);
 
         // 関数リテラル 確認
-        let (parameters, body) = if let ast::Expression::Function { parameters, body } = expression
+        let (parameters, body) = if let ast::ExpressionKind::Function {
+            parameters, body, ..
+        } = expression.kind
         {
             (parameters, body)
         } else {
             panic!(
-                "expected ast::Expression::Function, but got {:?}",
+                "expected ast::ExpressionKind::Function, but got {:?}",
                 expression
             );
         };
@@ -798,34 +1015,34 @@ mod tests {
         test_identifier_literal(&parameters[1], "y");
 
         // body 確認
-        let statement = if let ast::Statement::Block(statements) = *body {
+        let statement = if let ast::StatementKind::Block(statements) = body.kind {
             assert_eq!(statements.len(), 1);
             statements[0].clone()
         } else {
-            panic!("expected ast::Statement::Block, but got {:?}", body);
+            panic!("expected ast::StatementKind::Block, but got {:?}", body);
         };
 
-        let expression = if let ast::Statement::Expression(expression) = statement {
+        let expression = if let ast::StatementKind::Expression(expression) = &statement.kind {
             expression
         } else {
             panic!(
-                "expected ast::Statement::Expression, but got {:?}",
+                "expected ast::StatementKind::Expression, but got {:?}",
                 statement
             );
         };
 
-        if let ast::Expression::InfixExpression {
+        if let ast::ExpressionKind::InfixExpression {
             left,
             operator,
             right,
-        } = expression
+        } = &expression.kind
         {
-            test_identifier_literal(&left, "x");
-            assert_eq!(operator, operator::Infix::Plus);
-            test_identifier_literal(&right, "y");
+            test_identifier_literal(left, "x");
+            assert_eq!(*operator, operator::Infix::Plus);
+            test_identifier_literal(right, "y");
         } else {
             panic!(
-                "expected ast::Expression::InfixExpression, but got {:?}",
+                "expected ast::ExpressionKind::InfixExpression, but got {:?}",
                 expression
             );
         }
@@ -854,14 +1071,15 @@ mod tests {
             let expression = test_expression_statement(statement);
 
             // 関数リテラル 確認
-            let parameters = if let ast::Expression::Function { parameters, .. } = expression {
-                parameters
-            } else {
-                panic!(
-                    "expected ast::Expression::Function, but got {:?}",
-                    expression
-                );
-            };
+            let parameters =
+                if let ast::ExpressionKind::Function { parameters, .. } = expression.kind {
+                    parameters
+                } else {
+                    panic!(
+                        "expected ast::ExpressionKind::Function, but got {:?}",
+                        expression
+                    );
+                };
 
             // parameters 確認
             assert_eq!(parameters.len(), results.len());
@@ -890,10 +1108,14 @@ mod tests {
         let expression = test_expression_statement(statement);
 
         // 関数呼出式 確認
-        let (function, args) = if let ast::Expression::Call { function, args } = expression {
+        let (function, args) = if let ast::ExpressionKind::Call { function, args } = expression.kind
+        {
             (function, args)
         } else {
-            panic!("expected ast::Expression::Call, but got {:?}", expression);
+            panic!(
+                "expected ast::ExpressionKind::Call, but got {:?}",
+                expression
+            );
         };
 
         // function 確認
@@ -905,42 +1127,42 @@ mod tests {
         test_integer_literal(&args[0], 1);
 
         let (expression_left, operator, expression_right) =
-            if let ast::Expression::InfixExpression {
+            if let ast::ExpressionKind::InfixExpression {
                 left,
                 operator,
                 right,
-            } = &args[1]
+            } = &args[1].kind
             {
                 (left, operator, right)
             } else {
                 panic!(
-                    "expected ast::Expression::InfixExpression, but got {:?}",
+                    "expected ast::ExpressionKind::InfixExpression, but got {:?}",
                     args[1]
                 );
             };
 
-        test_integer_literal(&expression_left, 2);
+        test_integer_literal(expression_left, 2);
         assert_eq!(*operator, operator::Infix::Asterisk);
-        test_integer_literal(&expression_right, 3);
+        test_integer_literal(expression_right, 3);
 
         let (expression_left, operator, expression_right) =
-            if let ast::Expression::InfixExpression {
+            if let ast::ExpressionKind::InfixExpression {
                 left,
                 operator,
                 right,
-            } = &args[2]
+            } = &args[2].kind
             {
                 (left, operator, right)
             } else {
                 panic!(
-                    "expected ast::Expression::InfixExpression, but got {:?}",
+                    "expected ast::ExpressionKind::InfixExpression, but got {:?}",
                     args[3]
                 );
             };
 
-        test_integer_literal(&expression_left, 4);
+        test_integer_literal(expression_left, 4);
         assert_eq!(*operator, operator::Infix::Plus);
-        test_integer_literal(&expression_right, 5);
+        test_integer_literal(expression_right, 5);
     }
 
     #[test]
@@ -979,6 +1201,17 @@ mod tests {
                 "add(1 + 2 - 3 * 4 / 5 + 6)",
                 "add((((1 + 2) - ((3 * 4) / 5)) + 6));\n",
             ),
+            (
+                "a * [1, 2, 3, 4][b * c] * d",
+                "((a * ([1, 2, 3, 4][(b * c)])) * d);\n",
+            ),
+            (
+                "add(a * b[2], b[1], 2 * [1, 2][1])",
+                "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])));\n",
+            ),
+            ("f(x)[0]", "(f(x)[0]);\n"),
+            ("1.5 * -.5 + 1e-9", "((1.5 * (-0.5)) + 1e-9);\n"),
+            ("2.0 / 1e20", "(2.0 / 1e20);\n"),
         ];
 
         for (input, result) in problem {
@@ -992,4 +1225,246 @@ mod tests {
             assert_eq!(result.to_string(), program.to_code());
         }
     }
+
+    #[test]
+    fn test_statement_span() {
+        let input = "let x = 1 + 2;
+add(x,
+    3);";
+
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(err) => panic!("エラー: {}", err),
+        };
+
+        assert_eq!(program.statements.len(), 2);
+
+        let span = program.statements[0].span;
+        assert_eq!((span.start.line, span.start.column), (1, 1));
+        assert_eq!((span.end.line, span.end.column), (1, 15));
+
+        if let ast::StatementKind::Let { value, .. } = &program.statements[0].kind {
+            assert_eq!((value.span.start.offset, value.span.end.offset), (8, 13));
+        } else {
+            panic!(
+                "expected ast::StatementKind::Let, but got {:?}",
+                program.statements[0]
+            );
+        }
+
+        let expression = test_expression_statement(&program.statements[1]);
+        assert_eq!(
+            (expression.span.start.line, expression.span.start.column),
+            (2, 1)
+        );
+        assert_eq!(
+            (expression.span.end.line, expression.span.end.column),
+            (3, 7)
+        );
+    }
+
+    #[test]
+    fn test_parser_error_position() {
+        message::set_locale(Locale::Ja);
+        let problem = [
+            (
+                "let = 5;",
+                "1:5: (Identifierを期待しましたが、Assignでした。)",
+            ),
+            (
+                "let x = 5;\nlet y 6;",
+                "2:7: (Assignを期待しましたが、Integer(6)でした。)",
+            ),
+            ("let x = 1e+;", "1:9: (数値リテラルが不正です。: 1e+)"),
+            (
+                "let x = 1; /* 閉じていない",
+                "1:12: (ブロックコメントが閉じられていません。)",
+            ),
+            (
+                "if (x { x }",
+                "1:7: (Rparenthesesを期待しましたが、Lbraceでした。)",
+            ),
+        ];
+
+        for (input, result) in problem {
+            let lexer = lexer::Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            match parser.parse_program() {
+                Ok(program) => panic!("エラーを期待しましたが、{}でした。", program.to_code()),
+                Err(err) => assert_eq!(format!("{}", err), result),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parser_error_recovery() {
+        message::set_locale(Locale::Ja);
+        let problem = [
+            (
+                "let = 5;\nlet x = 10;\nlet y 6;\nreturn x;",
+                vec![
+                    "1:5: (Identifierを期待しましたが、Assignでした。)",
+                    "3:7: (Assignを期待しましたが、Integer(6)でした。)",
+                ],
+                "let x = 10;\nreturn x;\n",
+            ),
+            (
+                "let a = 1 let b = ;\nlet c = 3;",
+                vec!["1:19: (式のパーサーが未実装です。)"],
+                "let a = 1;\nlet c = 3;\n",
+            ),
+            (
+                "let f = fn(x) { let = 1; x };\nf(1",
+                vec![
+                    "1:21: (Identifierを期待しましたが、Assignでした。)",
+                    "2:4: (Rparenthesesを期待しましたが、EndOfFileでした。)",
+                ],
+                "let f = fn(x){\nx;\n};\n",
+            ),
+            ("let x = 1; x", vec![], "let x = 1;\nx;\n"),
+        ];
+
+        for (input, errors, code) in problem {
+            let lexer = lexer::Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let (program, actual) = parser.parse_program_with_errors();
+            let actual: Vec<String> = actual.iter().map(|err| err.to_string()).collect();
+            assert_eq!(actual, errors, "{}", input);
+            assert_eq!(program.to_code(), code, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_string_expression() {
+        let input = r#""hello\tworld";"#;
+
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(err) => panic!("エラー: {}", err),
+        };
+
+        assert_eq!(program.statements.len(), 1);
+
+        let expression = test_expression_statement(&program.statements[0]);
+
+        if let ast::ExpressionKind::String(string) = &expression.kind {
+            assert_eq!(string, "hello\tworld");
+        } else {
+            panic!(
+                "expected ast::ExpressionKind::String, but got {:?}",
+                expression
+            );
+        }
+
+        assert_eq!(program.to_code(), "\"hello\\tworld\";\n");
+    }
+
+    #[test]
+    fn test_array_expression() {
+        let problem = [
+            ("[]", 0, "[];\n"),
+            ("[1, 2 * 2, 3 + 3]", 3, "[1, (2 * 2), (3 + 3)];\n"),
+            (r#"[[1], "a"]"#, 2, "[[1], \"a\"];\n"),
+        ];
+
+        for (input, length, result) in problem {
+            let lexer = lexer::Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let program = match parser.parse_program() {
+                Ok(program) => program,
+                Err(err) => panic!("エラー: {}", err),
+            };
+
+            let expression = test_expression_statement(&program.statements[0]);
+
+            if let ast::ExpressionKind::Array(elements) = &expression.kind {
+                assert_eq!(elements.len(), length);
+            } else {
+                panic!(
+                    "expected ast::ExpressionKind::Array, but got {:?}",
+                    expression
+                );
+            }
+
+            assert_eq!(program.to_code(), result);
+        }
+    }
+
+    #[test]
+    fn test_index_expression() {
+        let input = "myArray[1 + 1]";
+
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        let program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(err) => panic!("エラー: {}", err),
+        };
+
+        let expression = test_expression_statement(&program.statements[0]);
+
+        if let ast::ExpressionKind::Index { left, index } = &expression.kind {
+            test_identifier_literal(left, "myArray");
+            assert_eq!(index.to_code(), "(1 + 1)");
+        } else {
+            panic!(
+                "expected ast::ExpressionKind::Index, but got {:?}",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_hash_expression() {
+        message::set_locale(Locale::Ja);
+        let problem = [
+            ("{}", 0, "{};\n"),
+            (
+                r#"{"one": 1, "two": 2, "three": 3}"#,
+                3,
+                "{\"one\": 1, \"two\": 2, \"three\": 3};\n",
+            ),
+            (
+                r#"{"one": 0 + 1, true: 10 - 8, 3: 15 / 5,}"#,
+                3,
+                "{\"one\": (0 + 1), true: (10 - 8), 3: (15 / 5)};\n",
+            ),
+        ];
+
+        for (input, length, result) in problem {
+            let lexer = lexer::Lexer::new(input);
+            let mut parser = Parser::new(lexer);
+            let program = match parser.parse_program() {
+                Ok(program) => program,
+                Err(err) => panic!("エラー: {}", err),
+            };
+
+            let expression = test_expression_statement(&program.statements[0]);
+
+            if let ast::ExpressionKind::Hash(pairs) = &expression.kind {
+                assert_eq!(pairs.len(), length);
+            } else {
+                panic!(
+                    "expected ast::ExpressionKind::Hash, but got {:?}",
+                    expression
+                );
+            }
+
+            assert_eq!(program.to_code(), result);
+        }
+
+        let lexer = lexer::Lexer::new(r#"{"one" 1}"#);
+        let mut parser = Parser::new(lexer);
+        match parser.parse_program() {
+            Ok(program) => panic!("エラーを期待しましたが、{}でした。", program.to_code()),
+            Err(err) => assert_eq!(
+                format!("{}", err),
+                "1:8: (Colonを期待しましたが、Integer(1)でした。)"
+            ),
+        }
+    }
 }
@@ -0,0 +1,457 @@
+use crate::builtin;
+use crate::compiler;
+use crate::diagnostic;
+use crate::error;
+use crate::interpreter;
+use crate::lexer;
+use crate::line_editor;
+use crate::parser;
+use crate::token;
+
+pub const PROMPT: &str = ">> ";
+pub const CONTINUATION_PROMPT: &str = ".. ";
+
+/// 補完の候補にするコマンド名
+const COMMANDS: [&str; 9] = [
+    ":tokens",
+    ":ast",
+    ":bytecode",
+    ":type",
+    ":env",
+    ":load",
+    ":reset",
+    ":help",
+    ":quit",
+];
+
+const HELP: &str = "\
+:tokens <コード>    字句解析の結果を表示する
+:ast <コード>       構文木を表示する
+:bytecode <コード>  バイトコードを逆アセンブルして表示する
+:type <コード>      評価結果の型を表示する
+:env                現在の束縛を表示する
+:load <ファイル>    ファイルを評価して束縛をセッションに取り込む
+:reset              束縛を全て消す
+:help               この一覧を表示する
+:quit               終了する";
+
+/// 対話モード。入力が文の途中で終わっていれば続きの行を待ってから評価する
+///
+/// 入力の先頭が `:` の行は `:ast` や `:env` などのコマンドとして扱う。
+pub struct Repl {
+    interpreter: interpreter::Interpreter,
+    buffer: String,
+    color: bool,
+    quit: bool,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl {
+            interpreter: interpreter::Interpreter::new(),
+            buffer: String::new(),
+            color: false,
+            quit: false,
+        }
+    }
+
+    /// エラー表示に色を付ける
+    pub fn with_color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// 続きの行を待っている間は継続プロンプトになる
+    pub fn prompt(&self) -> &'static str {
+        if self.buffer.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        }
+    }
+
+    /// `:quit` が入力されたか
+    pub fn is_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// 1行を受け取り、入力が揃ったら評価して表示する文字列を返す
+    ///
+    /// 括弧の中でなければ、継続中の空行で入力を打ち切って評価する。
+    pub fn read_line(&mut self, line: &str) -> Option<String> {
+        if self.buffer.is_empty() && line.trim_start().starts_with(':') {
+            return self.command(line.trim());
+        }
+        let force =
+            !self.buffer.is_empty() && line.trim().is_empty() && !has_open_bracket(&self.buffer);
+        self.buffer.push_str(line);
+        if !self.buffer.ends_with('\n') {
+            self.buffer.push('\n');
+        }
+        if !force && is_incomplete(&self.buffer) {
+            return None;
+        }
+        self.finish()
+    }
+
+    /// 溜まっている入力を評価する (入力の終わりで呼ぶ)
+    pub fn finish(&mut self) -> Option<String> {
+        let input = std::mem::take(&mut self.buffer);
+        if input.trim().is_empty() {
+            return None;
+        }
+        match self.interpreter.eval_str(&input) {
+            Ok(evaluated) if evaluated.inspect().is_empty() => None,
+            Ok(evaluated) => Some(evaluated.inspect()),
+            Err(err) => Some(self.render_error(err.as_ref(), &input, None)),
+        }
+    }
+
+    /// `:` で始まるコマンドを実行する
+    fn command(&mut self, line: &str) -> Option<String> {
+        let (name, argument) = match line.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (line, ""),
+        };
+        match name {
+            ":tokens" => Some(dump_tokens(argument)),
+            ":ast" => {
+                let lexer = lexer::Lexer::new(argument);
+                let mut parser = parser::Parser::new(lexer);
+                match parser.parse_program() {
+                    Ok(program) => Some(program.to_tree().trim_end().to_string()),
+                    Err(err) => Some(self.render_error(err.as_ref(), argument, None)),
+                }
+            }
+            ":bytecode" => {
+                let lexer = lexer::Lexer::new(argument);
+                let mut parser = parser::Parser::new(lexer);
+                let bytecode = parser
+                    .parse_program()
+                    .and_then(|program| compiler::Compiler::new().compile(&program));
+                match bytecode {
+                    Ok(bytecode) => Some(bytecode.disassemble().trim_end().to_string()),
+                    Err(err) => Some(self.render_error(err.as_ref(), argument, None)),
+                }
+            }
+            ":type" => match self.interpreter.eval_str(argument) {
+                Ok(evaluated) => Some(evaluated.type_name().to_string()),
+                Err(err) => Some(self.render_error(err.as_ref(), argument, None)),
+            },
+            ":env" => {
+                let bindings = self
+                    .interpreter
+                    .globals()
+                    .into_iter()
+                    .map(|(name, value)| format!("{} = {}", name, value.inspect().trim_end()))
+                    .collect::<Vec<_>>();
+                if bindings.is_empty() {
+                    None
+                } else {
+                    Some(bindings.join("\n"))
+                }
+            }
+            ":load" if !argument.is_empty() => {
+                let source = match std::fs::read_to_string(argument) {
+                    Ok(source) => source,
+                    Err(err) => {
+                        return Some(format!("ファイルを読み込めません。: {}: {}", argument, err))
+                    }
+                };
+                match self.interpreter.eval_str(&source) {
+                    Ok(_) => None,
+                    Err(err) => Some(self.render_error(err.as_ref(), &source, Some(argument))),
+                }
+            }
+            ":reset" => {
+                self.interpreter = interpreter::Interpreter::new();
+                None
+            }
+            ":quit" | ":q" => {
+                self.quit = true;
+                None
+            }
+            ":help" => Some(HELP.to_string()),
+            _ => Some(format!("不明なコマンドです。: {}\n\n{}", line, HELP)),
+        }
+    }
+
+    fn render_error(
+        &self,
+        err: &(dyn std::error::Error + 'static),
+        source: &str,
+        file: Option<&str>,
+    ) -> String {
+        match diagnostic::Diagnostic::from_error(err) {
+            Some(diagnostic) => {
+                let mut renderer = diagnostic::Renderer::new(source).with_color(self.color);
+                if let Some(file) = file {
+                    renderer = renderer.with_file(file);
+                }
+                renderer.render(&diagnostic)
+            }
+            None => err.to_string(),
+        }
+    }
+
+    /// Tab で補完する候補 (予約語・組み込み関数・束縛済みの名前・コマンド名)
+    pub fn completion_candidates(&self) -> Vec<String> {
+        let mut candidates = lexer::KEYWORDS
+            .iter()
+            .map(|(keyword, _)| *keyword)
+            .chain(builtin::names())
+            .chain(COMMANDS.iter().copied())
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        candidates.extend(self.interpreter.globals().into_iter().map(|(name, _)| name));
+        candidates
+    }
+
+    /// 標準入力から読み、EOF (Ctrl-D) で終わる
+    ///
+    /// 端末では行編集・履歴 (ホームディレクトリに保存)・Ctrl-R の検索・Tab 補完が使える。
+    pub fn run(&mut self) {
+        let mut editor = line_editor::LineEditor::new();
+        if let Some(path) = line_editor::default_history_path() {
+            editor = editor.with_history_file(&path);
+        }
+        loop {
+            let line = editor
+                .read_line(self.prompt(), &self.completion_candidates())
+                .expect("Failed to read line.");
+            match line {
+                line_editor::ReadLine::Line(line) => {
+                    if let Some(output) = self.read_line(&line) {
+                        println!("{}", output);
+                    }
+                }
+                line_editor::ReadLine::Interrupted => self.buffer.clear(), // 入力途中の文を捨てる
+                line_editor::ReadLine::Eof => {
+                    if let Some(output) = self.finish() {
+                        println!("{}", output);
+                    }
+                    break;
+                }
+            }
+            if self.quit {
+                break;
+            }
+        }
+    }
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Repl::new()
+    }
+}
+
+/// 字句解析の結果を1行1トークンで表示する (コメントも含める)
+fn dump_tokens(input: &str) -> String {
+    let mut lexer = lexer::Lexer::with_comments(input);
+    let mut lines = Vec::new();
+    loop {
+        let spanned = lexer.read_next_token();
+        if spanned.token == token::Token::EndOfFile {
+            break;
+        }
+        lines.push(format!("{} {:?}", spanned.span, spanned.token));
+    }
+    lines.join("\n")
+}
+
+/// 入力が文の途中で終わっていて、続きの行が必要か
+///
+/// 閉じていない括弧・ブロックコメント・文字列があるか、構文エラーが入力の終端で
+/// 起きている (`let x =` や `1 +` など) 場合に続きがあるとみなす。
+pub fn is_incomplete(input: &str) -> bool {
+    if has_open_bracket(input) {
+        return true;
+    }
+
+    let mut lexer = lexer::Lexer::new(input);
+    loop {
+        let spanned = lexer.read_next_token();
+        match spanned.token {
+            token::Token::EndOfFile => break,
+            token::Token::Error(
+                error::LexerError::UnterminatedBlockComment | error::LexerError::UnterminatedString,
+            ) => return true,
+            _ => {}
+        }
+    }
+
+    let lexer = lexer::Lexer::new(input);
+    let mut parser = parser::Parser::new(lexer);
+    let (_, errors) = parser.parse_program_with_errors();
+    errors
+        .iter()
+        .any(|error| error.span().start.offset >= input.len())
+}
+
+/// `(` `{` `[` が閉じられていないか
+fn has_open_bracket(input: &str) -> bool {
+    let mut lexer = lexer::Lexer::new(input);
+    let mut depth: i64 = 0;
+    loop {
+        match lexer.read_next_token().token {
+            token::Token::Lparentheses | token::Token::Lbrace | token::Token::Lbracket => {
+                depth += 1
+            }
+            token::Token::Rparentheses | token::Token::Rbrace | token::Token::Rbracket => {
+                depth -= 1
+            }
+            token::Token::EndOfFile => return depth > 0,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_incomplete() {
+        let problem = [
+            ("let x = 5;", false),
+            ("let add = fn(a, b) {", true),
+            ("let add = fn(a, b) {\n  a + b\n};", false),
+            ("if (x > 1", true),
+            ("[1, 2,", true),
+            ("{\"a\":", true),
+            ("let x =", true),
+            ("1 +", true),
+            ("let", true),
+            ("if (true) { 1 } else", true),
+            ("/* コメント", true),
+            ("\"複数行の\n文字列", true),
+            ("\"\\q", true),
+            ("\"\\q\";", false),
+            ("let = 5;", false),
+            ("1 + )", false),
+            ("}", false),
+            ("", false),
+        ];
+
+        for (input, expected) in problem {
+            assert_eq!(is_incomplete(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_read_line() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.prompt(), PROMPT);
+
+        assert_eq!(repl.read_line("let add = fn(a, b) {\n"), None);
+        assert_eq!(repl.prompt(), CONTINUATION_PROMPT);
+        assert_eq!(repl.read_line("\n"), None); // 括弧の中の空行は続ける
+        assert_eq!(repl.read_line("  a + b\n"), None);
+        assert_eq!(repl.read_line("};\n"), None);
+        assert_eq!(repl.prompt(), PROMPT);
+
+        assert_eq!(repl.read_line("add(1,\n"), None);
+        assert_eq!(repl.read_line("2)\n"), Some("3".to_string()));
+    }
+
+    #[test]
+    fn test_read_line_blank_line_submits() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.read_line("let x =\n"), None);
+        let output = repl.read_line("\n").expect("エラー表示");
+        assert!(output.starts_with("error[P0006]"), "{}", output);
+        assert_eq!(repl.prompt(), PROMPT);
+    }
+
+    #[test]
+    fn test_command() {
+        let mut repl = Repl::new();
+        assert_eq!(
+            repl.read_line(":tokens let x = 1; // c\n"),
+            Some(
+                "1:1 Let\n1:5 Identifier(\"x\")\n1:7 Assign\n1:9 Integer(1)\n1:10 Semicolon\n1:12 Comment(\"// c\")"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            repl.read_line(":ast -a\n"),
+            Some("ExpressionStatement 1:1\n  Prefix - 1:1\n    Identifier a 1:2".to_string())
+        );
+        assert_eq!(
+            repl.read_line(":bytecode -a\n"),
+            Some(
+                "== main ==\n0000 GetGlobal      0      (a)\n0003 Minus\n0004 ReturnValue"
+                    .to_string()
+            )
+        );
+        assert_eq!(repl.read_line(":type 1 + 1.5\n"), Some("float".to_string()));
+        assert_eq!(repl.read_line(":type len\n"), Some("builtin".to_string()));
+
+        assert_eq!(repl.read_line(":env\n"), None);
+        assert_eq!(repl.read_line("let b = [1];\n"), None);
+        assert_eq!(repl.read_line("let a = \"x\";\n"), None);
+        assert_eq!(
+            repl.read_line(":env\n"),
+            Some("a = \"x\"\nb = [1]".to_string())
+        );
+
+        assert_eq!(repl.read_line(":reset\n"), None);
+        assert_eq!(repl.read_line(":env\n"), None);
+
+        let output = repl.read_line(":ast let = 1\n").expect("エラー表示");
+        assert!(output.starts_with("error[P0003]"), "{}", output);
+        let output = repl.read_line(":foo\n").expect("エラー表示");
+        assert!(
+            output.starts_with("不明なコマンドです。: :foo"),
+            "{}",
+            output
+        );
+
+        assert!(!repl.is_quit());
+        assert_eq!(repl.read_line(":quit\n"), None);
+        assert!(repl.is_quit());
+    }
+
+    #[test]
+    fn test_completion_candidates() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.read_line("let counter = 1;\n"), None);
+        let candidates = repl.completion_candidates();
+        for name in ["let", "return", "len", "push", ":load", "counter"] {
+            assert!(candidates.contains(&name.to_string()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_command_load() {
+        let path = std::env::temp_dir().join(format!("repl_load_{}.monkey", std::process::id()));
+        std::fs::write(
+            &path,
+            "let double = fn(x) { x * 2 };\nlet y = double(21);\n",
+        )
+        .expect("一時ファイル");
+
+        let mut repl = Repl::new();
+        let command = format!(":load {}\n", path.display());
+        assert_eq!(repl.read_line(&command), None);
+        assert_eq!(repl.read_line("y\n"), Some("42".to_string()));
+        std::fs::remove_file(&path).expect("一時ファイル");
+
+        let output = repl.read_line(&command).expect("エラー表示");
+        assert!(
+            output.starts_with("ファイルを読み込めません。"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_finish() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.read_line("[1, 2\n"), None);
+        let output = repl.finish().expect("エラー表示");
+        assert!(output.starts_with("error[P0001]"), "{}", output);
+        assert_eq!(repl.finish(), None);
+    }
+}
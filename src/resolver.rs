@@ -0,0 +1,449 @@
+use crate::ast;
+use crate::builtin;
+use crate::environment;
+use crate::error;
+use std::rc::Rc;
+
+/// 評価の前に、識別子が指す変数の場所 (何個外側の環境の何番目のスロットか) を求める
+///
+/// スコープはグローバルと関数ごとに1つ (ブロックはスコープを作らない)。スコープ内の
+/// let と引数は、文の位置によらずスコープ全体で同じスロットを指す。束縛より前に
+/// 読んだ場合は、実行時に外側の同じ名前の変数を探すので、静的解決しない場合と同じ結果になる。
+///
+/// どのスコープにも組み込み関数にも無い名前を参照していればエラーにする
+/// (実行されない分岐の中でも)。グローバルの新しい名前は environment にスロットを用意する。
+pub fn resolve(
+    program: &mut ast::Program,
+    environment: &mut environment::Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
+    resolve_program(program, environment, true)
+}
+
+/// `resolve` と同じだが、見つからない名前はエラーにせず未解決のまま残す
+///
+/// REPL のように入力を続けて評価する場合に使う。未解決の名前は実行時に名前で探すので、
+/// 後の入力で定義する名前を関数の中から参照できる。
+pub fn resolve_incremental(
+    program: &mut ast::Program,
+    environment: &mut environment::Environment,
+) -> Result<(), Box<dyn std::error::Error>> {
+    resolve_program(program, environment, false)
+}
+
+fn resolve_program(
+    program: &mut ast::Program,
+    environment: &mut environment::Environment,
+    strict: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut declared = Vec::new();
+    declare_statements(&program.statements, &mut declared);
+    for name in declared {
+        environment.declare(name);
+    }
+
+    let mut resolver = Resolver {
+        scopes: vec![environment::SlotNames::new(environment.names().to_vec())],
+        strict,
+    };
+    for statement in &mut program.statements {
+        resolver.resolve_statement(statement)?;
+    }
+    program.globals = Some(environment.names().to_vec());
+    Ok(())
+}
+
+struct Resolver {
+    scopes: Vec<environment::SlotNames>, // 最後が最も内側
+    strict: bool,                        // 見つからない名前をエラーにするか
+}
+
+impl Resolver {
+    fn resolve_statement(
+        &mut self,
+        statement: &mut ast::Statement,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match &mut statement.kind {
+            ast::StatementKind::Let { identifier, value } => {
+                self.resolve_expression(value)?;
+                self.resolve_binding(identifier);
+            }
+            ast::StatementKind::Return(expression) | ast::StatementKind::Expression(expression) => {
+                self.resolve_expression(expression)?
+            }
+            ast::StatementKind::Block(statements) => {
+                for statement in statements {
+                    self.resolve_statement(statement)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_expression(
+        &mut self,
+        expression: &mut ast::Expression,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match &mut expression.kind {
+            ast::ExpressionKind::Identifier(name, resolution) => {
+                *resolution = self.lookup(name);
+                if self.strict && resolution.is_none() && builtin::lookup(name).is_none() {
+                    return Err(error::EvaluatorError::NotFoundIdentifier {
+                        identifier: name.clone(),
+                        span: expression.span,
+                    })?;
+                }
+            }
+            ast::ExpressionKind::PrefixExpression { expression, .. } => {
+                self.resolve_expression(expression)?
+            }
+            ast::ExpressionKind::InfixExpression { left, right, .. } => {
+                self.resolve_expression(left)?;
+                self.resolve_expression(right)?;
+            }
+            ast::ExpressionKind::IfExpression {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.resolve_expression(condition)?;
+                self.resolve_statement(consequence)?;
+                if let Some(alternative) = alternative {
+                    self.resolve_statement(alternative)?;
+                }
+            }
+            ast::ExpressionKind::Function {
+                parameters,
+                body,
+                slots,
+                ..
+            } => {
+                let mut names = parameters
+                    .iter()
+                    .map(|parameter| match &parameter.kind {
+                        ast::ExpressionKind::Identifier(name, _) => name.clone(),
+                        _ => unreachable!(),
+                    })
+                    .collect::<Vec<_>>();
+                declare_statement(body, &mut names);
+                let scope = environment::SlotNames::with_fallbacks(names, |name| self.lookup(name));
+                self.scopes.push(scope);
+                for parameter in parameters.iter_mut() {
+                    self.resolve_binding(parameter);
+                }
+                let resolved = self.resolve_statement(body);
+                let scope = self.scopes.pop().expect("関数のスコープがありません。");
+                resolved?;
+                *slots = Some(Rc::new(scope));
+            }
+            ast::ExpressionKind::Call { function, args } => {
+                self.resolve_expression(function)?;
+                for arg in args {
+                    self.resolve_expression(arg)?;
+                }
+            }
+            ast::ExpressionKind::Array(elements) => {
+                for element in elements {
+                    self.resolve_expression(element)?;
+                }
+            }
+            ast::ExpressionKind::Hash(pairs) => {
+                for (key, value) in pairs {
+                    self.resolve_expression(key)?;
+                    self.resolve_expression(value)?;
+                }
+            }
+            ast::ExpressionKind::Index { left, index } => {
+                self.resolve_expression(left)?;
+                self.resolve_expression(index)?;
+            }
+            ast::ExpressionKind::Integer(_)
+            | ast::ExpressionKind::BigInt(_)
+            | ast::ExpressionKind::Float(_)
+            | ast::ExpressionKind::String(_)
+            | ast::ExpressionKind::Boolean(_)
+            | ast::ExpressionKind::Illegal => {}
+        }
+        Ok(())
+    }
+
+    /// let や引数の識別子は、最も内側のスコープのスロットを指す
+    fn resolve_binding(&mut self, identifier: &mut ast::Expression) {
+        if let ast::ExpressionKind::Identifier(name, resolution) = &mut identifier.kind {
+            let scope = self.scopes.last().expect("スコープがありません。");
+            *resolution = Some(ast::Resolution {
+                depth: 0,
+                slot: scope.lookup(name).expect("宣言されていない束縛です。"),
+            });
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<ast::Resolution> {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, scope)| {
+                scope
+                    .lookup(name)
+                    .map(|slot| ast::Resolution { depth, slot })
+            })
+    }
+}
+
+/// 関数の本体で let により宣言される名前 (関数リテラルの中は含まない)
+pub(crate) fn declared_names(body: &ast::Statement) -> Vec<String> {
+    let mut names = Vec::new();
+    declare_statement(body, &mut names);
+    names
+}
+
+/// 文の中の let で宣言される名前を集める (関数リテラルの中は別のスコープなので含まない)
+fn declare_statements(statements: &[ast::Statement], names: &mut Vec<String>) {
+    for statement in statements {
+        declare_statement(statement, names);
+    }
+}
+
+fn declare_statement(statement: &ast::Statement, names: &mut Vec<String>) {
+    match &statement.kind {
+        ast::StatementKind::Let { identifier, value } => {
+            declare_expression(value, names);
+            if let ast::ExpressionKind::Identifier(name, _) = &identifier.kind {
+                names.push(name.clone());
+            }
+        }
+        ast::StatementKind::Return(expression) | ast::StatementKind::Expression(expression) => {
+            declare_expression(expression, names)
+        }
+        ast::StatementKind::Block(statements) => declare_statements(statements, names),
+    }
+}
+
+fn declare_expression(expression: &ast::Expression, names: &mut Vec<String>) {
+    match &expression.kind {
+        ast::ExpressionKind::PrefixExpression { expression, .. } => {
+            declare_expression(expression, names)
+        }
+        ast::ExpressionKind::InfixExpression { left, right, .. } => {
+            declare_expression(left, names);
+            declare_expression(right, names);
+        }
+        ast::ExpressionKind::IfExpression {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            declare_expression(condition, names);
+            declare_statement(consequence, names);
+            if let Some(alternative) = alternative {
+                declare_statement(alternative, names);
+            }
+        }
+        ast::ExpressionKind::Call { function, args } => {
+            declare_expression(function, names);
+            for arg in args {
+                declare_expression(arg, names);
+            }
+        }
+        ast::ExpressionKind::Array(elements) => {
+            for element in elements {
+                declare_expression(element, names);
+            }
+        }
+        ast::ExpressionKind::Hash(pairs) => {
+            for (key, value) in pairs {
+                declare_expression(key, names);
+                declare_expression(value, names);
+            }
+        }
+        ast::ExpressionKind::Index { left, index } => {
+            declare_expression(left, names);
+            declare_expression(index, names);
+        }
+        ast::ExpressionKind::Function { .. }
+        | ast::ExpressionKind::Identifier(..)
+        | ast::ExpressionKind::Integer(_)
+        | ast::ExpressionKind::BigInt(_)
+        | ast::ExpressionKind::Float(_)
+        | ast::ExpressionKind::String(_)
+        | ast::ExpressionKind::Boolean(_)
+        | ast::ExpressionKind::Illegal => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator;
+    use crate::lexer;
+    use crate::message::{self, Locale};
+    use crate::parser;
+    use std::{cell::RefCell, rc::Rc};
+
+    fn parse(input: &str) -> ast::Program {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = parser::Parser::new(lexer);
+        parser.parse_program().expect("parser error")
+    }
+
+    /// 参照している識別子の (名前, depth, slot) を出現順に集める
+    fn references(
+        statements: &[ast::Statement],
+        found: &mut Vec<(String, Option<(usize, usize)>)>,
+    ) {
+        fn walk(expression: &ast::Expression, found: &mut Vec<(String, Option<(usize, usize)>)>) {
+            match &expression.kind {
+                ast::ExpressionKind::Identifier(name, resolution) => found.push((
+                    name.clone(),
+                    resolution.map(|resolution| (resolution.depth, resolution.slot)),
+                )),
+                ast::ExpressionKind::InfixExpression { left, right, .. } => {
+                    walk(left, found);
+                    walk(right, found);
+                }
+                ast::ExpressionKind::Function { body, .. } => {
+                    references(std::slice::from_ref(body.as_ref()), found)
+                }
+                ast::ExpressionKind::Call { function, args } => {
+                    walk(function, found);
+                    args.iter().for_each(|arg| walk(arg, found));
+                }
+                _ => {}
+            }
+        }
+        for statement in statements {
+            match &statement.kind {
+                ast::StatementKind::Let { value, .. } => walk(value, found),
+                ast::StatementKind::Return(expression)
+                | ast::StatementKind::Expression(expression) => walk(expression, found),
+                ast::StatementKind::Block(statements) => references(statements, found),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut program = parse(
+            "let a = 1;
+             let f = fn(x) { let y = x; fn(z) { a + y + z + g() } };
+             let g = fn() { len };",
+        );
+        let mut environment = environment::Environment::new();
+        resolve(&mut program, &mut environment).expect("resolver error");
+        assert_eq!(environment.names(), ["a", "f", "g"]);
+
+        let mut found = Vec::new();
+        references(&program.statements, &mut found);
+        let expected = [
+            ("x", Some((0, 0))),
+            ("a", Some((2, 0))),
+            ("y", Some((1, 1))),
+            ("z", Some((0, 0))),
+            ("g", Some((2, 2))),
+            ("len", None),
+        ];
+        let expected = expected
+            .iter()
+            .map(|(name, resolution)| (name.to_string(), *resolution))
+            .collect::<Vec<_>>();
+        assert_eq!(found, expected);
+
+        if let ast::StatementKind::Let { value, .. } = &program.statements[1].kind {
+            if let ast::ExpressionKind::Function { slots, .. } = &value.kind {
+                assert_eq!(
+                    slots.as_ref().map(|slots| slots.names()),
+                    Some(&["x".to_string(), "y".to_string()][..])
+                );
+                return;
+            }
+        }
+        panic!("関数リテラルがありません。");
+    }
+
+    #[test]
+    fn test_resolve_error() {
+        message::set_locale(Locale::Ja);
+        let problem = [
+            ("x", "1:1: 識別子が見つかりません。: x"),
+            (
+                "let a = 1;\nif (false) { a + b }",
+                "2:18: 識別子が見つかりません。: b",
+            ),
+            (
+                "let f = fn(x) { y }; let y = 1;\n f(z)",
+                "2:4: 識別子が見つかりません。: z",
+            ),
+            (
+                "fn() { let x = 1; }; x",
+                "1:22: 識別子が見つかりません。: x",
+            ),
+        ];
+
+        for (input, expected) in problem {
+            let mut program = parse(input);
+            match resolve(&mut program, &mut environment::Environment::new()) {
+                Ok(()) => panic!("{}: エラーを期待しましたが、成功しました。", input),
+                Err(err) => assert_eq!(err.to_string(), expected, "{}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_incremental() {
+        let mut program = parse("let f = fn(x) { g(x) + y }; let y = 1; missing");
+        let mut environment = environment::Environment::new();
+        resolve_incremental(&mut program, &mut environment).expect("resolver error");
+        assert_eq!(environment.names(), ["f", "y"]);
+
+        let mut found = Vec::new();
+        references(&program.statements, &mut found);
+        let expected = [
+            ("g", None),
+            ("x", Some((0, 0))),
+            ("y", Some((1, 1))),
+            ("missing", None),
+        ];
+        let expected = expected
+            .iter()
+            .map(|(name, resolution)| (name.to_string(), *resolution))
+            .collect::<Vec<_>>();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_resolve_same_result() {
+        // 束縛より前の参照や条件付きの let は、名前で探したときと同じ値・エラーになる
+        let problem = [
+            "let x = 1; let f = fn() { let y = x; let x = 2; [y, x] }; f()",
+            "let f = fn(c) { if (c) { let v = 1; }; v }; f(true)",
+            "let f = fn(c) { if (c) { let v = 1; }; v }; f(false)",
+            "let v = 5; let f = fn(c) { if (c) { let v = 1; }; v }; [f(false), f(true)]",
+            "let f = fn() { g() }; let g = fn() { 3 }; f()",
+            "let f = fn() { g() }; f(); let g = fn() { 3 };",
+            "let len = fn(x) { 0 }; len([1])",
+            "let a = len([1]); let len = 1; [a, len]",
+            "let f = fn(a, a) { a }; f(1, 2)",
+            "let counter = fn(n) { fn() { n } }; let c = counter(7); c()",
+        ];
+
+        for input in problem {
+            let program = parse(input);
+            let expected = evaluator::Evaluator::eval(
+                &program,
+                &mut Rc::new(RefCell::new(environment::Environment::new())),
+            );
+            let mut resolved = program.clone();
+            let mut environment = environment::Environment::new();
+            resolve(&mut resolved, &mut environment).expect("resolver error");
+            let actual =
+                evaluator::Evaluator::eval(&resolved, &mut Rc::new(RefCell::new(environment)));
+            match (expected, actual) {
+                (Ok(expected), Ok(actual)) => assert_eq!(actual, expected, "{}", input),
+                (Err(expected), Err(actual)) => {
+                    assert_eq!(actual.to_string(), expected.to_string(), "{}", input)
+                }
+                (expected, actual) => panic!("{}: {:?} != {:?}", input, expected, actual),
+            }
+        }
+    }
+}
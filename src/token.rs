@@ -1,34 +1,66 @@
+use crate::bigint;
+use crate::error;
 use crate::operator;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
-    Illegal,            // 不正トークン
-    EndOfFile,          // ファイルの終端
-    Identifier(String), // 識別子 (x, y, test など)
-    Integer(i32),       // 数値 (0, 1000 など )
-    Assign,             // =
-    Plus,               // +
-    Minus,              // -
-    Exclamation,        // !
-    Asterisk,           // *
-    Slash,              // /
-    LessThan,           // <
-    GreaterThan,        // >
-    Equal,              // ==
-    NotEqual,           // !=
-    Comma,              // ,
-    Semicolon,          // ;
-    Lparentheses,       // (
-    Rparentheses,       // )
-    Lbrace,             // {
-    Rbrace,             // }
-    Function,           // fn
-    Let,                // let
-    True,               // true
-    False,              // false
-    If,                 // if
-    Else,               // else
-    Return,             // return
+    Illegal,                  // 不正トークン
+    Error(error::LexerError), // 字句解析エラー
+    Comment(String),          // コメント (// ..., /* ... */)
+    EndOfFile,                // ファイルの終端
+    Identifier(String),       // 識別子 (x, y, test など)
+    Integer(i64),             // 数値 (0, 1000 など )
+    BigInt(bigint::BigInt),   // i64 に収まらない整数 (99999999999999999999 など)
+    Float(f64),               // 浮動小数点数 (3.14, 1e-9 など)
+    String(String),           // 文字列 ("hello" など)
+    Assign,                   // =
+    Plus,                     // +
+    Minus,                    // -
+    Exclamation,              // !
+    Asterisk,                 // *
+    Slash,                    // /
+    LessThan,                 // <
+    GreaterThan,              // >
+    Equal,                    // ==
+    NotEqual,                 // !=
+    Comma,                    // ,
+    Colon,                    // :
+    Semicolon,                // ;
+    Lparentheses,             // (
+    Rparentheses,             // )
+    Lbrace,                   // {
+    Rbrace,                   // }
+    Lbracket,                 // [
+    Rbracket,                 // ]
+    Function,                 // fn
+    Let,                      // let
+    True,                     // true
+    False,                    // false
+    If,                       // if
+    Else,                     // else
+    Return,                   // return
+}
+
+/// ソース中の位置 (line, column は 1 始まり、offset はバイト単位で 0 始まり)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// ソース中の範囲 (start を含み、end を含まない)
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// 位置情報付きのトークン
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
 }
 
 impl Token {
@@ -39,11 +71,77 @@ impl Token {
             Token::Plus | Token::Minus => operator::Precedences::Sum,
             Token::Slash | Token::Asterisk => operator::Precedences::Product,
             Token::Lparentheses => operator::Precedences::Call,
+            Token::Lbracket => operator::Precedences::Index,
             _ => operator::Precedences::Lowest,
         }
     }
 }
 
+impl Position {
+    pub fn new() -> Self {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// ch を読み進めた後の位置を返す
+    pub fn advance(&self, ch: char) -> Position {
+        if ch == '\n' {
+            Position {
+                offset: self.offset + ch.len_utf8(),
+                line: self.line + 1,
+                column: 1,
+            }
+        } else {
+            Position {
+                offset: self.offset + ch.len_utf8(),
+                line: self.line,
+                column: self.column + 1,
+            }
+        }
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Position::new()
+    }
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Span { start, end }
+    }
+
+    /// self の先頭から other の末尾までの範囲を返す
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Span::new(Position::new(), Position::new())
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,14 +157,51 @@ mod tests {
         let equal = Token::Equal.precedence();
         let not_equal = Token::NotEqual.precedence();
         let identifier = Token::Identifier("test".to_string()).precedence();
+        let lparentheses = Token::Lparentheses.precedence();
+        let lbracket = Token::Lbracket.precedence();
+
+        assert!(plus == minus);
+        assert!(minus < asterisk);
+        assert!(asterisk == slash);
+        assert!(slash > less_than);
+        assert!(less_than == greater_than);
+        assert!(greater_than > equal);
+        assert!(equal == not_equal);
+        assert!(not_equal > identifier);
+        assert!(lbracket > lparentheses);
+    }
+
+    #[test]
+    fn test_position_advance() {
+        let position = Position::new().advance('a');
+        assert_eq!(
+            position,
+            Position {
+                offset: 1,
+                line: 1,
+                column: 2
+            }
+        );
+
+        let position = position.advance('\n');
+        assert_eq!(
+            position,
+            Position {
+                offset: 2,
+                line: 2,
+                column: 1
+            }
+        );
 
-        assert_eq!(plus == minus, true);
-        assert_eq!(minus < asterisk, true);
-        assert_eq!(asterisk == slash, true);
-        assert_eq!(slash > less_than, true);
-        assert_eq!(less_than == greater_than, true);
-        assert_eq!(greater_than > equal, true);
-        assert_eq!(equal == not_equal, true);
-        assert_eq!(not_equal > identifier, true);
+        let position = position.advance('あ');
+        assert_eq!(
+            position,
+            Position {
+                offset: 5,
+                line: 2,
+                column: 2
+            }
+        );
+        assert_eq!(position.to_string(), "2:2");
     }
 }
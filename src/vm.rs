@@ -0,0 +1,677 @@
+use crate::ast;
+use crate::builtin;
+use crate::code::{self, Opcode};
+use crate::compiler;
+use crate::error;
+use crate::evaluator;
+use crate::object;
+use crate::operator;
+use crate::token;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// 関数と、作成時に捕まえた変数
+#[derive(Debug, PartialEq)]
+pub struct Closure {
+    pub function: Rc<code::CompiledFunction>,
+    pub free: Vec<Rc<RefCell<Option<object::Object>>>>, // 作った関数と共有する (None は未束縛)
+}
+
+/// 実行中の関数のローカル変数
+enum Local {
+    Unbound,
+    Value(object::Object),
+    Captured(Rc<RefCell<Option<object::Object>>>), // クロージャに捕まえられた変数
+}
+
+/// 呼び出し元に戻るための情報
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    base: usize,       // ローカル変数の先頭の位置
+    stack_base: usize, // 呼び出した時点のスタックの深さ (呼び出した関数と引数は除く)
+}
+
+/// 実行中の関数と命令の位置 (エラーになった命令を、実行を抜けた後で調べるため)
+struct Location {
+    function: Rc<code::CompiledFunction>,
+    start: usize,
+}
+
+/// バイトコードをオペランドスタックとコールフレームで実行する
+///
+/// 結果とエラーは `evaluator::Evaluator::eval` と同じになる。グローバル変数は実行をまたいで残る。
+/// クロージャは変数を参照で捕まえるので、後から束縛し直した値が見える。
+pub struct Vm {
+    globals: Vec<Option<object::Object>>,
+    stack: Vec<object::Object>,
+    locals: Vec<Local>,
+    frames: Vec<Frame>,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Vm {
+            globals: Vec::new(),
+            stack: Vec::new(),
+            locals: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn run(
+        &mut self,
+        bytecode: &code::Bytecode,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        if self.globals.len() < bytecode.globals.len() {
+            self.globals.resize(bytecode.globals.len(), None);
+        }
+        let mut at = Location {
+            function: bytecode.main.clone(),
+            start: 0,
+        };
+        let result = self
+            .execute(bytecode, &mut at)
+            .map_err(|err| -> Box<dyn std::error::Error> {
+                // 位置の表に無い命令のエラーは、作りものの位置を示さないよう位置の無いエラーにする
+                match (
+                    at.function.span_at(at.start),
+                    err.downcast::<error::EvaluatorError>(),
+                ) {
+                    (None, Ok(err)) => Box::new(error::UnlocatedError(*err)),
+                    (_, Ok(err)) => err,
+                    (_, Err(err)) => err,
+                }
+            });
+        self.stack.clear();
+        self.locals.clear();
+        self.frames.clear();
+        result
+    }
+
+    fn execute(
+        &mut self,
+        bytecode: &code::Bytecode,
+        at: &mut Location,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        let mut closure = Rc::new(Closure {
+            function: bytecode.main.clone(),
+            free: Vec::new(),
+        });
+        let mut ip = 0;
+        let mut base = 0;
+        let mut stack_base = 0;
+
+        loop {
+            let start = ip;
+            at.start = start;
+            let opcode = Opcode::from_byte(at.function.instructions[ip]).expect("不正な命令です");
+            ip += 1;
+            let mut operand = |width: usize| {
+                let operand = code::read_operand(&at.function.instructions, ip, width);
+                ip += width;
+                operand
+            };
+
+            match opcode {
+                Opcode::Constant => {
+                    let constant = match &bytecode.constants[operand(2)] {
+                        code::Constant::Integer(integer) => object::Object::Integer(*integer),
+                        code::Constant::BigInt(bigint) => object::Object::BigInt(bigint.clone()),
+                        code::Constant::Float(float) => object::Object::Float(*float),
+                        code::Constant::String(string) => object::Object::String(string.clone()),
+                        code::Constant::Function(function) => {
+                            object::Object::Closure(Rc::new(Closure {
+                                function: function.clone(),
+                                free: Vec::new(),
+                            }))
+                        }
+                    };
+                    self.stack.push(constant);
+                }
+                Opcode::Pop => {
+                    self.pop();
+                }
+                Opcode::Null => self.stack.push(object::Object::Null),
+                Opcode::True => self.stack.push(object::Object::Boolean(true)),
+                Opcode::False => self.stack.push(object::Object::Boolean(false)),
+                Opcode::Add
+                | Opcode::Sub
+                | Opcode::Mul
+                | Opcode::Div
+                | Opcode::Equal
+                | Opcode::NotEqual
+                | Opcode::GreaterThan
+                | Opcode::LessThan => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let result = match (&left, &right) {
+                        (object::Object::Integer(left), object::Object::Integer(right)) => {
+                            integer_operation(opcode, *left, *right)
+                        }
+                        _ => None,
+                    };
+                    // 整数同士で済まない場合 (桁あふれ・ゼロ除算・他の型) は評価器と同じ処理にする
+                    let result = match result {
+                        Some(result) => result,
+                        None => evaluator::Evaluator::eval_infix_expression(
+                            &left,
+                            infix_operator(opcode),
+                            &right,
+                            span_at(&at.function, start),
+                        )?,
+                    };
+                    self.stack.push(result);
+                }
+                Opcode::Minus | Opcode::Bang => {
+                    let right = self.pop();
+                    let operator = match opcode {
+                        Opcode::Minus => operator::Prefix::Minus,
+                        _ => operator::Prefix::Exclamation,
+                    };
+                    let result = evaluator::Evaluator::eval_prefix_expression(
+                        operator,
+                        &right,
+                        span_at(&at.function, start),
+                    )?;
+                    self.stack.push(result);
+                }
+                Opcode::Jump => ip = operand(2),
+                Opcode::JumpIfFalse => {
+                    let target = operand(2);
+                    if !self.pop().is_truthly() {
+                        ip = target;
+                    }
+                }
+                Opcode::GetGlobal => {
+                    let index = operand(2);
+                    let value = match &self.globals[index] {
+                        Some(value) => value.clone(),
+                        None => {
+                            let variable = code::Variable::Global(index);
+                            self.load(variable, &closure, base, bytecode, start)?
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                Opcode::SetGlobal => {
+                    let index = operand(2);
+                    self.globals[index] = Some(self.pop());
+                }
+                Opcode::GetLocal => {
+                    let index = operand(1);
+                    let value = match &self.locals[base + index] {
+                        Local::Value(value) => value.clone(),
+                        _ => {
+                            let variable = code::Variable::Local(index);
+                            self.load(variable, &closure, base, bytecode, start)?
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                Opcode::SetLocal => {
+                    let index = operand(1);
+                    let value = self.pop();
+                    match &mut self.locals[base + index] {
+                        Local::Captured(cell) => *cell.borrow_mut() = Some(value),
+                        local => *local = Local::Value(value),
+                    }
+                }
+                Opcode::GetFree => {
+                    let variable = code::Variable::Free(operand(1));
+                    let value = self.load(variable, &closure, base, bytecode, start)?;
+                    self.stack.push(value);
+                }
+                Opcode::Array => {
+                    let length = operand(2);
+                    let elements = self.stack.split_off(self.stack.len() - length);
+                    self.stack.push(object::Object::Array(elements));
+                }
+                Opcode::Hash => {
+                    let length = operand(2);
+                    let elements = self.stack.split_off(self.stack.len() - length * 2);
+                    let mut hash = BTreeMap::new();
+                    let mut elements = elements.into_iter();
+                    while let (Some(key), Some(value)) = (elements.next(), elements.next()) {
+                        // コンパイラは HashKey で確かめるが、読み込んだファイルでは確かめていない
+                        let hash_key =
+                            key.hash_key()
+                                .ok_or_else(|| error::EvaluatorError::UnhashableKey {
+                                    key: key.clone(),
+                                    span: span_at(&at.function, start),
+                                })?;
+                        hash.insert(hash_key, value);
+                    }
+                    self.stack.push(object::Object::Hash(hash));
+                }
+                Opcode::HashKey => {
+                    let key = self.stack.last().expect("スタックが空です");
+                    if key.hash_key().is_none() {
+                        Err(error::EvaluatorError::UnhashableKey {
+                            key: key.clone(),
+                            span: span_at(&at.function, start),
+                        })?;
+                    }
+                }
+                Opcode::Index => {
+                    let index = self.pop();
+                    let left = self.pop();
+                    let result = evaluator::Evaluator::eval_index_expression(
+                        &left,
+                        &index,
+                        span_at(&at.function, start),
+                    )?;
+                    self.stack.push(result);
+                }
+                Opcode::Call => {
+                    let num_args = operand(1);
+                    let callee = operand(2);
+                    let span = span_at(&at.function, start);
+                    match self.stack[self.stack.len() - 1 - num_args].clone() {
+                        object::Object::Closure(called) => {
+                            if called.function.num_parameters != num_args {
+                                let callee = match &bytecode.constants[callee] {
+                                    code::Constant::String(callee) => callee.clone(),
+                                    other => format!("{:?}", other),
+                                };
+                                return Err(error::EvaluatorError::ArityMismatch {
+                                    function: callee,
+                                    expected: called.function.num_parameters,
+                                    actual: num_args,
+                                    span,
+                                })?;
+                            }
+                            self.frames.push(Frame {
+                                closure: std::mem::replace(&mut closure, called),
+                                ip,
+                                base,
+                                stack_base,
+                            });
+                            at.function = closure.function.clone();
+                            ip = 0;
+                            base = self.locals.len();
+                            let args = self.stack.len() - num_args;
+                            self.locals
+                                .extend(self.stack.drain(args..).map(Local::Value));
+                            self.locals
+                                .resize_with(base + at.function.num_locals, || Local::Unbound);
+                            self.pop(); // 呼び出した関数
+                            stack_base = self.stack.len();
+                        }
+                        object::Object::Builtin(builtin) => {
+                            let args = self.stack.split_off(self.stack.len() - num_args);
+                            self.pop();
+                            let result = builtin.call(&args, span)?;
+                            self.stack.push(result);
+                        }
+                        object => Err(error::EvaluatorError::NotCallable { object, span })?,
+                    }
+                }
+                Opcode::ReturnValue => {
+                    let value = self.pop();
+                    let frame = match self.frames.pop() {
+                        Some(frame) => frame,
+                        None => return Ok(value), // トップレベルの終わり
+                    };
+                    // return で式の途中から戻る場合は、積んだままの値も取り除く
+                    self.stack.truncate(stack_base);
+                    self.locals.truncate(base);
+                    self.stack.push(value);
+                    closure = frame.closure;
+                    at.function = closure.function.clone();
+                    ip = frame.ip;
+                    base = frame.base;
+                    stack_base = frame.stack_base;
+                }
+                Opcode::Closure => {
+                    let index = operand(2);
+                    let function = match &bytecode.constants[index] {
+                        code::Constant::Function(function) => function.clone(),
+                        other => panic!("関数の定数ではありません。: {:?}", other),
+                    };
+                    let free = function
+                        .captures
+                        .iter()
+                        .map(|capture| match *capture {
+                            code::Variable::Local(index) => self.capture_local(base + index),
+                            code::Variable::Free(index) => closure.free[index].clone(),
+                            code::Variable::Global(_) => panic!("グローバル変数は捕まえません。"),
+                        })
+                        .collect();
+                    self.stack
+                        .push(object::Object::Closure(Rc::new(Closure { function, free })));
+                }
+            }
+        }
+    }
+
+    /// 変数の値を読む
+    ///
+    /// 未束縛なら fallbacks をたどって外側で同じ名前の変数を探し、グローバル変数も
+    /// 未束縛なら組み込み関数を探す (評価器が外側の環境を名前で探すのと同じ)。
+    fn load(
+        &self,
+        mut variable: code::Variable,
+        closure: &Closure,
+        base: usize,
+        bytecode: &code::Bytecode,
+        start: usize,
+    ) -> Result<object::Object, Box<dyn std::error::Error>> {
+        let function = &closure.function;
+        loop {
+            let (value, fallback, name) = match variable {
+                code::Variable::Global(index) => {
+                    if let Some(value) = &self.globals[index] {
+                        return Ok(value.clone());
+                    }
+                    let name = &bytecode.globals[index];
+                    (builtin::lookup(name), None, name)
+                }
+                code::Variable::Local(index) => {
+                    let value = match &self.locals[base + index] {
+                        Local::Unbound => None,
+                        Local::Value(value) => Some(value.clone()),
+                        Local::Captured(cell) => cell.borrow().clone(),
+                    };
+                    (
+                        value,
+                        function.local_fallbacks[index],
+                        &function.local_names[index],
+                    )
+                }
+                code::Variable::Free(index) => (
+                    closure.free[index].borrow().clone(),
+                    function.free_fallbacks[index],
+                    &function.free_names[index],
+                ),
+            };
+            match (value, fallback) {
+                (Some(value), _) => return Ok(value),
+                (None, Some(fallback)) => variable = fallback,
+                (None, None) => {
+                    return Err(error::EvaluatorError::NotFoundIdentifier {
+                        identifier: name.clone(),
+                        span: span_at(function, start),
+                    })?
+                }
+            }
+        }
+    }
+
+    /// ローカル変数をクロージャと共有するセルに移して、そのセルを返す
+    fn capture_local(&mut self, index: usize) -> Rc<RefCell<Option<object::Object>>> {
+        let cell = match std::mem::replace(&mut self.locals[index], Local::Unbound) {
+            Local::Captured(cell) => cell,
+            Local::Value(value) => Rc::new(RefCell::new(Some(value))),
+            Local::Unbound => Rc::new(RefCell::new(None)),
+        };
+        self.locals[index] = Local::Captured(cell.clone());
+        cell
+    }
+
+    fn pop(&mut self) -> object::Object {
+        self.stack.pop().expect("スタックが空です")
+    }
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Vm::new()
+    }
+}
+
+/// Program をコンパイルして新しい VM で実行する
+pub fn eval(program: &ast::Program) -> Result<object::Object, Box<dyn std::error::Error>> {
+    let bytecode = compiler::Compiler::new().compile(program)?;
+    Vm::new().run(&bytecode)
+}
+
+/// start の命令のエラーに付ける位置
+///
+/// 位置の表に無ければ仮の位置にする (`Vm::run` が位置の無いエラーに置き換える)。
+fn span_at(function: &code::CompiledFunction, start: usize) -> token::Span {
+    function.span_at(start).unwrap_or_default()
+}
+
+/// i64 の範囲で計算できれば計算する
+fn integer_operation(opcode: Opcode, left: i64, right: i64) -> Option<object::Object> {
+    let integer = match opcode {
+        Opcode::Add => left.checked_add(right),
+        Opcode::Sub => left.checked_sub(right),
+        Opcode::Mul => left.checked_mul(right),
+        Opcode::Div if right != 0 => left.checked_div(right),
+        Opcode::Equal => return Some(object::Object::Boolean(left == right)),
+        Opcode::NotEqual => return Some(object::Object::Boolean(left != right)),
+        Opcode::GreaterThan => return Some(object::Object::Boolean(left > right)),
+        Opcode::LessThan => return Some(object::Object::Boolean(left < right)),
+        _ => None,
+    };
+    integer.map(object::Object::Integer)
+}
+
+fn infix_operator(opcode: Opcode) -> operator::Infix {
+    match opcode {
+        Opcode::Add => operator::Infix::Plus,
+        Opcode::Sub => operator::Infix::Minus,
+        Opcode::Mul => operator::Infix::Asterisk,
+        Opcode::Div => operator::Infix::Slash,
+        Opcode::Equal => operator::Infix::Equal,
+        Opcode::NotEqual => operator::Infix::NotEqual,
+        Opcode::GreaterThan => operator::Infix::GreaterThan,
+        Opcode::LessThan => operator::Infix::LessThan,
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment;
+    use crate::lexer;
+    use crate::message::{self, Locale};
+    use crate::parser;
+    use std::cell::RefCell;
+
+    fn parse(input: &str) -> ast::Program {
+        let lexer = lexer::Lexer::new(input);
+        let mut parser = parser::Parser::new(lexer);
+        parser.parse_program().expect("parser error")
+    }
+
+    /// 木構造の評価器と同じ結果になるか確かめて、VM の結果を返す
+    fn test_run(input: &str) -> object::Object {
+        let program = parse(input);
+        let expected = evaluator::Evaluator::eval(
+            &program,
+            &mut Rc::new(RefCell::new(environment::Environment::new())),
+        )
+        .expect("evaluator error");
+        let actual = eval(&program).expect("vm error");
+        assert_eq!(actual.inspect(), expected.inspect(), "{}", input);
+        actual
+    }
+
+    #[test]
+    fn test_closures() {
+        message::set_locale(Locale::Ja);
+        let tests = [
+            (
+                "let newAdder = fn(a, b) { fn(c) { a + b + c } };
+                let adder = newAdder(1, 2);
+                adder(8);",
+                11,
+            ),
+            (
+                "let newAdderOuter = fn(a, b) {
+                    let c = a + b;
+                    fn(d) {
+                        let e = d + c;
+                        fn(f) { e + f; };
+                    };
+                };
+                let newAdderInner = newAdderOuter(1, 2);
+                let adder = newAdderInner(3);
+                adder(8);",
+                14,
+            ),
+            (
+                "let countDown = fn(x) { if (x == 0) { return 0; } else { countDown(x - 1); } };
+                let wrapper = fn() { countDown(1); };
+                wrapper();",
+                0,
+            ),
+            (
+                "let wrapper = fn() {
+                    let countDown = fn(x) { if (x == 0) { return 0; } else { countDown(x - 1); } };
+                    countDown(1);
+                };
+                wrapper();",
+                0,
+            ),
+            (
+                "let fibonacci = fn(x) {
+                    if (x == 0) { return 0; }
+                    if (x == 1) { return 1; }
+                    fibonacci(x - 1) + fibonacci(x - 2);
+                };
+                fibonacci(15);",
+                610,
+            ),
+            ("let f = fn() { g() }; let g = fn() { 5 }; f()", 5),
+            ("let a = 1; let f = fn() { a }; let a = 2; f()", 2),
+            ("let f = fn(a, a) { a }; f(1, 2)", 2),
+            (
+                "let f = fn(x) { let y = x * 2; if (y > 5) { let z = y; } else { let z = 0; } z }; f(3)",
+                6,
+            ),
+            // 変数は参照で捕まえるので、後から束縛し直した値が見える
+            (
+                "let f = fn() { let x = 1; let g = fn() { x }; let x = 2; g() }; f()",
+                2,
+            ),
+            (
+                "let f = fn(x) { let g = fn() { x }; let x = 5; g() }; f(1)",
+                5,
+            ),
+            (
+                "let f = fn() { let g = fn() { g }; let h = g; let g = 1; h() }; f()",
+                1,
+            ),
+            // 後で let するローカル変数は、グローバル変数ではなく関数のローカル変数を指す
+            (
+                "let f = fn() { let g = fn() { h() }; let h = fn() { 7 }; g() }; f()",
+                7,
+            ),
+            (
+                "let h = fn() { 1 }; let f = fn() { let g = fn() { h() }; let a = g(); let h = fn() { 7 }; a * 10 + g() }; f()",
+                17,
+            ),
+            // 束縛されていない変数は、外側で同じ名前の変数を探す
+            (
+                "let v = 5; let f = fn(c) { if (c) { let v = 1; }; v }; f(false) * 10 + f(true)",
+                51,
+            ),
+            (
+                "let a = 1; let f = fn(c) { if (c) { let a = 2; }; fn() { a } }; f(false)() * 10 + f(true)()",
+                12,
+            ),
+            (
+                "let f = fn(a) { fn(c) { if (c) { let a = 2; }; fn() { a } } }; f(3)(false)() * 10 + f(3)(true)()",
+                32,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                test_run(input),
+                object::Object::Integer(expected),
+                "{}",
+                input
+            );
+        }
+
+        let errors = [
+            (
+                "let f = fn(x) { if (x) { let y = 1; } y }; f(false)",
+                "1:39: 識別子が見つかりません。: y",
+            ),
+            (
+                "let f = fn() { let g = fn() { h }; g() }; let r = f(); let h = 1; r",
+                "1:31: 識別子が見つかりません。: h",
+            ),
+        ];
+        for (input, expected) in errors {
+            let program = parse(input);
+            let evaluated = evaluator::Evaluator::eval(
+                &program,
+                &mut Rc::new(RefCell::new(environment::Environment::new())),
+            )
+            .expect_err("エラーを期待しました");
+            let actual = eval(&program).expect_err("エラーを期待しました");
+            assert_eq!(evaluated.to_string(), expected, "{}", input);
+            assert_eq!(actual.to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_deep_recursion() {
+        // 評価器では Rust のスタックを使い切る深さ
+        let program = parse(
+            "let sum = fn(n, acc) { if (n == 0) { acc } else { sum(n - 1, acc + n) } };
+            sum(100000, 0)",
+        );
+        assert_eq!(
+            eval(&program).expect("vm error"),
+            object::Object::Integer(5000050000)
+        );
+    }
+
+    #[test]
+    fn test_function_object() {
+        let function = test_run("let f = fn(x) { x + 2; }; f");
+        assert_eq!(function.type_name(), "function");
+        assert_eq!(function.inspect(), "fn(x){\n(x + 2);\n}\n");
+    }
+
+    #[test]
+    fn test_error_in_function() {
+        message::set_locale(Locale::Ja);
+        let program = parse("let f = fn(x) {\n  x + true\n};\nf(1)");
+        match eval(&program) {
+            Ok(ok) => panic!("エラーを期待しましたが、{:?}でした。", ok),
+            Err(err) => assert_eq!(err.to_string(), "2:3: 型のミスマッチ: 1 + true"),
+        }
+    }
+
+    #[test]
+    fn test_globals_persist() {
+        let mut compiler = compiler::Compiler::new();
+        let mut vm = Vm::new();
+        for (input, expected) in [
+            (
+                "let a = 5; let double = fn(x) { x * 2 };",
+                object::Object::Null,
+            ),
+            ("double(a)", object::Object::Integer(10)),
+            (
+                "let len = fn(x) { 0 }; len([1])",
+                object::Object::Integer(0),
+            ),
+        ] {
+            let bytecode = compiler.compile(&parse(input)).expect("compiler error");
+            assert_eq!(vm.run(&bytecode).expect("vm error"), expected, "{}", input);
+        }
+
+        // エラーの後もスタックは空から始まる
+        let bytecode = compiler
+            .compile(&parse("a + true"))
+            .expect("compiler error");
+        assert!(vm.run(&bytecode).is_err());
+        let bytecode = compiler.compile(&parse("a")).expect("compiler error");
+        assert_eq!(
+            vm.run(&bytecode).expect("vm error"),
+            object::Object::Integer(5)
+        );
+    }
+}
@@ -0,0 +1,708 @@
+//! 木構造の評価器 (`evaluator::Evaluator`) と、それ以外の実行経路の結果を突き合わせる
+//!
+//! tests/corpus/*.monkey の各スクリプトと、乱数で作ったプログラムを全ての経路で実行し、
+//! 結果の `Object` とエラーの種類・メッセージが評価器と同じになることを確かめる。
+//! 静的解決する経路は、どこでも束縛されない名前の参照を実行前にエラーにしてよい。
+//! 乱数のシードと件数は環境変数 DIFFERENTIAL_SEED, DIFFERENTIAL_CASES で変えられる。
+
+use go_interpreter::ast;
+use go_interpreter::bytecode_file;
+use go_interpreter::compiler::Compiler;
+use go_interpreter::diagnostic::Diagnostic;
+use go_interpreter::interpreter::Interpreter;
+use go_interpreter::lexer::Lexer;
+use go_interpreter::object::Object;
+use go_interpreter::optimizer;
+use go_interpreter::parser::Parser;
+use go_interpreter::vm::{self, Vm};
+use std::error::Error;
+use std::path::Path;
+
+type Evaluated = Result<Object, Box<dyn Error>>;
+
+/// 評価器と比べる実行経路
+struct Engine {
+    name: &'static str,
+    eval: fn(&ast::Program) -> Evaluated,
+    /// 実行前に、定義されていない名前の参照をエラーにする
+    checks_names: bool,
+}
+
+/// 経路を増やしたらここに足す
+const ENGINES: [Engine; 5] = [
+    Engine {
+        name: "vm",
+        eval: vm::eval,
+        checks_names: false,
+    },
+    Engine {
+        name: "bytecode file",
+        eval: eval_via_file,
+        checks_names: false,
+    },
+    Engine {
+        name: "optimizer",
+        eval: eval_optimized,
+        checks_names: false,
+    },
+    Engine {
+        name: "optimizer + vm",
+        eval: eval_optimized_vm,
+        checks_names: false,
+    },
+    Engine {
+        name: "resolver",
+        eval: eval_resolved,
+        checks_names: true,
+    },
+];
+
+/// ファイルに書き出したバイトコードを読み込み直して実行する
+fn eval_via_file(program: &ast::Program) -> Evaluated {
+    let bytecode = Compiler::new().compile(program)?;
+    let bytecode = bytecode_file::read(&bytecode_file::write(&bytecode, true))?;
+    Vm::new().run(&bytecode)
+}
+
+fn eval_optimized(program: &ast::Program) -> Evaluated {
+    eval_tree(&optimizer::optimize(program.clone()))
+}
+
+fn eval_optimized_vm(program: &ast::Program) -> Evaluated {
+    vm::eval(&optimizer::optimize(program.clone()))
+}
+
+/// 識別子を静的解決してから、スロット番号で変数を引いて評価する
+fn eval_resolved(program: &ast::Program) -> Evaluated {
+    let mut interpreter = Interpreter::new();
+    let mut program = program.clone();
+    interpreter.resolve(&mut program)?;
+    interpreter.eval_program(&program)
+}
+
+fn eval_tree(program: &ast::Program) -> Evaluated {
+    Interpreter::new().eval_program(program)
+}
+
+fn parse(input: &str) -> ast::Program {
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer);
+    match parser.parse_program() {
+        Ok(program) => program,
+        Err(err) => panic!("構文エラー: {}\n{}", err, input),
+    }
+}
+
+/// 評価結果を比べられる形にしたもの
+#[derive(Debug)]
+enum Outcome {
+    Value(Object),
+    Error {
+        code: Option<&'static str>,
+        position: Option<String>,
+        message: String,
+    },
+}
+
+impl From<Evaluated> for Outcome {
+    fn from(evaluated: Evaluated) -> Self {
+        match evaluated {
+            Ok(object) => Outcome::Value(object),
+            Err(err) => {
+                let diagnostic = Diagnostic::from_error(err.as_ref());
+                Outcome::Error {
+                    code: diagnostic.as_ref().map(|diagnostic| diagnostic.code),
+                    position: diagnostic
+                        .and_then(|diagnostic| diagnostic.span)
+                        .map(|span| span.to_string()),
+                    message: err.to_string(),
+                }
+            }
+        }
+    }
+}
+
+fn same_outcome(expected: &Outcome, actual: &Outcome) -> bool {
+    match (expected, actual) {
+        (Outcome::Value(expected), Outcome::Value(actual)) => same_object(expected, actual),
+        (
+            Outcome::Error {
+                code,
+                position,
+                message,
+            },
+            Outcome::Error {
+                code: actual_code,
+                position: actual_position,
+                message: actual_message,
+            },
+        ) => code == actual_code && position == actual_position && message == actual_message,
+        _ => false,
+    }
+}
+
+/// 関数は経路ごとに表現が異なる (`Function` と `Closure`) ので表示で比べる
+fn same_object(expected: &Object, actual: &Object) -> bool {
+    match (expected, actual) {
+        (Object::Array(expected), Object::Array(actual)) => {
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual)
+                    .all(|(expected, actual)| same_object(expected, actual))
+        }
+        (Object::Hash(expected), Object::Hash(actual)) => {
+            expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(actual)
+                    .all(|((ek, ev), (ak, av))| ek == ak && same_object(ev, av))
+        }
+        _ if expected.type_name() == "function" => {
+            actual.type_name() == "function" && expected.inspect() == actual.inspect()
+        }
+        _ => expected == actual,
+    }
+}
+
+/// 全ての経路が評価器と同じ結果になることを確かめ、評価器の結果を返す
+fn assert_conformance(input: &str, context: &str) -> Outcome {
+    let program = parse(input);
+    let expected = Outcome::from(eval_tree(&program));
+    for engine in ENGINES.iter() {
+        let actual = Outcome::from((engine.eval)(&program));
+        let conforms = same_outcome(&expected, &actual)
+            || (engine.checks_names && never_bound(&program, &actual));
+        assert!(
+            conforms,
+            "{}: {} の結果が評価器と異なります。\n評価器: {:?}\n{}: {:?}\n--- プログラム ---\n{}",
+            context, engine.name, expected, engine.name, actual, input
+        );
+    }
+    expected
+}
+
+/// 識別子が見つからないエラーで、その名前がプログラムのどこでも束縛されないか
+///
+/// 束縛されない名前は、評価器でも実行されれば必ずエラーになる。
+fn never_bound(program: &ast::Program, outcome: &Outcome) -> bool {
+    let name = match outcome {
+        Outcome::Error {
+            code: Some("E0004"),
+            message,
+            ..
+        } => match message.rsplit_once(": ") {
+            Some((_, name)) => name,
+            None => return false,
+        },
+        _ => return false,
+    };
+    let mut names = Vec::new();
+    for statement in &program.statements {
+        bound_names(statement, &mut names);
+    }
+    !names.iter().any(|bound| bound == name)
+}
+
+/// let と関数の引数で束縛される名前を集める
+fn bound_names(statement: &ast::Statement, names: &mut Vec<String>) {
+    match &statement.kind {
+        ast::StatementKind::Let { identifier, value } => {
+            names.push(identifier.to_code());
+            expression_bound_names(value, names);
+        }
+        ast::StatementKind::Return(value) | ast::StatementKind::Expression(value) => {
+            expression_bound_names(value, names)
+        }
+        ast::StatementKind::Block(statements) => statements
+            .iter()
+            .for_each(|statement| bound_names(statement, names)),
+    }
+}
+
+fn expression_bound_names(expression: &ast::Expression, names: &mut Vec<String>) {
+    match &expression.kind {
+        ast::ExpressionKind::PrefixExpression { expression, .. } => {
+            expression_bound_names(expression, names)
+        }
+        ast::ExpressionKind::InfixExpression { left, right, .. }
+        | ast::ExpressionKind::Index {
+            left, index: right, ..
+        } => {
+            expression_bound_names(left, names);
+            expression_bound_names(right, names);
+        }
+        ast::ExpressionKind::IfExpression {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            expression_bound_names(condition, names);
+            bound_names(consequence, names);
+            if let Some(alternative) = alternative {
+                bound_names(alternative, names);
+            }
+        }
+        ast::ExpressionKind::Function {
+            parameters, body, ..
+        } => {
+            names.extend(parameters.iter().map(|parameter| parameter.to_code()));
+            bound_names(body, names);
+        }
+        ast::ExpressionKind::Call { function, args } => {
+            expression_bound_names(function, names);
+            args.iter()
+                .for_each(|arg| expression_bound_names(arg, names));
+        }
+        ast::ExpressionKind::Array(elements) => elements
+            .iter()
+            .for_each(|element| expression_bound_names(element, names)),
+        ast::ExpressionKind::Hash(pairs) => pairs.iter().for_each(|(key, value)| {
+            expression_bound_names(key, names);
+            expression_bound_names(value, names);
+        }),
+        _ => {}
+    }
+}
+
+/// 先頭のコメント `// expect: <表示>` か `// error: <コード>` を期待する結果として読む
+fn expectation(source: &str) -> Option<(&str, &str)> {
+    let line = source.lines().next()?.strip_prefix("//")?.trim();
+    let (kind, value) = line.split_once(':')?;
+    Some((kind.trim(), value.trim()))
+}
+
+#[test]
+fn test_corpus() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut paths = std::fs::read_dir(&directory)
+        .expect("tests/corpus を読めません")
+        .map(|entry| entry.expect("tests/corpus を読めません").path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "monkey")
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "tests/corpus にスクリプトがありません");
+
+    for path in paths {
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        let source = std::fs::read_to_string(&path).expect("スクリプトを読めません");
+        let outcome = assert_conformance(&source, &name);
+        match (expectation(&source), &outcome) {
+            (Some(("expect", expected)), Outcome::Value(object)) => {
+                assert_eq!(object.inspect(), expected, "{}", name)
+            }
+            (Some(("error", expected)), Outcome::Error { code, .. }) => {
+                assert_eq!(code.as_deref(), Some(expected), "{}", name)
+            }
+            (expectation, outcome) => panic!(
+                "{}: {:?} を期待しましたが、{:?}でした。",
+                name, expectation, outcome
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_random_programs() {
+    let seed = env_number("DIFFERENTIAL_SEED").unwrap_or(0x5eed);
+    let cases = env_number("DIFFERENTIAL_CASES").unwrap_or(300);
+    for case in 0..cases {
+        // 失敗したケースだけを DIFFERENTIAL_SEED と DIFFERENTIAL_CASES=1 で再現できるようにする
+        let case_seed = seed.wrapping_add(case);
+        let program = Generator::new(case_seed).program();
+        assert_conformance(&program, &format!("DIFFERENTIAL_SEED={}", case_seed));
+    }
+}
+
+fn env_number(name: &str) -> Option<u64> {
+    std::env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+}
+
+/// 乱数でプログラムを作る
+///
+/// 多くのプログラムが最後まで評価されるよう、型を決めて式を組み立てる。
+/// 一部はわざと型を無視して、エラーの経路も比べる。
+/// 必ず停止するように、関数からは先に定義された関数しか呼ばない (再帰しない)。
+/// ただし関数の中では、後で let する関数を先に参照する関数も作る (呼ぶのは両方の定義の後)。
+/// 関数の中では、同じ名前の let し直しや if の中での let も作る。
+struct Generator {
+    state: u64,
+    scopes: Vec<Vec<Binding>>,
+    next_name: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Type {
+    Number,
+    Boolean,
+    String,
+    Array,
+}
+
+const TYPES: [Type; 4] = [Type::Number, Type::Boolean, Type::String, Type::Array];
+
+#[derive(Clone)]
+enum Binding {
+    Value(String, Type),
+    Function(String, Vec<Type>, Type), // 名前・引数の型・戻り値の型
+}
+
+impl Generator {
+    fn new(seed: u64) -> Self {
+        Generator {
+            // xorshift は 0 から抜け出せないので混ぜておく
+            state: seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1,
+            scopes: vec![Vec::new()],
+            next_name: 0,
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn choose<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())].clone()
+    }
+
+    /// 数字は識別子に使えないので、英小文字で番号を表す
+    fn fresh_name(&mut self, prefix: char) -> String {
+        let mut name = prefix.to_string();
+        let mut n = self.next_name;
+        self.next_name += 1;
+        loop {
+            name.push((b'a' + (n % 26) as u8) as char);
+            n /= 26;
+            if n == 0 {
+                break name;
+            }
+        }
+    }
+
+    fn bind(&mut self, binding: Binding) {
+        self.scopes.last_mut().unwrap().push(binding);
+    }
+
+    fn variables(&self, ty: Type) -> Vec<String> {
+        self.scopes
+            .iter()
+            .flatten()
+            .filter_map(|binding| match binding {
+                Binding::Value(name, value_type) if *value_type == ty => Some(name.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn functions(&self, ty: Type) -> Vec<(String, Vec<Type>)> {
+        self.scopes
+            .iter()
+            .flatten()
+            .filter_map(|binding| match binding {
+                Binding::Function(name, parameters, returns) if *returns == ty => {
+                    Some((name.clone(), parameters.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn program(&mut self) -> String {
+        let mut lines = Vec::new();
+        for _ in 0..1 + self.below(5) {
+            lines.push(self.let_statement(0));
+        }
+        let ty = self.choose(&TYPES);
+        lines.push(self.expression(ty, 3));
+        lines.join("\n")
+    }
+
+    fn let_statement(&mut self, depth: usize) -> String {
+        if self.chance(40) {
+            let parameters = (0..self.below(3))
+                .map(|_| self.choose(&TYPES))
+                .collect::<Vec<_>>();
+            let returns = self.choose(&TYPES);
+            let function = self.function(&parameters, returns, depth);
+            let name = self.fresh_name('g');
+            self.bind(Binding::Function(name.clone(), parameters, returns));
+            format!("let {} = {};", name, function)
+        } else {
+            let ty = self.choose(&TYPES);
+            let value = self.expression(ty, 2);
+            let name = self.fresh_name('v');
+            self.bind(Binding::Value(name.clone(), ty));
+            format!("let {} = {};", name, value)
+        }
+    }
+
+    fn function(&mut self, parameters: &[Type], returns: Type, depth: usize) -> String {
+        let mut names = Vec::new();
+        self.scopes.push(Vec::new());
+        for ty in parameters {
+            let name = self.fresh_name('p');
+            self.bind(Binding::Value(name.clone(), *ty));
+            names.push(name);
+        }
+        let mut body = Vec::new();
+        if depth < 2 {
+            for _ in 0..self.below(3) {
+                body.push(self.local_statement(depth + 1));
+            }
+        }
+        if self.chance(30) {
+            let condition = self.expression(Type::Boolean, 1);
+            let value = self.expression(returns, 1);
+            body.push(format!("if ({}) {{ return {}; }};", condition, value));
+        }
+        body.push(self.expression(returns, 2));
+        self.scopes.pop();
+        format!("fn({}) {{ {} }}", names.join(", "), body.join(" "))
+    }
+
+    /// 関数の中の文
+    fn local_statement(&mut self, depth: usize) -> String {
+        match self.below(10) {
+            0 | 1 => self.rebinding(depth),
+            2 => self.conditional_let(),
+            3 => self.forward_functions(depth),
+            _ => self.let_statement(depth),
+        }
+    }
+
+    /// 見えている変数を同じ型の値で let し直す (外側の変数なら、この関数の中で隠す)
+    ///
+    /// その前に変数を読むクロージャを作ることもある (クロージャからは新しい値が見える)。
+    fn rebinding(&mut self, depth: usize) -> String {
+        let ty = self.choose(&TYPES);
+        let variables = self.variables(ty);
+        if variables.is_empty() {
+            return self.let_statement(depth);
+        }
+        let name = self.choose(&variables);
+        let value = self.expression(ty, 2);
+        let rebinding = format!("let {} = {};", name, value);
+        if self.chance(50) {
+            return rebinding;
+        }
+        let reader = self.fresh_name('g');
+        self.bind(Binding::Function(reader.clone(), Vec::new(), ty));
+        format!("let {} = fn() {{ {} }}; {}", reader, name, rebinding)
+    }
+
+    /// 条件が偽なら束縛されない let (束縛されなければ外側の同じ名前を探す)
+    fn conditional_let(&mut self) -> String {
+        let ty = self.choose(&TYPES);
+        let condition = self.expression(Type::Boolean, 1);
+        let value = self.expression(ty, 1);
+        let variables = self.variables(ty);
+        let name = if !variables.is_empty() && self.chance(70) {
+            self.choose(&variables)
+        } else {
+            // どこにも無い名前なら、条件が偽のときに読むとエラーになる
+            let name = self.fresh_name('v');
+            self.bind(Binding::Value(name.clone(), ty));
+            name
+        };
+        format!("if ({}) {{ let {} = {}; }};", condition, name, value)
+    }
+
+    /// 後で let する関数を先に参照する関数を作る (`let a = fn() { b() }; let b = fn() { .. };`)
+    ///
+    /// 後の関数は先の関数より前に作るので、互いに呼び合うことはない。
+    fn forward_functions(&mut self, depth: usize) -> String {
+        let parameters = (0..self.below(3))
+            .map(|_| self.choose(&TYPES))
+            .collect::<Vec<_>>();
+        let returns = self.choose(&TYPES);
+        let later = self.function(&parameters, returns, depth);
+        let later_name = self.fresh_name('g');
+        self.bind(Binding::Function(
+            later_name.clone(),
+            parameters.clone(),
+            returns,
+        ));
+        let call = self.call(later_name.clone(), parameters, 1);
+        let first_name = self.fresh_name('g');
+        self.bind(Binding::Function(first_name.clone(), Vec::new(), returns));
+        format!(
+            "let {} = fn() {{ {} }}; let {} = {};",
+            first_name, call, later_name, later
+        )
+    }
+
+    /// ty 型の式 (ごくまれに型を無視した式になる)
+    fn expression(&mut self, ty: Type, depth: usize) -> String {
+        let ty = if self.below(200) == 0 {
+            self.choose(&TYPES)
+        } else {
+            ty
+        };
+        if depth == 0 || self.chance(20) {
+            return self.atom(ty);
+        }
+        let depth = depth - 1;
+        match self.below(10) {
+            0 => {
+                let condition = self.expression(Type::Boolean, depth);
+                let consequence = self.expression(ty, depth);
+                let alternative = self.expression(ty, depth);
+                format!(
+                    "if ({}) {{ {} }} else {{ {} }}",
+                    condition, consequence, alternative
+                )
+            }
+            1 => self.call_function(ty, depth),
+            2 => {
+                // 添字はまれに範囲外になる
+                let elements = (0..1 + self.below(3))
+                    .map(|_| self.expression(ty, depth))
+                    .collect::<Vec<_>>();
+                let out_of_range = self.chance(5) as usize;
+                let index = self.below(elements.len() + out_of_range);
+                format!("[{}][{}]", elements.join(", "), index)
+            }
+            3 => {
+                let key = self.choose(&["\"a\"", "\"b\"", "1", "true"]);
+                let value = self.expression(ty, depth);
+                let other = self.expression(ty, depth);
+                let lookup = self.choose(&[key, "\"c\""]);
+                format!("{{{}: {}, \"c\": {}}}[{}]", key, value, other, lookup)
+            }
+            _ => match ty {
+                Type::Number => self.number(depth),
+                Type::Boolean => self.boolean(depth),
+                Type::String => {
+                    let left = self.expression(Type::String, depth);
+                    let right = self.expression(Type::String, depth);
+                    format!("({} + {})", left, right)
+                }
+                Type::Array => self.array(depth),
+            },
+        }
+    }
+
+    fn number(&mut self, depth: usize) -> String {
+        match self.below(4) {
+            0 => format!("(-{})", self.expression(Type::Number, depth)),
+            1 => {
+                let ty = self.choose(&[Type::String, Type::Array]);
+                format!("len({})", self.expression(ty, depth))
+            }
+            _ => {
+                let operator = self.choose(&["+", "-", "*", "/"]);
+                let left = self.expression(Type::Number, depth);
+                let right = self.expression(Type::Number, depth);
+                format!("({} {} {})", left, operator, right)
+            }
+        }
+    }
+
+    fn boolean(&mut self, depth: usize) -> String {
+        match self.below(3) {
+            0 => format!("(!{})", self.expression(Type::Boolean, depth)),
+            1 => {
+                let operator = self.choose(&["<", ">", "==", "!="]);
+                let left = self.expression(Type::Number, depth);
+                let right = self.expression(Type::Number, depth);
+                format!("({} {} {})", left, operator, right)
+            }
+            _ => {
+                let ty = self.choose(&[Type::Boolean, Type::String]);
+                let operator = self.choose(&["==", "!="]);
+                let left = self.expression(ty, depth);
+                let right = self.expression(ty, depth);
+                format!("({} {} {})", left, operator, right)
+            }
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> String {
+        match self.below(3) {
+            0 => format!("rest({})", self.expression(Type::Array, depth)),
+            1 => {
+                let array = self.expression(Type::Array, depth);
+                let ty = self.choose(&TYPES);
+                let element = self.expression(ty, depth);
+                format!("push({}, {})", array, element)
+            }
+            _ => {
+                let elements = (0..self.below(4))
+                    .map(|_| {
+                        let ty = self.choose(&TYPES);
+                        self.expression(ty, depth)
+                    })
+                    .collect::<Vec<_>>();
+                format!("[{}]", elements.join(", "))
+            }
+        }
+    }
+
+    /// 定義済みの関数 (なければ即時に呼ぶ関数リテラル) を呼ぶ。引数の数はときどき間違える
+    fn call_function(&mut self, ty: Type, depth: usize) -> String {
+        let functions = self.functions(ty);
+        let (callee, parameters) = if functions.is_empty() || self.chance(20) {
+            let parameters = (0..self.below(2))
+                .map(|_| self.choose(&TYPES))
+                .collect::<Vec<_>>();
+            (self.function(&parameters, ty, 2), parameters)
+        } else {
+            self.choose(&functions)
+        };
+        self.call(callee, parameters, depth)
+    }
+
+    fn call(&mut self, callee: String, mut parameters: Vec<Type>, depth: usize) -> String {
+        if self.chance(3) {
+            parameters.push(Type::Number);
+        }
+        let arguments = parameters
+            .iter()
+            .map(|ty| self.expression(*ty, depth))
+            .collect::<Vec<_>>();
+        format!("{}({})", callee, arguments.join(", "))
+    }
+
+    fn atom(&mut self, ty: Type) -> String {
+        let variables = self.variables(ty);
+        if !variables.is_empty() && self.chance(50) {
+            return self.choose(&variables);
+        }
+        if self.below(300) == 0 {
+            return "undefined_name".to_string();
+        }
+        match ty {
+            Type::Number => match self.below(10) {
+                0 => self
+                    .choose(&["9223372036854775807", "4611686018427387904"])
+                    .to_string(),
+                1 => format!("{}.5", self.below(4)),
+                _ => self.below(10).to_string(),
+            },
+            Type::Boolean => self.choose(&["true", "false"]).to_string(),
+            Type::String => self.choose(&["\"a\"", "\"bc\"", "\"\""]).to_string(),
+            Type::Array => {
+                let elements = (0..self.below(4))
+                    .map(|_| self.below(10).to_string())
+                    .collect::<Vec<_>>();
+                format!("[{}]", elements.join(", "))
+            }
+        }
+    }
+}